@@ -1205,6 +1205,7 @@ pub fn progressive_strings(input: &str) -> Vec<String> {
             Item::Data(data) => lines.push(vec![data.span().as_str(&inputs, |s| s.into())]),
             Item::Module(items) => lines.push(vec![items.span.as_str(&inputs, |s| s.into())]),
             Item::Import(import) => lines.push(vec![import.span().as_str(&inputs, |s| s.into())]),
+            Item::Error(span) => lines.push(vec![span.as_str(&inputs, |s| s.into())]),
         }
     }
     let mut strings = Vec::new();
@@ -478,6 +478,7 @@ pub fn switch(
             return Err(env.error("Stack was empty when discarding excess switch arguments."));
         }
         env.rt.stack.drain(discard_start..discard_end);
+        env.record_branch(i);
         env.exec(f)?;
     } else {
         // Array
@@ -543,6 +544,7 @@ pub fn switch(
                     // println!("  elem: {}", elem);
                     let node = &branches[elem];
                     let arg_count = args[elem];
+                    env.record_branch(elem);
                     for (i, row) in rows_to_sel.iter_mut().rev().enumerate().rev() {
                         let row = match row {
                             Ok(row) => row.next().unwrap(),
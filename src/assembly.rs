@@ -1,4 +1,5 @@
 use std::{
+    collections::HashMap,
     fmt,
     hash::{DefaultHasher, Hash, Hasher},
     ops::{Index, IndexMut},
@@ -31,6 +32,8 @@ pub struct Assembly {
     pub inputs: Inputs,
     pub(crate) dynamic_functions: EcoVec<DynFn>,
     pub(crate) test_assert_count: usize,
+    /// The names of test scopes, keyed by span index
+    pub(crate) test_scope_names: HashMap<usize, EcoString>,
 }
 
 /// A Uiua function
@@ -249,6 +252,7 @@ impl Assembly {
             },
             dynamic_functions: EcoVec::new(),
             test_assert_count: 0,
+            test_scope_names: HashMap::new(),
         })
     }
     /// Serialize the assembly into a `.uasm` file
@@ -346,6 +350,7 @@ impl Default for Assembly {
             dynamic_functions: EcoVec::new(),
             inputs: Inputs::default(),
             test_assert_count: 0,
+            test_scope_names: HashMap::new(),
         }
     }
 }
@@ -416,6 +421,83 @@ pub struct DocComment {
     pub sig: Option<DocCommentSig>,
 }
 
+impl DocComment {
+    /// Get the code examples in the comment
+    ///
+    /// Examples are lines that start with `ex:`, or `ex!` if the example should fail.
+    /// Following lines that start with `:` continue the example.
+    pub fn examples(&self) -> Vec<DocCommentExample> {
+        let mut examples: Vec<DocCommentExample> = Vec::new();
+        let mut continuable = false;
+        for (i, line) in self.text.lines().enumerate() {
+            match ExampleLine::parse(line) {
+                Some(ExampleLine::Start { code, should_error }) => {
+                    continuable = true;
+                    examples.push(DocCommentExample {
+                        code: code.into(),
+                        should_error,
+                        line: i,
+                        lines: 1,
+                    });
+                }
+                Some(ExampleLine::Continue(code)) if continuable => {
+                    let example = examples.last_mut().unwrap();
+                    example.code.push('\n');
+                    example.code.push_str(code);
+                    example.lines += 1;
+                }
+                _ => continuable = false,
+            }
+        }
+        examples
+    }
+}
+
+/// A line of a code example in a doc comment
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ExampleLine<'a> {
+    /// The first line of an example
+    Start { code: &'a str, should_error: bool },
+    /// A line that continues the previous example
+    Continue(&'a str),
+}
+
+impl<'a> ExampleLine<'a> {
+    /// Parse a line of doc comment text
+    ///
+    /// The returned code is always a suffix of the line
+    pub(crate) fn parse(line: &'a str) -> Option<Self> {
+        let strip = |code: &'a str| code.strip_prefix(' ').unwrap_or(code);
+        if let Some(code) = line.strip_prefix("ex:") {
+            Some(ExampleLine::Start {
+                code: strip(code),
+                should_error: false,
+            })
+        } else if let Some(code) = line.strip_prefix("ex!") {
+            Some(ExampleLine::Start {
+                code: strip(code),
+                should_error: true,
+            })
+        } else {
+            line.strip_prefix(':')
+                .map(|code| ExampleLine::Continue(strip(code)))
+        }
+    }
+}
+
+/// A code example in a doc comment
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocCommentExample {
+    /// The example code
+    pub code: String,
+    /// Whether the example is expected to fail
+    pub should_error: bool,
+    /// The index of the example's first line in the comment text
+    pub line: usize,
+    /// The number of comment lines the example spans
+    pub lines: usize,
+}
+
 /// A signature in a doc comment
 #[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct DocCommentSig {
@@ -25,6 +25,8 @@ pub enum Item {
     Module(Sp<ScopedModule>),
     /// A data definition
     Data(DataDef),
+    /// Code that could not be parsed
+    Error(CodeSpan),
 }
 
 /// A binding
@@ -0,0 +1,302 @@
+//! Benchmarking with warmup, adaptive iteration counts, and baseline comparison
+
+use std::{fmt::Write, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{algorithm::get_ops, InputSrc, Ops, Span, Uiua, UiuaResult, Value};
+
+/// How long to run a benchmark before measuring it, in seconds
+const WARMUP_TIME: f64 = 0.1;
+/// How long to spend measuring a benchmark, in seconds
+const MEASURE_TIME: f64 = 1.0;
+/// The minimum number of samples to take
+const MIN_SAMPLES: usize = 10;
+/// The maximum number of samples to take
+const MAX_SAMPLES: usize = 100;
+
+/// The format of a benchmark report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BenchFormat {
+    /// Human-readable output
+    #[default]
+    Pretty,
+    /// A JSON object
+    Json,
+}
+
+impl FromStr for BenchFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(BenchFormat::Pretty),
+            "json" => Ok(BenchFormat::Json),
+            _ => Err(format!("unknown bench format `{s}`")),
+        }
+    }
+}
+
+/// The result of a benchmark
+///
+/// All times are in seconds per iteration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BenchResult {
+    /// The name of the benchmark
+    pub name: String,
+    /// The file that contains the benchmark
+    pub file: Option<String>,
+    /// The line of the benchmark
+    pub line: usize,
+    /// The number of samples taken
+    pub samples: usize,
+    /// The number of iterations in each sample
+    pub iterations: usize,
+    /// The mean time
+    pub mean: f64,
+    /// The median time
+    pub median: f64,
+    /// The standard deviation of the time
+    pub stddev: f64,
+    /// The minimum time
+    pub min: f64,
+    /// The maximum time
+    pub max: f64,
+    /// The number of samples outside of the inner fences
+    pub outliers: usize,
+}
+
+impl BenchResult {
+    fn new(name: String, span: &Span, iterations: usize, mut times: Vec<f64>) -> Self {
+        times.sort_by(|a, b| a.total_cmp(b));
+        let n = times.len() as f64;
+        let mean = times.iter().sum::<f64>() / n;
+        let variance = times.iter().map(|t| (t - mean).powi(2)).sum::<f64>() / n;
+        let quantile = |q: f64| {
+            let i = q * (times.len() - 1) as f64;
+            let (lo, hi) = (times[i.floor() as usize], times[i.ceil() as usize]);
+            lo + (hi - lo) * i.fract()
+        };
+        // Tukey's fences
+        let (q1, q3) = (quantile(0.25), quantile(0.75));
+        let iqr = q3 - q1;
+        let (low, high) = (q1 - 1.5 * iqr, q3 + 1.5 * iqr);
+        let (file, line) = match span {
+            Span::Code(span) => (
+                match &span.src {
+                    InputSrc::File(path) => Some(path.to_string_lossy().into_owned()),
+                    _ => None,
+                },
+                span.start.line as usize,
+            ),
+            Span::Builtin => (None, 0),
+        };
+        BenchResult {
+            name,
+            file,
+            line,
+            samples: times.len(),
+            iterations,
+            mean,
+            median: quantile(0.5),
+            stddev: variance.sqrt(),
+            min: times[0],
+            max: times[times.len() - 1],
+            outliers: times.iter().filter(|&&t| t < low || t > high).count(),
+        }
+    }
+}
+
+/// A comparison between a benchmark and its baseline
+#[derive(Debug, Clone)]
+pub struct BenchComparison {
+    /// The baseline mean time
+    pub baseline: f64,
+    /// The relative change in mean time
+    pub change: f64,
+    /// Whether the change is a regression
+    pub regressed: bool,
+}
+
+/// Compare benchmark results against a baseline
+///
+/// A benchmark regresses if its mean time increased by more than `threshold`,
+/// given as a fraction of the baseline mean.
+/// Benchmarks whose baseline mean is zero are not compared.
+pub fn compare(
+    results: &[BenchResult],
+    baseline: &[BenchResult],
+    threshold: f64,
+) -> Vec<Option<BenchComparison>> {
+    (results.iter())
+        .map(|res| {
+            let base = (baseline.iter().find(|base| base.name == res.name))
+                .filter(|base| base.mean > 0.0)?;
+            let change = (res.mean - base.mean) / base.mean;
+            Some(BenchComparison {
+                baseline: base.mean,
+                change,
+                regressed: change > threshold,
+            })
+        })
+        .collect()
+}
+
+/// Serialize benchmark results so they can be used as a baseline
+pub fn json_report(results: &[BenchResult]) -> String {
+    #[derive(Serialize)]
+    struct JsonReport<'a> {
+        benchmarks: &'a [BenchResult],
+    }
+    serde_json::to_string_pretty(&JsonReport {
+        benchmarks: results,
+    })
+    .unwrap()
+}
+
+/// Parse benchmark results that were saved with [`json_report`]
+pub fn parse_baseline(json: &str) -> Result<Vec<BenchResult>, String> {
+    #[derive(Deserialize)]
+    struct JsonReport {
+        benchmarks: Vec<BenchResult>,
+    }
+    serde_json::from_str::<JsonReport>(json)
+        .map(|report| report.benchmarks)
+        .map_err(|e| e.to_string())
+}
+
+/// Render benchmark results as a table
+pub fn pretty_report(results: &[BenchResult], comparisons: &[Option<BenchComparison>]) -> String {
+    let mut rows = vec![vec![
+        "Benchmark".into(),
+        "Mean".into(),
+        "± Stddev".into(),
+        "Median".into(),
+        "Min".into(),
+        "Max".into(),
+        "Runs".into(),
+        "Outliers".into(),
+    ]];
+    if !comparisons.is_empty() {
+        rows[0].extend(["Baseline".into(), "Change".into()]);
+    }
+    for (i, res) in results.iter().enumerate() {
+        let mut row = vec![
+            res.name.clone(),
+            format_time(res.mean),
+            format_time(res.stddev),
+            format_time(res.median),
+            format_time(res.min),
+            format_time(res.max),
+            format!("{}×{}", res.samples, res.iterations),
+            res.outliers.to_string(),
+        ];
+        match comparisons.get(i) {
+            Some(Some(comp)) => {
+                row.push(format_time(comp.baseline));
+                row.push(format!(
+                    "{:+.1}%{}",
+                    comp.change * 100.0,
+                    if comp.regressed { " regressed" } else { "" }
+                ));
+            }
+            Some(None) => row.extend(["-".into(), "new".into()]),
+            None => {}
+        }
+        rows.push(row);
+    }
+    let widths: Vec<usize> = (0..rows[0].len())
+        .map(|i| (rows.iter().map(|row| row[i].chars().count()).max()).unwrap_or(0))
+        .collect();
+    let mut report = String::new();
+    for row in rows {
+        let mut line = String::new();
+        for (cell, width) in row.iter().zip(&widths) {
+            line.push_str(cell);
+            line.push_str(&" ".repeat(width - cell.chars().count() + 2));
+        }
+        _ = writeln!(report, "{}", line.trim_end());
+    }
+    report
+}
+
+/// Format a time in seconds with an appropriate unit
+fn format_time(secs: f64) -> String {
+    let (value, unit) = if secs >= 1.0 {
+        (secs, "s")
+    } else if secs >= 1e-3 {
+        (secs * 1e3, "ms")
+    } else if secs >= 1e-6 {
+        (secs * 1e6, "µs")
+    } else {
+        (secs * 1e9, "ns")
+    };
+    format!("{value:.2}{unit}")
+}
+
+/// Benchmark a function
+///
+/// When not benchmarking, the function is simply called once.
+pub(crate) fn bench(ops: Ops, env: &mut Uiua) -> UiuaResult {
+    let [f] = get_ops(ops, env)?;
+    let name = (env.pop("benchmark name")?).as_string(env, "Benchmark name must be a string")?;
+    let args = (0..f.sig.args)
+        .map(|i| env.pop(i + 1))
+        .collect::<UiuaResult<Vec<Value>>>()?;
+    let height = env.stack_height();
+    let run = |env: &mut Uiua| -> UiuaResult {
+        for arg in args.iter().rev() {
+            env.push(arg.clone());
+        }
+        env.exec(f.clone())?;
+        env.truncate_stack(height);
+        Ok(())
+    };
+    if !env.rt.benchmarking {
+        return run(env);
+    }
+    let span = env.span();
+    // Warm up and estimate the time of one iteration
+    let start = env.rt.backend.now();
+    let mut warmup_iters = 0;
+    while warmup_iters == 0 || env.rt.backend.now() - start < WARMUP_TIME {
+        run(env)?;
+        warmup_iters += 1;
+    }
+    let estimate = (env.rt.backend.now() - start) / warmup_iters as f64;
+    // Fast functions are run many times per sample so that timer resolution does not matter,
+    // while slow functions take fewer samples
+    let samples = ((MEASURE_TIME / estimate) as usize).clamp(MIN_SAMPLES, MAX_SAMPLES);
+    let iterations = ((MEASURE_TIME / samples as f64 / estimate) as usize).max(1);
+    let mut times = Vec::with_capacity(samples);
+    for _ in 0..samples {
+        let start = env.rt.backend.now();
+        for _ in 0..iterations {
+            run(env)?;
+        }
+        times.push((env.rt.backend.now() - start) / iterations as f64);
+    }
+    (env.rt.bench_results).push(BenchResult::new(name, &span, iterations, times));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bench() {
+        let code = "# Experimental!\nbench(+1) \"inc\" 5";
+        let mut env = Uiua::with_safe_sys().with_benchmarking(true);
+        env.run_str(code).unwrap();
+        let results = env.take_bench_results();
+        assert_eq!(results[0].name, "inc");
+        assert!(results[0].min <= results[0].median && results[0].median <= results[0].max);
+        let comparisons = compare(&results, &results, 0.1);
+        assert!(comparisons[0].as_ref().is_some_and(|comp| !comp.regressed));
+        let baseline = parse_baseline(&json_report(&results)).unwrap();
+        assert_eq!(baseline[0].mean, results[0].mean);
+        let mut zero = results.clone();
+        zero[0].mean = 0.0;
+        assert!(compare(&results, &zero, 0.1)[0].is_none());
+    }
+}
@@ -408,6 +408,10 @@ impl VirtualEnv {
                     let [sig] = get_args(args)?;
                     self.handle_args_outputs(sig.args, 1);
                 }
+                Bench => {
+                    let [sig] = get_args(args)?;
+                    self.handle_args_outputs(sig.args + 1, 0);
+                }
                 Repeat => {
                     let [f] = get_args_nodes(args)?;
                     let n = self.pop();
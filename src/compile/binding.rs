@@ -215,7 +215,8 @@ impl Compiler {
                     if let Span::Code(dup_span) = comp.get_span(*span) {
                         if let Ok(rest_sig) = nodes_sig(rest) {
                             if rest_sig.args == sig.args && rest_sig.outputs + 1 == sig.outputs {
-                                comp.emit_diagnostic(
+                                comp.emit_lint(
+                                    "redundant-stack-op",
                                     "Functions should consume their arguments. \
                                         Try removing this.",
                                     DiagnosticKind::Style,
@@ -375,62 +376,84 @@ impl Compiler {
         prev_com: Option<EcoString>,
     ) -> UiuaResult {
         let m = m.value;
-        let scope_kind = match &m.kind {
-            ModuleKind::Named(name) => ScopeKind::Module(name.value.clone()),
-            ModuleKind::Test => ScopeKind::Test,
+        let ModuleKind::Named(name) = m.kind else {
+            if let Some(line) = &m.imports {
+                self.add_error(
+                    line.tilde_span.clone(),
+                    "Items cannot be imported from test modules",
+                );
+            }
+            let name = test_scope_name(&m.items)
+                .unwrap_or_else(|| format!("test at line {}", m.open_span.start.line).into());
+            self.test_scope(name, m.open_span, |comp| {
+                comp.items(m.items, false)?;
+                comp.end_enum()
+            })?;
+            return Ok(());
         };
-        let (module, ()) = self.in_scope(scope_kind, |comp| {
+        let (module, ()) = self.in_scope(ScopeKind::Module(name.value.clone()), |comp| {
             comp.items(m.items, false)?;
             comp.end_enum()?;
             Ok(())
         })?;
-        match m.kind {
-            ModuleKind::Named(name) => {
-                // Add imports
-                if let Some(line) = m.imports {
-                    for item in line.items {
-                        if let Some(mut local) = module.names.get(&item.value).copied() {
-                            local.public = false;
-                            (self.code_meta.global_references)
-                                .insert(item.span.clone(), local.index);
-                            self.scope.names.insert(item.value, local);
-                        } else {
-                            self.add_error(
-                                item.span.clone(),
-                                format!("{} does not exist in {}", item.value, name.value),
-                            );
-                        }
-                    }
-                }
-                // Add global
-                let global_index = self.next_global;
-                self.next_global += 1;
-                let local = LocalName {
-                    index: global_index,
-                    public: true,
-                };
-                let comment = prev_com
-                    .or_else(|| module.comment.clone())
-                    .map(|text| DocComment::from(text.as_str()));
-                self.asm.add_binding_at(
-                    local,
-                    BindingKind::Module(module),
-                    Some(name.span.clone()),
-                    comment,
-                );
-                // Add local
-                self.scope.names.insert(name.value.clone(), local);
-                (self.code_meta.global_references).insert(name.span.clone(), local.index);
-            }
-            ModuleKind::Test => {
-                if let Some(line) = &m.imports {
+        // Add imports
+        if let Some(line) = m.imports {
+            for item in line.items {
+                if let Some(mut local) = module.names.get(&item.value).copied() {
+                    local.public = false;
+                    (self.code_meta.global_references).insert(item.span.clone(), local.index);
+                    self.scope.names.insert(item.value, local);
+                } else {
                     self.add_error(
-                        line.tilde_span.clone(),
-                        "Items cannot be imported from test modules",
+                        item.span.clone(),
+                        format!("{} does not exist in {}", item.value, name.value),
                     );
                 }
             }
         }
+        // Add global
+        let global_index = self.next_global;
+        self.next_global += 1;
+        let local = LocalName {
+            index: global_index,
+            public: true,
+        };
+        let comment = prev_com
+            .or_else(|| module.comment.clone())
+            .map(|text| DocComment::from(text.as_str()));
+        self.asm.add_binding_at(
+            local,
+            BindingKind::Module(module),
+            Some(name.span.clone()),
+            comment,
+        );
+        // Add local
+        self.scope.names.insert(name.value.clone(), local);
+        (self.code_meta.global_references).insert(name.span.clone(), local.index);
+        Ok(())
+    }
+    /// Compile a test scope
+    ///
+    /// Test scopes whose names do not match the test filter are skipped
+    pub(super) fn test_scope(
+        &mut self,
+        name: EcoString,
+        span: CodeSpan,
+        f: impl FnOnce(&mut Self) -> UiuaResult,
+    ) -> UiuaResult {
+        if (self.test_filter.as_ref()).is_some_and(|filter| !name.contains(filter.as_str())) {
+            return Ok(());
+        }
+        let markers = self.mode != RunMode::Normal;
+        let span = self.add_span(span);
+        if markers {
+            self.asm.test_scope_names.insert(span, name);
+            (self.asm.root).push(Node::ImplPrim(ImplPrimitive::BeginTest, span));
+        }
+        self.in_scope(ScopeKind::Test, f)?;
+        if markers {
+            (self.asm.root).push(Node::ImplPrim(ImplPrimitive::EndTest, span));
+        }
         Ok(())
     }
     pub(super) fn import(
@@ -569,3 +592,18 @@ impl Compiler {
         }
     }
 }
+
+/// Get the name of a test scope from its first comment
+pub(crate) fn test_scope_name(items: &[Item]) -> Option<EcoString> {
+    let Some(Item::Words(lines)) = items.first() else {
+        return None;
+    };
+    let first = lines
+        .iter()
+        .flatten()
+        .find(|word| !matches!(word.value, Word::Spaces))?;
+    match &first.value {
+        Word::Comment(comment) if !comment.trim().is_empty() => Some(comment.trim().into()),
+        _ => None,
+    }
+}
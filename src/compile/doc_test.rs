@@ -0,0 +1,241 @@
+//! Compiling doc comment examples as tests
+//!
+//! Example code is parsed from the original source with everything else blanked out,
+//! so spans in example code point into the doc comment.
+
+use std::collections::BTreeMap;
+
+use crate::ExampleLine;
+
+use super::*;
+
+/// The location of a doc comment example in the source
+struct SourceExample {
+    /// The line index and column index of the start of the code on each line
+    lines: Vec<(usize, usize)>,
+    /// Whether the example is expected to fail
+    should_error: bool,
+}
+
+impl Compiler {
+    /// Compile the examples in the doc comments of bindings created since `binding_start` as tests
+    pub(super) fn compile_doc_tests(&mut self, input: &str, src: &InputSrc, binding_start: usize) {
+        let lines: Vec<&str> = input.lines().collect();
+        let mut documented = BTreeMap::new();
+        for binding in &self.asm.bindings[binding_start..] {
+            if binding.comment.is_some() && binding.span.src == *src {
+                let line = binding.span.start.line as usize - 1;
+                documented
+                    .entry(line)
+                    .or_insert_with(|| binding.span.clone());
+            }
+        }
+        for (line, span) in documented {
+            let name: String = (lines[line].chars())
+                .skip(span.start.col as usize - 1)
+                .take(span.end.col.saturating_sub(span.start.col) as usize)
+                .collect();
+            for (i, example) in source_examples(&lines, line).into_iter().enumerate() {
+                let name = format!("{name} example {}", i + 1).into();
+                if let Err(e) = self.doc_test(input, src, &lines, name, span.clone(), example) {
+                    self.errors.push(e);
+                }
+            }
+        }
+    }
+    fn doc_test(
+        &mut self,
+        input: &str,
+        src: &InputSrc,
+        lines: &[&str],
+        name: EcoString,
+        binding_span: CodeSpan,
+        example: SourceExample,
+    ) -> UiuaResult {
+        let mut items = self.parse_masked(input, src, &mask(lines, &example.lines))?;
+        let [Item::Words(code)] = items.as_mut_slice() else {
+            // Examples with bindings are compiled as is
+            return self.test_scope(name, binding_span, |comp| comp.items(items, false));
+        };
+        let mut code = take(code);
+        code.retain(|line| line.iter().any(|word| word.value.is_code()));
+        if code.is_empty() {
+            return Ok(());
+        }
+
+        // Split off the expected result
+        let mut expected = None;
+        let last = code.last_mut().unwrap();
+        if let Some(i) = (last.iter()).position(|w| matches!(w.value, Word::OutputComment { .. })) {
+            let comment_span = last[i].span.clone();
+            let start = comment_span.start;
+            last.truncate(i);
+            let line = start.line as usize - 1;
+            let col = start.col as usize - 1
+                + (lines[line].chars().skip(start.col as usize - 1))
+                    .take_while(|&c| c == '#')
+                    .count();
+            let text: String = lines[line].chars().skip(col).collect();
+            if !text.trim().is_empty() {
+                let mut items = self.parse_masked(input, src, &mask(lines, &[(line, col)]))?;
+                let mut words = match items.pop() {
+                    Some(Item::Words(words)) if items.is_empty() => words,
+                    _ => {
+                        return Err(self.error(
+                            comment_span,
+                            "The expected result of an example must be an expression",
+                        ))
+                    }
+                };
+                words.retain(|line| line.iter().any(|word| word.value.is_code()));
+                expected = Some((words, text.trim().to_string()));
+            }
+        }
+
+        code.retain(|line| line.iter().any(|word| word.value.is_code()));
+        let (Some(first), Some(last)) = (code.first(), code.last()) else {
+            return Ok(());
+        };
+        let span = (first[0].span.clone()).merge(last.last().unwrap().span.clone());
+
+        let sp = |word: Word| span.clone().sp(word);
+        let prim = |prim: Primitive| sp(Word::Primitive(prim));
+        let boxes = |lines: Vec<Vec<Sp<Word>>>| {
+            sp(Word::Array(Arr {
+                signature: None,
+                lines,
+                boxes: true,
+                closed: true,
+            }))
+        };
+        let func = |line: Vec<Sp<Word>>| {
+            sp(Word::Func(Func {
+                signature: None,
+                lines: vec![line],
+                closed: true,
+            }))
+        };
+        let words = if example.should_error {
+            // ⍤msg ⍣(0◌{code})(1◌)
+            let body = func(vec![
+                sp(Word::Number(Ok(0.0))),
+                prim(Primitive::Pop),
+                boxes(code),
+            ]);
+            let handler = func(vec![sp(Word::Number(Ok(1.0))), prim(Primitive::Pop)]);
+            vec![
+                prim(Primitive::Assert),
+                sp(Word::String("Example should have failed".into())),
+                sp(Word::Modified(Box::new(Modified {
+                    modifier: span.clone().sp(Modifier::Primitive(Primitive::Try)),
+                    operands: vec![body, handler],
+                    pack_expansion: false,
+                }))),
+            ]
+        } else if let Some((expected, text)) = expected {
+            // ⍤msg ≍{expected}{code}
+            vec![
+                prim(Primitive::Assert),
+                sp(Word::String(format!(
+                    "Example result does not match {text}"
+                ))),
+                prim(Primitive::Match),
+                boxes(expected),
+                boxes(code),
+            ]
+        } else {
+            // ◌{code}
+            vec![prim(Primitive::Pop), boxes(code)]
+        };
+        self.test_scope(name, span, |comp| {
+            comp.items(vec![Item::Words(vec![words])], false)
+        })
+    }
+    /// Parse a masked version of an input
+    fn parse_masked(&mut self, input: &str, src: &InputSrc, masked: &str) -> UiuaResult<Vec<Item>> {
+        let (items, errors, _) = parse(masked, src.clone(), &mut self.asm.inputs);
+        // Parsing replaces the input, so restore it
+        self.asm.inputs.add_src(src.clone(), input);
+        if errors.is_empty() {
+            Ok(items)
+        } else {
+            Err(UiuaErrorKind::Parse(errors, self.asm.inputs.clone().into()).into())
+        }
+    }
+}
+
+/// Find the examples in the doc comment above a binding
+fn source_examples(lines: &[&str], binding_line: usize) -> Vec<SourceExample> {
+    let is_comment = |i: usize| {
+        lines
+            .get(i)
+            .is_some_and(|l| l.trim_start().starts_with('#'))
+    };
+    let Some(start) = (0..binding_line)
+        .rev()
+        .take_while(|&i| is_comment(i))
+        .last()
+    else {
+        return Vec::new();
+    };
+    let mut examples: Vec<SourceExample> = Vec::new();
+    let mut continuable = false;
+    for (i, line) in lines.iter().enumerate().take(binding_line).skip(start) {
+        let text = line.trim_start()[1..].trim_start();
+        let col = |code: &str| line.chars().count() - code.chars().count();
+        match ExampleLine::parse(text) {
+            Some(ExampleLine::Start { code, should_error }) => {
+                continuable = true;
+                examples.push(SourceExample {
+                    lines: vec![(i, col(code))],
+                    should_error,
+                });
+            }
+            Some(ExampleLine::Continue(code)) if continuable => {
+                examples.last_mut().unwrap().lines.push((i, col(code)));
+            }
+            _ => continuable = false,
+        }
+    }
+    examples
+}
+
+/// Blank out everything in the input except the given line suffixes
+///
+/// Line and column positions are preserved.
+fn mask(lines: &[&str], segments: &[(usize, usize)]) -> String {
+    let mut masked = String::new();
+    for (i, line) in lines.iter().enumerate() {
+        if let Some(&(_, col)) = segments.iter().find(|(l, _)| *l == i) {
+            masked.extend((line.chars().enumerate()).map(|(j, c)| if j < col { ' ' } else { c }));
+        }
+        masked.push('\n');
+    }
+    masked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn doc_tests() {
+        let code = "\
+# Double a number
+# ex: Double 5 ## 10
+# ex: Double 2 ## 5
+# ex! ⍤\"fails\" 0
+Double ← ×2";
+        let mut env = Uiua::with_safe_sys();
+        let mut comp = Compiler::new();
+        comp.mode(RunMode::Test).doc_tests(true);
+        let err = comp
+            .load_str(code)
+            .and_then(|comp| env.run_asm(comp.finish()))
+            .unwrap_err();
+        assert!(err.multi.is_empty(), "{}", err.report());
+        assert!(err.to_string().contains("does not match 5"), "{err}");
+        let report = env.take_reports().pop().unwrap().to_string();
+        assert!(report.contains("2 tests passed, 1 failed"), "{report}");
+    }
+}
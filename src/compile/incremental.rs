@@ -0,0 +1,620 @@
+//! Reuse of previous compilation work, for the language server
+
+use std::{
+    collections::hash_map::DefaultHasher,
+    hash::{Hash, Hasher},
+};
+
+use parking_lot::Mutex;
+
+use crate::parse::ParseError;
+
+use super::*;
+
+/// Parsed files and compiled modules shared between compilers
+///
+/// Entries are keyed by path and validated by content hash
+#[derive(Clone, Default)]
+pub(crate) struct ImportCache(Arc<Mutex<ImportCacheInner>>);
+
+#[derive(Default)]
+struct ImportCacheInner {
+    parsed: HashMap<PathBuf, ParsedFile>,
+    compiled: HashMap<PathBuf, Arc<CompiledImport>>,
+}
+
+struct ParsedFile {
+    hash: u64,
+    items: Vec<Item>,
+    errors: Vec<Sp<ParseError>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+/// A module compiled on its own
+///
+/// Bindings, functions, and spans are numbered as if the module were the first thing compiled,
+/// so they must be relocated when the module is merged into a compiler.
+struct CompiledImport {
+    /// The files compiled for the module and their content hashes
+    files: Vec<(PathBuf, u64)>,
+    /// The module's bindings
+    module: Module,
+    /// The number of global bindings used by the module
+    globals: usize,
+    asm: Assembly,
+    /// The modules imported by the module
+    imports: HashMap<PathBuf, Module>,
+    index_macros: HashMap<usize, IndexMacro>,
+    code_macros: HashMap<usize, CodeMacro>,
+    unevaluated_constants: HashMap<usize, Node>,
+    code_meta: CodeMeta,
+    deprecated_prim_errors: HashSet<Primitive>,
+    diagnostics: BTreeSet<Diagnostic>,
+}
+
+/// How to renumber a compiled module's indices when merging it into a compiler
+struct Relocation {
+    bindings: usize,
+    functions: usize,
+    dynamic_functions: usize,
+    /// The new index of each of the module's spans
+    spans: Vec<usize>,
+}
+
+/// A snapshot of a file's compilation after some of its top-level items
+#[cfg(feature = "lsp")]
+#[derive(Clone)]
+pub(crate) struct ItemCheckpoint {
+    /// The number of items compiled
+    items: usize,
+    /// The line on which the last compiled item ends
+    end_line: usize,
+    item_errored: bool,
+    comp: Compiler,
+}
+
+/// The number of checkpoints to aim for in a file
+#[cfg(feature = "lsp")]
+const CHECKPOINTS_PER_FILE: usize = 16;
+
+fn content_hash(input: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    input.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl Compiler {
+    /// Parse a file, reusing a cached parse if its content is unchanged
+    pub(super) fn parse_file(
+        &mut self,
+        input: &str,
+        src: &InputSrc,
+    ) -> (Vec<Item>, Vec<Sp<ParseError>>, Vec<Diagnostic>) {
+        let (Some(cache), InputSrc::File(path)) = (&self.import_cache, src) else {
+            return parse(input, src.clone(), &mut self.asm.inputs);
+        };
+        let hash = content_hash(input);
+        if let Some(parsed) =
+            (cache.0.lock().parsed.get(path.as_ref())).filter(|parsed| parsed.hash == hash)
+        {
+            return (
+                parsed.items.clone(),
+                parsed.errors.clone(),
+                parsed.diagnostics.clone(),
+            );
+        }
+        let (items, errors, diagnostics) = parse(input, src.clone(), &mut self.asm.inputs);
+        cache.0.lock().parsed.insert(
+            path.to_path_buf(),
+            ParsedFile {
+                hash,
+                items: items.clone(),
+                errors: errors.clone(),
+                diagnostics: diagnostics.clone(),
+            },
+        );
+        (items, errors, diagnostics)
+    }
+    /// Import a module through the import cache, returning whether it was imported
+    ///
+    /// The module is compiled on its own and merged into this compiler,
+    /// so the compiled module can be reused by any compiler that imports it.
+    /// If the module fails to compile or shares imports with this compiler,
+    /// nothing is imported and it should be compiled in place instead.
+    pub(super) fn import_cached(&mut self, path: &Path, input: &str, kind: FileScopeKind) -> bool {
+        let Some(cache) = self.import_cache.clone() else {
+            return false;
+        };
+        // Test asserts are compiled differently in Git modules
+        if (self.scopes()).any(|sc| sc.kind == ScopeKind::File(FileScopeKind::Git)) {
+            return false;
+        }
+        let backend = self.backend();
+        let cached = (cache.0.lock().compiled.get(path).cloned()).filter(|cached| {
+            cached.files.iter().all(|(path, hash)| {
+                (backend.file_read_all(path).ok())
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .is_some_and(|input| content_hash(&input) == *hash)
+            })
+        });
+        let compiled = match cached {
+            Some(compiled) => compiled,
+            None => {
+                let Some(compiled) = self.compile_import(path, input, kind) else {
+                    return false;
+                };
+                let compiled = Arc::new(compiled);
+                (cache.0.lock().compiled).insert(path.into(), compiled.clone());
+                compiled
+            }
+        };
+        let shared = (compiled.imports.keys())
+            .any(|path| self.imports.contains_key(path) || self.current_imports.contains(path));
+        if shared {
+            return false;
+        }
+        self.merge_import(path, &compiled);
+        true
+    }
+    /// Compile a module in a fresh compiler with the same settings as this one
+    fn compile_import(
+        &self,
+        path: &Path,
+        input: &str,
+        kind: FileScopeKind,
+    ) -> Option<CompiledImport> {
+        let mut comp = Compiler {
+            mode: self.mode,
+            current_imports: self.current_imports.clone(),
+            comptime: self.comptime,
+            pre_eval_mode: self.pre_eval_mode,
+            macro_env: Uiua::with_backend(self.backend()),
+            doc_tests: self.doc_tests,
+            test_filter: self.test_filter.clone(),
+            infer_types: self.infer_types,
+            import_cache: self.import_cache.clone(),
+            ..Compiler::default()
+        };
+        let (module, ()) = (comp.in_scope(ScopeKind::File(kind), |comp| {
+            comp.load_str_src(input, path).map(drop)
+        }))
+        .ok()?;
+        let files = (comp.asm.inputs.files.iter())
+            .map(|entry| (entry.key().clone(), content_hash(entry.value())))
+            .collect();
+        Some(CompiledImport {
+            files,
+            module,
+            globals: comp.next_global,
+            asm: comp.asm,
+            imports: comp.imports,
+            index_macros: comp.index_macros,
+            code_macros: comp.code_macros,
+            unevaluated_constants: comp.macro_env.rt.unevaluated_constants,
+            code_meta: comp.code_meta,
+            deprecated_prim_errors: comp.deprecated_prim_errors,
+            diagnostics: comp.diagnostics,
+        })
+    }
+    /// Merge a module that was compiled on its own into this compiler
+    fn merge_import(&mut self, path: &Path, compiled: &CompiledImport) {
+        let asm = &compiled.asm;
+        let reloc = Relocation {
+            bindings: self.next_global,
+            functions: self.asm.functions.len(),
+            dynamic_functions: self.asm.dynamic_functions.len(),
+            spans: (asm.spans.iter())
+                .map(|span| self.add_span(span.clone()))
+                .collect(),
+        };
+        // Assembly
+        for (i, binding) in asm.bindings.iter().enumerate() {
+            let mut kind = binding.kind.clone();
+            reloc.binding_kind(&mut kind);
+            let local = LocalName {
+                index: reloc.bindings + i,
+                public: binding.public,
+            };
+            let span = Some(binding.span.clone());
+            (self.asm).add_binding_at(local, kind, span, binding.comment.clone());
+        }
+        self.next_global += compiled.globals;
+        for f in &asm.functions {
+            let mut f = f.clone();
+            reloc.node(&mut f);
+            self.asm.functions.push(f);
+        }
+        (self.asm.dynamic_functions).extend(asm.dynamic_functions.iter().cloned());
+        let mut root = asm.root.clone();
+        reloc.node(&mut root);
+        self.asm.root.push(root);
+        for entry in asm.inputs.files.iter() {
+            (self.asm.inputs.files).insert(entry.key().clone(), entry.value().clone());
+        }
+        for entry in asm.inputs.macros.iter() {
+            (self.asm.inputs.macros).insert(entry.key().clone(), entry.value().clone());
+        }
+        self.asm.test_assert_count += asm.test_assert_count;
+        for (span, name) in &asm.test_scope_names {
+            (self.asm.test_scope_names).insert(reloc.spans[*span], name.clone());
+        }
+        // Modules and macros
+        let modules = (compiled.imports.iter())
+            .map(|(path, module)| (path.clone(), module))
+            .chain([(path.to_path_buf(), &compiled.module)]);
+        for (path, module) in modules {
+            let mut module = module.clone();
+            reloc.names(&mut module.names);
+            self.imports.insert(path, module);
+        }
+        for (index, mac) in &compiled.index_macros {
+            let mut mac = mac.clone();
+            reloc.names(&mut mac.names);
+            (self.index_macros).insert(reloc.bindings + index, mac);
+        }
+        for (index, mac) in &compiled.code_macros {
+            let mut mac = mac.clone();
+            reloc.node(&mut mac.root.node);
+            reloc.names(&mut mac.names);
+            (self.code_macros).insert(reloc.bindings + index, mac);
+        }
+        for (index, node) in &compiled.unevaluated_constants {
+            let mut node = node.clone();
+            reloc.node(&mut node);
+            (self.macro_env.rt.unevaluated_constants).insert(reloc.bindings + index, node);
+        }
+        // Metadata
+        let CodeMeta {
+            global_references,
+            constant_references,
+            function_sigs,
+            macro_expansions,
+            inline_macros,
+            incomplete_refs,
+            top_level_values,
+            strands,
+            array_inner_spans,
+            array_shapes,
+            import_srcs,
+            obverses,
+            inferred_types,
+        } = compiled.code_meta.clone();
+        let meta = &mut self.code_meta;
+        (meta.global_references).extend(
+            (global_references.into_iter()).map(|(span, index)| (span, reloc.bindings + index)),
+        );
+        meta.constant_references.extend(constant_references);
+        meta.function_sigs.extend(function_sigs);
+        meta.macro_expansions.extend(macro_expansions);
+        meta.inline_macros.extend(inline_macros);
+        (meta.incomplete_refs).extend(
+            (incomplete_refs.into_iter()).map(|(span, index)| (span, reloc.bindings + index)),
+        );
+        meta.top_level_values.extend(top_level_values);
+        meta.strands.extend(strands);
+        meta.array_inner_spans.extend(array_inner_spans);
+        meta.array_shapes.extend(array_shapes);
+        meta.import_srcs.extend(import_srcs);
+        meta.obverses.extend(obverses);
+        meta.inferred_types.extend(inferred_types);
+        (self.deprecated_prim_errors).extend(compiled.deprecated_prim_errors.iter().copied());
+        (self.diagnostics).extend(compiled.diagnostics.iter().cloned());
+    }
+    /// Compile a file, resuming from the latest checkpoint that precedes the first changed line
+    ///
+    /// Returns the new checkpoints along with the result, or [`None`] if compilation was canceled
+    #[cfg(feature = "lsp")]
+    pub(crate) fn load_incremental(
+        &mut self,
+        input: &str,
+        src: InputSrc,
+        checkpoints: &[ItemCheckpoint],
+        first_changed_line: usize,
+        cancel: &dyn Fn() -> bool,
+    ) -> Option<(UiuaResult<&mut Self>, Vec<ItemCheckpoint>)> {
+        let node_start = self.asm.root.len();
+        let src = self.asm.inputs.add_src(src, input);
+        let (items, errors, diagnostics) = self.parse_file(input, &src);
+        if !errors.is_empty() || self.doc_tests {
+            return Some((self.load_impl(input, src), Vec::new()));
+        }
+        // Find a checkpoint whose items are unchanged
+        let resume = checkpoints.iter().rposition(|cp| {
+            cp.end_line < first_changed_line
+                && (items.get(cp.items - 1)).and_then(item_end_line) == Some(cp.end_line)
+                && cp.imports_unchanged(&src)
+        });
+        let mut new_checkpoints = Vec::new();
+        let mut item_errored = false;
+        if let Some(i) = resume {
+            let cp = &checkpoints[i];
+            new_checkpoints.extend_from_slice(&checkpoints[..=i]);
+            item_errored = cp.item_errored;
+            let import_cache = self.import_cache.clone();
+            *self = cp.comp.clone();
+            self.import_cache = import_cache;
+            self.asm.inputs.add_src(src.clone(), input);
+        } else {
+            self.begin_file(&src);
+            self.set_scope_comment(&items);
+        }
+        let stride = (items.len() / CHECKPOINTS_PER_FILE).max(1);
+        let mut compiled = resume.map_or(0, |i| checkpoints[i].items);
+        let mut items = VecDeque::from(items);
+        items.drain(..compiled);
+        let res = self.catching_crash(input, |comp| {
+            let mut prelude = BindingPrelude::default();
+            while let Some(item) = items.pop_front() {
+                if cancel() {
+                    return None;
+                }
+                let end_line = item_end_line(&item);
+                let must_run = item_must_run(&item, &items, false);
+                if let Err(e) = comp.item(item, false, must_run, &mut prelude) {
+                    if !item_errored {
+                        comp.errors.push(e);
+                    }
+                    item_errored = true;
+                }
+                compiled += 1;
+                if compiled % stride != 0
+                    || items.is_empty()
+                    || prelude != BindingPrelude::default()
+                {
+                    continue;
+                }
+                if let Some(end_line) = end_line {
+                    new_checkpoints.push(ItemCheckpoint {
+                        items: compiled,
+                        end_line,
+                        item_errored,
+                        comp: comp.clone(),
+                    });
+                }
+            }
+            Some(())
+        });
+        if let Ok(None) = res {
+            return None;
+        }
+        if cancel() {
+            return None;
+        }
+        // Parse diagnostics are emitted last so that checkpoints do not keep stale ones
+        for diagnostic in diagnostics {
+            self.emit_diagnostic_impl(diagnostic);
+        }
+        let res = res.map(|_| Ok(()));
+        Some((self.finish_file(&src, node_start, res), new_checkpoints))
+    }
+}
+
+#[cfg(feature = "lsp")]
+impl ItemCheckpoint {
+    /// Check that the files imported before this checkpoint have not changed
+    fn imports_unchanged(&self, src: &InputSrc) -> bool {
+        let backend = self.comp.backend();
+        (self.comp.asm.inputs.files.iter())
+            .filter(|entry| !matches!(src, InputSrc::File(path) if **path == **entry.key()))
+            .all(|entry| {
+                (backend.file_read_all(entry.key()).ok())
+                    .and_then(|bytes| String::from_utf8(bytes).ok())
+                    .is_some_and(|input| input == entry.value().as_str())
+            })
+    }
+}
+
+impl Relocation {
+    fn node(&self, node: &mut Node) {
+        match node {
+            Node::Array { inner, span, .. } => {
+                self.node(Arc::make_mut(inner));
+                self.span(span);
+            }
+            Node::CallGlobal(index, _) => *index += self.bindings,
+            Node::CallMacro { index, span, .. } | Node::BindGlobal { index, span } => {
+                *index += self.bindings;
+                self.span(span);
+            }
+            Node::CustomInverse(cust, span) => {
+                let cust = Arc::make_mut(cust);
+                if let Ok(normal) = &mut cust.normal {
+                    self.node(&mut normal.node);
+                }
+                let under = (cust.under.iter_mut()).flat_map(|(before, after)| [before, after]);
+                for sn in (cust.un.iter_mut()).chain(&mut cust.anti).chain(under) {
+                    self.node(&mut sn.node);
+                }
+                self.span(span);
+            }
+            Node::Switch {
+                branches: ops,
+                span,
+                ..
+            }
+            | Node::Mod(_, ops, span)
+            | Node::ImplMod(_, ops, span) => {
+                for sn in ops.make_mut() {
+                    self.node(&mut sn.node);
+                }
+                self.span(span);
+            }
+            Node::Dynamic(f) => f.index += self.dynamic_functions,
+            Node::NoInline(inner) | Node::TrackCaller(inner) => self.node(Arc::make_mut(inner)),
+            Node::Call(f, span) => {
+                f.index += self.functions;
+                self.span(span);
+            }
+            Node::Run(nodes) => {
+                for node in nodes.make_mut() {
+                    self.node(node);
+                }
+            }
+            Node::Label(_, span)
+            | Node::RemoveLabel(_, span)
+            | Node::Format(_, span)
+            | Node::MatchFormatPattern(_, span)
+            | Node::Unpack { span, .. }
+            | Node::ValidateType { span, .. }
+            | Node::PushUnder(_, span)
+            | Node::CopyToUnder(_, span)
+            | Node::PopUnder(_, span)
+            | Node::Prim(_, span)
+            | Node::ImplPrim(_, span) => self.span(span),
+            Node::SetOutputComment { .. } | Node::Push(_) => {}
+        }
+    }
+    fn span(&self, span: &mut usize) {
+        *span = self.spans[*span];
+    }
+    fn names(&self, names: &mut IndexMap<Ident, LocalName>) {
+        for local in names.values_mut() {
+            local.index += self.bindings;
+        }
+    }
+    fn binding_kind(&self, kind: &mut BindingKind) {
+        match kind {
+            BindingKind::Func(f) => f.index += self.functions,
+            BindingKind::Module(module) => self.names(&mut module.names),
+            BindingKind::CodeMacro(node) => self.node(node),
+            BindingKind::Const(_) | BindingKind::Import(_) | BindingKind::IndexMacro(_) => {}
+        }
+    }
+}
+
+/// Get the line on which an item ends
+#[cfg(feature = "lsp")]
+fn item_end_line(item: &Item) -> Option<usize> {
+    let span = match item {
+        Item::Words(lines) => (lines.iter().flatten())
+            .map(|word| word.span.clone())
+            .reduce(CodeSpan::merge)?,
+        Item::Binding(binding) => binding.span(),
+        Item::Import(import) => (import.lines.iter().flatten())
+            .map(|line| line.tilde_span.clone())
+            .fold(import.span(), CodeSpan::merge),
+        Item::Module(module) => module.span.clone(),
+        Item::Data(data) => data.span(),
+        Item::Error(span) => span.clone(),
+    };
+    Some(span.end.line as usize)
+}
+
+#[cfg(all(test, feature = "lsp"))]
+mod tests {
+    use super::*;
+    use crate::NativeSys;
+
+    /// Compile edited code from checkpoints and check that it matches a full compile
+    fn check_incremental(
+        original: &str,
+        edited: &str,
+        src: InputSrc,
+        first_changed_line: usize,
+        edit: impl FnOnce(),
+    ) {
+        let (_, checkpoints) = (Compiler::with_backend(NativeSys))
+            .load_incremental(original, src.clone(), &[], 1, &|| false)
+            .unwrap();
+        assert!(!checkpoints.is_empty());
+        edit();
+        let mut full = Compiler::with_backend(NativeSys);
+        full.load_str_src(edited, src.clone()).unwrap();
+        let mut incremental = Compiler::with_backend(NativeSys);
+        let (res, _) = incremental
+            .load_incremental(edited, src, &checkpoints, first_changed_line, &|| false)
+            .unwrap();
+        res.unwrap();
+        assert_eq!(incremental.asm.root, full.asm.root);
+        let run = |mut comp: Compiler| {
+            let mut env = Uiua::with_native_sys();
+            env.run_asm(comp.finish()).unwrap();
+            env.take_stack()
+        };
+        assert_eq!(run(incremental), run(full));
+    }
+
+    #[test]
+    fn edit_last_line() {
+        let code = "F ← +1\nG ← ×2\nH ← F G\nH 3\nH 4\n";
+        let edited = code.replace("H 4", "H 5");
+        check_incremental(code, &edited, InputSrc::Str(0), 5, || {});
+    }
+
+    #[test]
+    fn edit_before_checkpoint() {
+        let code = "F ← +1\nG ← ×2\nH ← F G\nH 3\nH 4\n";
+        let edited = code.replace("G ← ×2", "G ← ×3");
+        check_incremental(code, &edited, InputSrc::Str(0), 2, || {});
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "uiua_incremental_{name}_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn edit_import() {
+        let dir = temp_dir("edit_import");
+        let lib = dir.join("lib.ua");
+        fs::write(&lib, "X ← 1\n").unwrap();
+        let code = "Lib ~ \"lib.ua\"\nF ← +1\nG ← ×2\nF G Lib~X\n4\n";
+        // Only the last line is reported as changed, but the import must be recompiled
+        check_incremental(code, code, dir.join("main.ua").as_path().into(), 5, || {
+            fs::write(&lib, "X ← 2\n").unwrap()
+        });
+        _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn cached_imports() {
+        let dir = temp_dir("cached_imports");
+        fs::write(dir.join("a.ua"), "A ← +1\n").unwrap();
+        fs::write(dir.join("b.ua"), "B ← ×2\nC ← 10\n").unwrap();
+        let run = |code: &str, cache: Option<&ImportCache>| {
+            let path = dir.join("main.ua");
+            fs::write(&path, code).unwrap();
+            let mut comp = Compiler::with_backend(NativeSys);
+            if let Some(cache) = cache {
+                comp.import_cache(cache.clone());
+            }
+            comp.load_file(&path).unwrap();
+            let mut env = Uiua::with_native_sys();
+            env.run_asm(comp.finish()).unwrap();
+            env.take_stack()
+        };
+        let cached = |cache: &ImportCache| {
+            let mut entries: Vec<_> = (cache.0.lock().compiled.iter())
+                .map(|(path, compiled)| (path.file_name().unwrap().to_owned(), compiled.clone()))
+                .collect();
+            entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+            entries
+        };
+        let cache = ImportCache::default();
+
+        // Both imports are cached, even though the second comes after other code
+        let code = "X ← 3\nA ~ \"a.ua\"\nB ~ \"b.ua\"\nA~A B~B X\nB~C\n";
+        assert_eq!(run(code, Some(&cache)), run(code, None));
+        let first = cached(&cache);
+        let names: Vec<_> = first
+            .iter()
+            .map(|(name, _)| name.to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.ua", "b.ua"]);
+
+        // Cached modules are reused at different import positions
+        let code = "B ~ \"b.ua\"\nY ← 5\nA ~ \"a.ua\"\nB~B A~A Y B~C\n";
+        assert_eq!(run(code, Some(&cache)), run(code, None));
+        let second = cached(&cache);
+        assert!((first.iter().zip(&second)).all(|((_, a), (_, b))| Arc::ptr_eq(a, b)));
+        _ = fs::remove_dir_all(&dir);
+    }
+}
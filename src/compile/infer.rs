@@ -0,0 +1,894 @@
+//! Static inference of value types and shapes
+//!
+//! This is an abstract interpretation of compiled [`Node`]s. It tracks what is known about
+//! the element type and shape of each value so that operations which will likely fail
+//! can be reported before the code is run.
+
+use crate::PrimClass;
+
+use super::*;
+
+/// The maximum depth of function calls to follow
+const MAX_CALL_DEPTH: usize = 8;
+/// The maximum number of nodes to visit in one analysis
+const MAX_STEPS: usize = 100_000;
+
+/// The element type of an array
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ElemType {
+    /// Bytes
+    Byte,
+    /// Real numbers
+    Num,
+    /// Complex numbers
+    Complex,
+    /// Characters
+    Char,
+    /// Boxes
+    Box,
+}
+
+impl fmt::Display for ElemType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ElemType::Byte => write!(f, "byte"),
+            ElemType::Num => write!(f, "number"),
+            ElemType::Complex => write!(f, "complex"),
+            ElemType::Char => write!(f, "character"),
+            ElemType::Box => write!(f, "box"),
+        }
+    }
+}
+
+/// What is statically known about a value
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ValueType {
+    /// The element type, if it is known
+    pub elem: Option<ElemType>,
+    /// The shape, if the rank is known
+    ///
+    /// Individual dimensions may still be unknown.
+    pub shape: Option<Vec<Option<usize>>>,
+    /// The type of the values in a box array
+    boxed: Option<Box<ValueType>>,
+    /// The value itself, if it is a constant
+    value: Option<Value>,
+}
+
+impl ValueType {
+    fn of(val: &Value) -> Self {
+        let (elem, boxed) = match val {
+            Value::Num(_) => (ElemType::Num, None),
+            Value::Byte(_) => (ElemType::Byte, None),
+            Value::Complex(_) => (ElemType::Complex, None),
+            Value::Char(_) => (ElemType::Char, None),
+            Value::Box(arr) => {
+                let boxed = (arr.data.iter())
+                    .map(|Boxed(val)| Self::of(val).forget())
+                    .reduce(Self::union);
+                (ElemType::Box, boxed.map(Box::new))
+            }
+        };
+        ValueType {
+            elem: Some(elem),
+            shape: Some(val.shape().iter().copied().map(Some).collect()),
+            boxed,
+            value: Some(val.clone()),
+        }
+    }
+    fn new(elem: Option<ElemType>, shape: Option<Vec<Option<usize>>>) -> Self {
+        ValueType {
+            elem,
+            shape,
+            ..Default::default()
+        }
+    }
+    fn scalar(elem: ElemType) -> Self {
+        Self::new(Some(elem), Some(Vec::new()))
+    }
+    /// Whether anything is known about the value
+    pub fn is_known(&self) -> bool {
+        self.elem.is_some() || self.shape.is_some()
+    }
+    fn rank(&self) -> Option<usize> {
+        self.shape.as_ref().map(Vec::len)
+    }
+    /// Forget the constant value
+    fn forget(mut self) -> Self {
+        self.value = None;
+        self
+    }
+    /// Replace the shape, forgetting the constant value
+    fn with_shape(self, shape: Option<Vec<Option<usize>>>) -> Self {
+        ValueType {
+            shape,
+            ..self.forget()
+        }
+    }
+    /// The type of a row, or the value itself if it is a scalar
+    fn row(&self) -> Self {
+        let shape = (self.shape.as_ref()).map(|shape| shape.iter().skip(1).copied().collect());
+        self.clone().with_shape(shape)
+    }
+    /// The element type, looking inside boxes
+    fn inner_elem(&self) -> Option<ElemType> {
+        match self.elem {
+            Some(ElemType::Box) => self.boxed.as_ref()?.elem,
+            elem => elem,
+        }
+    }
+    /// Keep only what is known about both types
+    fn union(self, other: Self) -> Self {
+        let shape = match (self.shape, other.shape) {
+            (Some(a), Some(b)) if a.len() == b.len() => Some(
+                (a.into_iter().zip(b))
+                    .map(|(a, b)| if a == b { a } else { None })
+                    .collect(),
+            ),
+            _ => None,
+        };
+        ValueType {
+            elem: if self.elem == other.elem {
+                self.elem
+            } else {
+                None
+            },
+            shape,
+            boxed: if self.boxed == other.boxed {
+                self.boxed
+            } else {
+                None
+            },
+            value: None,
+        }
+    }
+    fn shape_str(&self) -> String {
+        let Some(shape) = &self.shape else {
+            return "[…]".into();
+        };
+        let dims: Vec<String> = (shape.iter())
+            .map(|dim| dim.map_or("?".into(), |dim| dim.to_string()))
+            .collect();
+        format!("[{}]", dims.join(" × "))
+    }
+}
+
+impl fmt::Display for ValueType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !self.is_known() {
+            return write!(f, "?");
+        }
+        match &self.shape {
+            Some(shape) => {
+                for (i, dim) in shape.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, "×")?;
+                    }
+                    match dim {
+                        Some(dim) => write!(f, "{dim}")?,
+                        None => write!(f, "?")?,
+                    }
+                }
+                if !shape.is_empty() {
+                    write!(f, " ")?;
+                }
+            }
+            None => write!(f, "… ")?,
+        }
+        match self.elem {
+            Some(elem) => write!(f, "{elem}"),
+            None => write!(f, "?"),
+        }
+    }
+}
+
+/// Analysis was abandoned because a node's effect on the stack is unknown
+/// or because it took too long
+struct Bail;
+
+type InferResult<T = ()> = Result<T, Bail>;
+
+struct Infer<'a> {
+    asm: &'a Assembly,
+    stack: Vec<ValueType>,
+    under: Vec<ValueType>,
+    /// The number of fills in effect
+    fill_depth: usize,
+    /// The number of function calls being followed
+    call_depth: usize,
+    /// The number of nodes visited
+    steps: usize,
+    /// Likely errors and their span indices
+    errors: Vec<(usize, String)>,
+    /// The types of values bound to global bindings
+    bound: Vec<(usize, ValueType)>,
+}
+
+impl Compiler {
+    /// Infer the types of a file's values and report operations that will likely fail
+    pub(super) fn infer_file(&mut self, src: &InputSrc, node_start: usize) {
+        let mut errors = Vec::new();
+        let mut types = Vec::new();
+        // Functions
+        for binding in &self.asm.bindings {
+            let BindingKind::Func(f) = &binding.kind else {
+                continue;
+            };
+            if binding.span.src != *src {
+                continue;
+            }
+            let mut infer = Infer::new(&self.asm);
+            let res = infer.node(&self.asm[f]);
+            errors.append(&mut infer.errors);
+            if res.is_ok() {
+                let start = infer.stack.len().saturating_sub(f.sig.outputs);
+                let outputs: Vec<_> = infer.stack.drain(start..).rev().collect();
+                types.push((binding.span.clone(), outputs));
+            }
+        }
+        // Top-level code
+        let mut infer = Infer::new(&self.asm);
+        for node in self.asm.root.iter().skip(node_start) {
+            if infer.node(node).is_err() {
+                break;
+            }
+        }
+        errors.append(&mut infer.errors);
+        for (index, ty) in infer.bound {
+            if let Some(binding) = self.asm.bindings.get(index) {
+                if binding.span.src == *src {
+                    types.push((binding.span.clone(), vec![ty.forget()]));
+                }
+            }
+        }
+
+        self.code_meta.inferred_types.extend(types);
+        for (span, message) in errors {
+            if let Some(span) = self.asm.spans.get(span).cloned() {
+                self.emit_lint("type-error", message, DiagnosticKind::Warning, span);
+            }
+        }
+    }
+}
+
+impl<'a> Infer<'a> {
+    fn new(asm: &'a Assembly) -> Self {
+        Infer {
+            asm,
+            stack: Vec::new(),
+            under: Vec::new(),
+            fill_depth: 0,
+            call_depth: 0,
+            steps: 0,
+            errors: Vec::new(),
+            bound: Vec::new(),
+        }
+    }
+    /// Pop a value, which is unknown if it comes from outside the analyzed code
+    fn pop(&mut self) -> ValueType {
+        self.stack.pop().unwrap_or_default()
+    }
+    fn pop_n(&mut self, n: usize) -> Vec<ValueType> {
+        (0..n).map(|_| self.pop()).collect()
+    }
+    /// Push values popped with [`Infer::pop_n`] back in their original order
+    fn push_n(&mut self, vals: &[ValueType]) {
+        self.stack.extend(vals.iter().rev().cloned());
+    }
+    fn push(&mut self, ty: ValueType) {
+        self.stack.push(ty);
+    }
+    fn error(&mut self, span: usize, message: impl Into<String>) {
+        if self.call_depth == 0 {
+            self.errors.push((span, message.into()));
+        }
+    }
+    /// Apply a signature without knowing anything about the outputs
+    fn opaque(&mut self, sig: Signature) {
+        self.pop_n(sig.args);
+        for _ in 0..sig.outputs {
+            self.push(ValueType::default());
+        }
+    }
+    fn node(&mut self, node: &Node) -> InferResult {
+        self.steps += 1;
+        if self.steps > MAX_STEPS {
+            return Err(Bail);
+        }
+        match node {
+            Node::Run(nodes) => {
+                for node in nodes {
+                    self.node(node)?;
+                }
+            }
+            Node::Push(val) => self.push(ValueType::of(val)),
+            Node::Array {
+                len: ArrayLen::Static(len),
+                inner,
+                boxed,
+                ..
+            } => {
+                self.node(inner)?;
+                let rows = self.pop_n(*len);
+                self.push(array_type(rows, *boxed));
+            }
+            Node::Prim(prim, span) => self.prim(*prim, *span)?,
+            Node::Mod(prim, args, span) => self.modifier(*prim, args, *span, node)?,
+            Node::Call(f, _) if self.call_depth < MAX_CALL_DEPTH => {
+                self.call_depth += 1;
+                let res = self.node(&self.asm[f]);
+                self.call_depth -= 1;
+                res?;
+            }
+            Node::BindGlobal { index, .. } => {
+                let ty = self.pop();
+                self.bound.push((*index, ty));
+            }
+            Node::PushUnder(n, _) => {
+                for ty in self.pop_n(*n) {
+                    self.under.push(ty);
+                }
+            }
+            Node::CopyToUnder(n, _) => {
+                for i in 0..*n {
+                    let ty = (self.stack.len().checked_sub(i + 1))
+                        .map(|i| self.stack[i].clone())
+                        .unwrap_or_default();
+                    self.under.push(ty);
+                }
+            }
+            Node::PopUnder(n, _) => {
+                for _ in 0..*n {
+                    let ty = self.under.pop().unwrap_or_default();
+                    self.push(ty);
+                }
+            }
+            Node::NoInline(inner) | Node::TrackCaller(inner) => self.node(inner)?,
+            node => self.opaque(node.sig().map_err(|_| Bail)?),
+        }
+        Ok(())
+    }
+    fn prim(&mut self, prim: Primitive, span: usize) -> InferResult {
+        use Primitive::*;
+        match prim {
+            Identity => {}
+            Pop => {
+                self.pop();
+            }
+            Dup => {
+                let x = self.pop();
+                self.push(x.clone());
+                self.push(x);
+            }
+            Flip => {
+                let a = self.pop();
+                let b = self.pop();
+                self.push(a);
+                self.push(b);
+            }
+            Over => {
+                let a = self.pop();
+                let b = self.pop();
+                self.push(b.clone());
+                self.push(a);
+                self.push(b);
+            }
+            Around => {
+                let a = self.pop();
+                let b = self.pop();
+                self.push(a.clone());
+                self.push(b);
+                self.push(a);
+            }
+            Len => {
+                self.pop();
+                self.push(ValueType::scalar(ElemType::Num));
+            }
+            Shape => {
+                let x = self.pop();
+                self.push(ValueType::new(Some(ElemType::Num), Some(vec![x.rank()])));
+            }
+            Box => {
+                let x = self.pop();
+                self.push(ValueType {
+                    boxed: Some(x.forget().into()),
+                    ..ValueType::scalar(ElemType::Box)
+                });
+            }
+            Reverse => {
+                let x = self.pop();
+                self.push(x.forget());
+            }
+            Deshape => {
+                let x = self.pop();
+                let shape = (x.shape.as_ref()).map(|shape| vec![shape.iter().copied().product()]);
+                self.push(x.with_shape(shape));
+            }
+            Fix => {
+                let x = self.pop();
+                let shape =
+                    (x.shape.as_ref()).map(|shape| once(Some(1)).chain(shape.clone()).collect());
+                self.push(x.with_shape(shape));
+            }
+            First | Last => {
+                let x = self.pop();
+                self.push(x.row());
+            }
+            Transpose => {
+                let x = self.pop();
+                let shape = x.shape.clone().map(|mut shape| {
+                    if !shape.is_empty() {
+                        shape.rotate_left(1);
+                    }
+                    shape
+                });
+                self.push(x.with_shape(shape));
+            }
+            Range => {
+                let x = self.pop();
+                let shape = match (x.value.as_ref().and_then(const_dims), x.rank()) {
+                    (Some(dims), Some(0)) => Some(dims),
+                    (_, Some(0)) => Some(vec![None]),
+                    _ => None,
+                };
+                self.push(ValueType::new(Some(ElemType::Num), shape));
+            }
+            Keep | Take | Drop => {
+                self.pop();
+                let x = self.pop();
+                self.push(x.with_shape(None));
+            }
+            Reshape => {
+                let dims = self.pop();
+                let x = self.pop();
+                let shape = match (dims.value.as_ref().and_then(const_dims), &dims.shape) {
+                    (Some(dims), _) => Some(dims),
+                    (_, Some(shape)) if shape.len() == 1 => shape[0].map(|n| vec![None; n]),
+                    _ => None,
+                };
+                self.push(x.with_shape(shape));
+            }
+            Couple => {
+                let a = self.pop();
+                let b = self.pop();
+                let elem = self.combine_elems("couple", &a, &b, span);
+                let shape = match (&a.shape, &b.shape) {
+                    (Some(sa), Some(sb)) if sa.len() == sb.len() => {
+                        if self.fill_depth == 0 && dims_conflict(sa, sb) {
+                            self.error(
+                                span,
+                                format!(
+                                    "Cannot couple arrays with shapes {} and {}",
+                                    a.shape_str(),
+                                    b.shape_str()
+                                ),
+                            );
+                        }
+                        Some(once(Some(2)).chain(merge_dims(sa, sb)).collect())
+                    }
+                    _ => None,
+                };
+                self.push(ValueType::new(elem, shape));
+            }
+            Join => {
+                let a = self.pop();
+                let b = self.pop();
+                let elem = self.combine_elems("join", &a, &b, span);
+                let shape = match (&a.shape, &b.shape) {
+                    _ if self.fill_depth > 0 => None,
+                    (Some(sa), Some(sb)) => {
+                        let (shape, ok) = join_shape(sa, sb);
+                        if !ok {
+                            self.error(
+                                span,
+                                format!(
+                                    "Cannot join arrays of shapes {} and {}",
+                                    a.shape_str(),
+                                    b.shape_str()
+                                ),
+                            );
+                        }
+                        shape
+                    }
+                    _ => None,
+                };
+                self.push(ValueType::new(elem, shape));
+            }
+            Select | Pick => {
+                let indices = self.pop();
+                let x = self.pop();
+                self.check_indices(&indices, span);
+                let shape = match (&indices.shape, &x.shape) {
+                    (Some(is), Some(xs)) if prim == Select => {
+                        Some(is.iter().chain(xs.iter().skip(1)).copied().collect())
+                    }
+                    (Some(is), Some(xs)) => match is.split_last() {
+                        Some((Some(n), outer)) if *n <= xs.len() => {
+                            Some(outer.iter().chain(&xs[*n..]).copied().collect())
+                        }
+                        _ => None,
+                    },
+                    _ => None,
+                };
+                self.push(x.with_shape(shape));
+            }
+            prim if prim.class() == PrimClass::MonadicPervasive => {
+                self.monadic_pervasive(prim, span)
+            }
+            prim if prim.class() == PrimClass::DyadicPervasive => self.dyadic_pervasive(prim, span),
+            prim => self.opaque(prim.sig().ok_or(Bail)?),
+        }
+        Ok(())
+    }
+    fn modifier(
+        &mut self,
+        prim: Primitive,
+        args: &[SigNode],
+        span: usize,
+        node: &Node,
+    ) -> InferResult {
+        use Primitive::*;
+        match (prim, args) {
+            (Dip, [f]) => {
+                let x = self.pop();
+                self.node(&f.node)?;
+                self.push(x);
+            }
+            (Gap, [f]) => {
+                self.pop();
+                self.node(&f.node)?;
+            }
+            (Both, [f]) => {
+                let a = self.pop_n(f.sig.args);
+                let b = self.pop_n(f.sig.args);
+                self.push_n(&b);
+                self.node(&f.node)?;
+                self.push_n(&a);
+                self.node(&f.node)?;
+            }
+            (Bracket, [f, g]) => {
+                let a = self.pop_n(f.sig.args);
+                let b = self.pop_n(g.sig.args);
+                self.push_n(&b);
+                self.node(&g.node)?;
+                self.push_n(&a);
+                self.node(&f.node)?;
+            }
+            (Fork, [f, g]) => {
+                let args = self.pop_n(f.sig.args.max(g.sig.args));
+                self.push_n(&args[..g.sig.args]);
+                self.node(&g.node)?;
+                self.push_n(&args[..f.sig.args]);
+                self.node(&f.node)?;
+            }
+            (Fill, [fill, f]) => {
+                self.node(&fill.node)?;
+                self.pop_n(fill.sig.outputs);
+                self.fill_depth += 1;
+                let res = self.node(&f.node);
+                self.fill_depth -= 1;
+                res?;
+            }
+            (Rows, [f]) => {
+                let args = self.pop_n(f.sig.args);
+                // Arrays with a single row are repeated
+                let counts: Vec<usize> = (args.iter())
+                    .filter_map(|arg| *arg.shape.as_ref()?.first()?)
+                    .filter(|&n| n != 1)
+                    .collect();
+                let row_count = counts.first().copied();
+                if let Some(n) = counts.iter().find(|&&n| Some(n) != row_count) {
+                    if self.fill_depth == 0 {
+                        self.error(
+                            span,
+                            format!(
+                                "Cannot {} arrays with different number of rows {} and {n}",
+                                prim.format(),
+                                counts[0]
+                            ),
+                        );
+                    }
+                }
+                let rows: Vec<_> = args.iter().map(ValueType::row).collect();
+                self.push_n(&rows);
+                self.node(&f.node)?;
+                for out in self.pop_n(f.sig.outputs).into_iter().rev() {
+                    let shape = (out.shape.as_ref())
+                        .map(|shape| once(row_count).chain(shape.iter().copied()).collect());
+                    self.push(out.with_shape(shape));
+                }
+            }
+            _ => self.opaque(node.sig().map_err(|_| Bail)?),
+        }
+        Ok(())
+    }
+    fn monadic_pervasive(&mut self, prim: Primitive, span: usize) {
+        use Primitive::*;
+        let x = self.pop();
+        let verb = match prim {
+            Not => Some("not"),
+            Sqrt => Some("take the square root of"),
+            Floor => Some("get the floor of"),
+            Ceil => Some("get the ceiling of"),
+            Round => Some("round"),
+            _ => None,
+        };
+        if let (Some(verb), Some(ElemType::Char)) = (verb, x.inner_elem()) {
+            self.error(span, format!("Cannot {verb} character"));
+        }
+        let elem = x.elem.map(|elem| match (prim, elem) {
+            (_, ElemType::Box) => ElemType::Box,
+            (Abs, ElemType::Complex) => ElemType::Num,
+            (_, ElemType::Complex) => ElemType::Complex,
+            (Neg | Abs, ElemType::Char) => ElemType::Char,
+            _ => ElemType::Num,
+        });
+        self.push(ValueType::new(elem, x.shape));
+    }
+    fn dyadic_pervasive(&mut self, prim: Primitive, span: usize) {
+        let a = self.pop();
+        let b = self.pop();
+        let mut elem = match (a.inner_elem(), b.inner_elem()) {
+            (Some(ea), Some(eb)) => match dyadic_elem(prim, ea, eb) {
+                Ok(elem) => elem,
+                Err(verb) => {
+                    self.error(span, format!("Cannot {verb} {ea} and {eb}"));
+                    None
+                }
+            },
+            _ => None,
+        };
+        if a.elem == Some(ElemType::Box) || b.elem == Some(ElemType::Box) {
+            elem = Some(ElemType::Box);
+        } else if a.elem.is_none() || b.elem.is_none() {
+            elem = None;
+        }
+        let shape = match (&a.shape, &b.shape) {
+            (Some(sa), Some(sb)) => {
+                let shape = pervasive_shape(sa, sb);
+                if shape.is_none() && self.fill_depth == 0 {
+                    self.error(
+                        span,
+                        format!(
+                            "Shapes {} and {} are not compatible",
+                            a.shape_str(),
+                            b.shape_str()
+                        ),
+                    );
+                }
+                shape
+            }
+            _ => None,
+        };
+        self.push(ValueType::new(elem, shape));
+    }
+    /// Get the element type of two arrays that are combined into one
+    fn combine_elems(
+        &mut self,
+        verb: &str,
+        a: &ValueType,
+        b: &ValueType,
+        span: usize,
+    ) -> Option<ElemType> {
+        use ElemType::*;
+        match (a.elem?, b.elem?) {
+            (ea, eb) if ea == eb => Some(ea),
+            (Box, _) | (_, Box) => Some(Box),
+            (ea, eb) if ea == Char || eb == Char => {
+                self.error(span, format!("Cannot {verb} {ea} array and {eb} array"));
+                None
+            }
+            (Complex, _) | (_, Complex) => Some(Complex),
+            _ => Some(Num),
+        }
+    }
+    fn check_indices(&mut self, indices: &ValueType, span: usize) {
+        let message = match indices.elem {
+            Some(ElemType::Char) => "Index must be an array of integers, not characters".into(),
+            Some(ElemType::Complex) => {
+                "Index must be an array of integers, not complex numbers".into()
+            }
+            Some(ElemType::Box) => "Index must be an array of integers, not boxes".into(),
+            _ => match &indices.value {
+                Some(Value::Num(arr)) => match arr.data.iter().find(|n| n.fract() != 0.0) {
+                    Some(n) => {
+                        format!("Index must be an array of integers, but {n} is not an integer")
+                    }
+                    None => return,
+                },
+                _ => return,
+            },
+        };
+        self.error(span, message);
+    }
+}
+
+/// Get the type of an array made from some rows
+fn array_type(rows: Vec<ValueType>, boxed: bool) -> ValueType {
+    let len = rows.len();
+    let row = rows.into_iter().map(ValueType::forget).reduce(|a, b| {
+        let elem = match (a.elem, b.elem) {
+            (Some(ElemType::Byte | ElemType::Num), Some(ElemType::Byte | ElemType::Num))
+                if a.elem != b.elem =>
+            {
+                Some(ElemType::Num)
+            }
+            (ea, eb) if ea == eb => ea,
+            _ => None,
+        };
+        ValueType { elem, ..a.union(b) }
+    });
+    if boxed {
+        return ValueType {
+            boxed: row.map(Into::into),
+            ..ValueType::new(Some(ElemType::Box), Some(vec![Some(len)]))
+        };
+    }
+    let Some(row) = row else {
+        return ValueType::new(None, Some(vec![Some(0)]));
+    };
+    let shape =
+        (row.shape.as_ref()).map(|shape| once(Some(len)).chain(shape.iter().copied()).collect());
+    row.with_shape(shape)
+}
+
+/// Get the dimensions described by a constant list of natural numbers
+///
+/// Negative or fractional numbers become unknown dimensions.
+fn const_dims(val: &Value) -> Option<Vec<Option<usize>>> {
+    if val.rank() > 1 {
+        return None;
+    }
+    match val {
+        Value::Byte(arr) => Some(arr.data.iter().map(|&n| Some(n as usize)).collect()),
+        Value::Num(arr) => Some(
+            (arr.data.iter())
+                .map(|&n| (n >= 0.0 && n.fract() == 0.0).then_some(n as usize))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Get the element type of a dyadic pervasive operation, or a description of why it fails
+fn dyadic_elem(
+    prim: Primitive,
+    a: ElemType,
+    b: ElemType,
+) -> Result<Option<ElemType>, &'static str> {
+    use ElemType::*;
+    use Primitive::{
+        Add, Atan, Div, Eq, Ge, Gt, Le, Log, Lt, Max, Min, Modulus, Mul, Ne, Pow, Sub,
+    };
+    if matches!(prim, Eq | Ne | Lt | Le | Gt | Ge) {
+        return Ok(Some(Byte));
+    }
+    let verb = match prim {
+        Add => "add",
+        Sub => "subtract",
+        Mul => "multiply",
+        Div => "divide",
+        Modulus => "modulo",
+        Pow => "get the power of",
+        Log => "get the log of",
+        Min => "get the min of",
+        Max => "get the max of",
+        Atan => "get the atan2 of",
+        _ => return Ok(None),
+    };
+    match (a, b) {
+        (Char, Char) => match prim {
+            Sub => Ok(Some(Num)),
+            Min | Max => Ok(Some(Char)),
+            _ => Err(verb),
+        },
+        (Char, Complex) | (Complex, Char) => Err(verb),
+        (Char, _) => match prim {
+            Add | Mul => Ok(Some(Char)),
+            _ => Err(verb),
+        },
+        (_, Char) => match prim {
+            Add | Sub | Mul | Div => Ok(Some(Char)),
+            _ => Err(verb),
+        },
+        (Complex, _) | (_, Complex) => Ok(Some(Complex)),
+        (Byte, Byte) if matches!(prim, Min | Max) => Ok(Some(Byte)),
+        _ => Ok(Some(Num)),
+    }
+}
+
+/// Whether two dimensions are known to be different
+fn dim_conflict(a: Option<usize>, b: Option<usize>) -> bool {
+    matches!((a, b), (Some(a), Some(b)) if a != b)
+}
+
+fn dims_conflict(a: &[Option<usize>], b: &[Option<usize>]) -> bool {
+    a.iter().zip(b).any(|(&a, &b)| dim_conflict(a, b))
+}
+
+fn merge_dims<'a>(
+    a: &'a [Option<usize>],
+    b: &'a [Option<usize>],
+) -> impl Iterator<Item = Option<usize>> + 'a {
+    (a.iter().zip(b)).map(|(&a, &b)| if dim_conflict(a, b) { None } else { a.or(b) })
+}
+
+/// Get the shape of a pervasive operation, or [`None`] if the shapes are incompatible
+fn pervasive_shape(a: &[Option<usize>], b: &[Option<usize>]) -> Option<Vec<Option<usize>>> {
+    let (long, short) = if a.len() >= b.len() { (a, b) } else { (b, a) };
+    let mut shape = long.to_vec();
+    for (dim, &other) in shape.iter_mut().zip(short) {
+        *dim = match (*dim, other) {
+            (Some(a), Some(b)) if a == b || b == 1 => Some(a),
+            (Some(1), b) => b,
+            (Some(_), Some(_)) => return None,
+            (Some(a), None) => Some(a),
+            (None, Some(1)) | (None, None) => None,
+            (None, Some(b)) => Some(b),
+        };
+    }
+    Some(shape)
+}
+
+/// Get the shape of a join and whether the shapes are compatible
+fn join_shape(a: &[Option<usize>], b: &[Option<usize>]) -> (Option<Vec<Option<usize>>>, bool) {
+    let add = |a: Option<usize>, b: Option<usize>| a.zip(b).map(|(a, b)| a + b);
+    match (a.len(), b.len()) {
+        (0, 0) => (Some(vec![Some(2)]), true),
+        (ra, rb) if ra == rb => {
+            let ok = !dims_conflict(&a[1..], &b[1..]);
+            let shape = once(add(a[0], b[0])).chain(merge_dims(&a[1..], &b[1..]));
+            (ok.then(|| shape.collect()), ok)
+        }
+        (ra, rb) if ra == rb + 1 || rb == ra + 1 => {
+            let (long, short) = if ra > rb { (a, b) } else { (b, a) };
+            let ok = !dims_conflict(&long[1..], short);
+            let shape = once(add(long[0], Some(1))).chain(merge_dims(&long[1..], short));
+            (ok.then(|| shape.collect()), ok)
+        }
+        (0, _) | (_, 0) => {
+            let long = if a.is_empty() { b } else { a };
+            let shape = once(add(long[0], Some(1))).chain(long[1..].iter().copied());
+            (Some(shape.collect()), true)
+        }
+        _ => (None, true),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infer_types() {
+        let code = "\
+F ← ⊂ [1 2] ↯2_3
+G ← ⊏ 1.5
+H ← ×@a ⊏ : \"abc\"
+I ← ⬚0⊂ [1 2] ↯2_3 ⇡
+J ← ⊟ ⇡3 ⇡ ⧻";
+        let mut comp = Compiler::new();
+        comp.infer_types(true).load_str(code).unwrap();
+        let messages: Vec<_> = (comp.take_diagnostics().into_iter())
+            .filter(|diag| diag.lint() == Some("type-error"))
+            .map(|diag| diag.message)
+            .collect();
+        assert_eq!(
+            messages,
+            [
+                "Cannot join arrays of shapes [2] and [2 × 3]",
+                "Index must be an array of integers, but 1.5 is not an integer",
+                "Cannot multiply character and character",
+            ]
+        );
+        let types = |name: &str| {
+            let binding = (comp.asm.bindings.iter())
+                .find(|b| b.span.as_str(&comp.asm.inputs, |s| s == name))
+                .unwrap();
+            let types = &comp.code_meta.inferred_types[&binding.span];
+            types.iter().map(ToString::to_string).collect::<Vec<_>>()
+        };
+        assert_eq!(types("J"), ["2×3 number"]);
+        assert_eq!(types("I"), ["… number"]);
+    }
+}
@@ -1,6 +1,9 @@
 pub(crate) mod algebra;
 mod binding;
 mod data;
+mod doc_test;
+mod incremental;
+mod infer;
 pub(crate) mod invert;
 mod modifier;
 pub(crate) mod optimize;
@@ -31,6 +34,7 @@ use crate::{
     function::DynamicFunction,
     ident_modifier_args,
     lex::{CodeSpan, Sp, Span},
+    literate,
     lsp::{CodeMeta, ImportSrc, SetInverses, SigDecl},
     parse::{flip_unsplit_lines, max_placeholder, parse, split_words},
     Array, ArrayLen, Assembly, BindingKind, Boxed, CustomInverse, Diagnostic, DiagnosticKind,
@@ -39,6 +43,11 @@ use crate::{
     Signature, SysBackend, Uiua, UiuaError, UiuaErrorKind, UiuaResult, Value, CONSTANTS,
     EXAMPLE_UA, SUBSCRIPT_DIGITS, VERSION,
 };
+pub(crate) use binding::test_scope_name;
+pub(crate) use incremental::ImportCache;
+#[cfg(feature = "lsp")]
+pub(crate) use incremental::ItemCheckpoint;
+pub use infer::{ElemType, ValueType};
 pub use pre_eval::PreEvalMode;
 
 /// The Uiua compiler
@@ -82,6 +91,14 @@ pub struct Compiler {
     pre_eval_mode: PreEvalMode,
     /// The interpreter used for comptime code
     macro_env: Uiua,
+    /// Whether to compile doc comment examples as tests
+    doc_tests: bool,
+    /// Only compile test scopes whose names contain this pattern
+    test_filter: Option<String>,
+    /// Whether to infer the types and shapes of values
+    infer_types: bool,
+    /// Parsed and compiled modules shared between compilers
+    import_cache: Option<ImportCache>,
 }
 
 impl Default for Compiler {
@@ -107,11 +124,15 @@ impl Default for Compiler {
             comptime: true,
             pre_eval_mode: PreEvalMode::default(),
             macro_env: Uiua::default(),
+            doc_tests: false,
+            test_filter: None,
+            infer_types: false,
+            import_cache: None,
         }
     }
 }
 
-#[derive(Default)]
+#[derive(Default, PartialEq)]
 struct BindingPrelude {
     comment: Option<EcoString>,
     track_caller: bool,
@@ -299,6 +320,42 @@ impl Compiler {
         self.mode = mode;
         self
     }
+    /// Set whether to compile the examples in doc comments as tests
+    ///
+    /// Examples are lines of doc comments that start with `ex:`.
+    /// An example may end with an output comment containing its expected result.
+    /// Examples that start with `ex!` are expected to fail.
+    ///
+    /// Like other tests, examples only run in [`RunMode::Test`] or [`RunMode::All`].
+    ///
+    /// Defaults to false
+    pub fn doc_tests(&mut self, doc_tests: bool) -> &mut Self {
+        self.doc_tests = doc_tests;
+        self
+    }
+    /// Only compile test scopes whose names contain a pattern
+    ///
+    /// A test scope's name is its first comment line. Doc comment examples are named after their binding.
+    pub fn test_filter(&mut self, filter: Option<String>) -> &mut Self {
+        self.test_filter = filter;
+        self
+    }
+    /// Set whether to infer the types and shapes of values at compile time
+    ///
+    /// Operations that will likely fail, like joining arrays with incompatible shapes,
+    /// are reported as warnings. The inferred types of bindings are kept for tooling.
+    ///
+    /// Defaults to false
+    pub fn infer_types(&mut self, infer_types: bool) -> &mut Self {
+        self.infer_types = infer_types;
+        self
+    }
+    /// Share parsed and compiled imported modules with other compilers
+    #[cfg(feature = "lsp")]
+    pub(crate) fn import_cache(&mut self, cache: ImportCache) -> &mut Self {
+        self.import_cache = Some(cache);
+        self
+    }
     /// Enable experimental features
     pub fn experimental(&mut self, experimental: bool) -> &mut Self {
         self.scope.experimental = experimental;
@@ -327,9 +384,12 @@ impl Compiler {
     /// Compile a Uiua file from a file at a path
     pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> UiuaResult<&mut Self> {
         let path = path.as_ref();
-        let input: EcoString = fs::read_to_string(path)
+        let mut input: EcoString = fs::read_to_string(path)
             .map_err(|e| UiuaErrorKind::Load(path.into(), e.into()))?
             .into();
+        if literate::is_markdown_path(path) {
+            input = literate::extract_uiua(&input).into();
+        }
         // _ = crate::lsp::Spans::from_input(&input);
         self.asm.inputs.files.insert(path.into(), input.clone());
         self.load_impl(&input, InputSrc::File(path.into()))
@@ -388,14 +448,39 @@ impl Compiler {
     }
     fn load_impl(&mut self, input: &str, src: InputSrc) -> UiuaResult<&mut Self> {
         let node_start = self.asm.root.len();
-        let (items, errors, diagnostics) = parse(input, src.clone(), &mut self.asm.inputs);
+        let (items, errors, diagnostics) = self.parse_file(input, &src);
         for diagnostic in diagnostics {
             self.emit_diagnostic_impl(diagnostic);
         }
-        if !errors.is_empty() {
+        // Well-formed items are only compiled after syntax errors for the sake of tooling
+        if !errors.is_empty() && self.pre_eval_mode != PreEvalMode::Lsp {
             return Err(UiuaErrorKind::Parse(errors, self.asm.inputs.clone().into()).into());
         }
-        if let InputSrc::File(path) = &src {
+        self.begin_file(&src);
+
+        let binding_start = self.asm.bindings.len();
+        let error_count = self.errors.len();
+        let mut res = self.catching_crash(input, |env| env.items(items, false));
+        if !errors.is_empty() {
+            // Only syntax errors are reported
+            self.errors.truncate(error_count);
+            res = Ok(Err(UiuaErrorKind::Parse(
+                errors,
+                self.asm.inputs.clone().into(),
+            )
+            .into()));
+        }
+        if self.doc_tests && self.higher_scopes.is_empty() && matches!(res, Ok(Ok(()))) {
+            res = self.catching_crash(input, |env| {
+                env.compile_doc_tests(input, &src, binding_start);
+                Ok(())
+            });
+        }
+        self.finish_file(&src, node_start, res)
+    }
+    /// Mark a file as being compiled
+    fn begin_file(&mut self, src: &InputSrc) {
+        if let InputSrc::File(path) = src {
             self.current_imports.push(path.to_path_buf());
             self.scope.file_path = Some(if path.is_absolute() {
                 current_dir()
@@ -406,9 +491,17 @@ impl Compiler {
                 path.to_path_buf()
             });
         }
-
-        let res = self.catching_crash(input, |env| env.items(items, false));
-
+    }
+    /// Optimize a compiled file and collect its errors
+    fn finish_file(
+        &mut self,
+        src: &InputSrc,
+        node_start: usize,
+        res: UiuaResult<UiuaResult>,
+    ) -> UiuaResult<&mut Self> {
+        if self.infer_types && self.errors.is_empty() && matches!(res, Ok(Ok(()))) {
+            self.infer_file(src, node_start);
+        }
         // Optimize root
         self.asm.root.optimize();
         // Optimize and pre-eval functions
@@ -428,7 +521,7 @@ impl Compiler {
             }
         }
 
-        if let InputSrc::File(_) = &src {
+        if let InputSrc::File(_) = src {
             self.current_imports.pop();
         }
         match res {
@@ -467,7 +560,23 @@ code:
         }
     }
     pub(crate) fn items(&mut self, items: Vec<Item>, from_macro: bool) -> UiuaResult {
-        // Set scope comment
+        self.set_scope_comment(&items);
+        let mut prelude = BindingPrelude::default();
+        let mut item_errored = false;
+        let mut items = VecDeque::from(items);
+        while let Some(item) = items.pop_front() {
+            let must_run = item_must_run(&item, &items, from_macro);
+            if let Err(e) = self.item(item, from_macro, must_run, &mut prelude) {
+                if !item_errored {
+                    self.errors.push(e);
+                }
+                item_errored = true;
+            }
+        }
+        Ok(())
+    }
+    /// Set the scope comment from the first item
+    fn set_scope_comment(&mut self, items: &[Item]) {
         if let Some(Item::Words(lines)) = items.first() {
             let mut started = false;
             let mut comment = String::new();
@@ -498,37 +607,6 @@ code:
                 self.scope.comment = Some(comment.trim().into());
             }
         }
-
-        let mut prelude = BindingPrelude::default();
-        let mut item_errored = false;
-        let mut items = VecDeque::from(items);
-        while let Some(item) = items.pop_front() {
-            let must_run = from_macro
-                || matches!(&item, Item::Words(_))
-                    && items.iter().any(|item| match item {
-                        Item::Binding(binding)
-                            if (binding.words.iter())
-                                .filter(|word| word.value.is_code())
-                                .count()
-                                == 0 =>
-                        {
-                            true
-                        }
-                        Item::Words(lines) => lines.iter().any(|line| {
-                            line.iter().find(|w| w.value.is_code()).is_some_and(|w| {
-                                matches!(w.value, Word::Primitive(Primitive::Assert))
-                            })
-                        }),
-                        _ => false,
-                    });
-            if let Err(e) = self.item(item, from_macro, must_run, &mut prelude) {
-                if !item_errored {
-                    self.errors.push(e);
-                }
-                item_errored = true;
-            }
-        }
-        Ok(())
     }
     fn item(
         &mut self,
@@ -543,6 +621,7 @@ code:
             Item::Binding(binding) => self.binding(binding, take(prelude)),
             Item::Import(import) => self.import(import, take(prelude).comment),
             Item::Data(data) => self.data_def(data, true, take(prelude)),
+            Item::Error(_) => Ok(()),
         }
     }
     /// Compile top-level words
@@ -604,7 +683,14 @@ code:
                 once(&line).chain(&lines).any(|line| {
                     line.iter()
                         .find(|w| w.value.is_code())
-                        .is_some_and(|w| matches!(w.value, Word::Primitive(Primitive::Assert)))
+                        .is_some_and(|w| match &w.value {
+                            Word::Primitive(Primitive::Assert) => true,
+                            Word::Modified(m) => matches!(
+                                m.modifier.value,
+                                Modifier::Primitive(Primitive::Property | Primitive::Bench)
+                            ),
+                            _ => false,
+                        })
                 })
             };
             if line.is_empty()
@@ -719,7 +805,8 @@ code:
             let comment = DocComment::from(text);
             if let Some(sig) = &comment.sig {
                 if !sig.matches_sig(function.sig) {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "signature-mismatch",
                         format!(
                             "{}'s comment describes {}, \
                             but its code has signature {}",
@@ -750,7 +837,8 @@ code:
         let comment = comment.map(|text| {
             let comment = DocComment::from(text);
             if let Some(sig) = &comment.sig {
-                self.emit_diagnostic(
+                self.emit_lint(
+                    "signature-mismatch",
                     format!(
                         "{}'s comment describes {}, \
                         but it is a constant",
@@ -799,7 +887,8 @@ code:
                     url = format!("/{url}");
                 }
                 url = format!("github.com{url}");
-                self.emit_diagnostic(
+                self.emit_lint(
+                    "deprecated",
                     "Implicit GitHub URLs are deprecated and \
                     will be removed in the future. Prefix the \
                     URL with `github.com/` or `https://github.com/`.",
@@ -862,10 +951,12 @@ code:
                         format!("Cycle detected importing {}", path.to_string_lossy()),
                     ));
                 }
-                let (module, ()) = self.in_scope(ScopeKind::File(file_kind), |comp| {
-                    comp.load_str_src(&input, &path).map(drop)
-                })?;
-                self.imports.insert(path.clone(), module);
+                if !self.import_cached(&path, &input, file_kind) {
+                    let (module, ()) = self.in_scope(ScopeKind::File(file_kind), |comp| {
+                        comp.load_str_src(&input, &path).map(drop)
+                    })?;
+                    self.imports.insert(path.clone(), module);
+                }
                 #[cfg(target_arch = "wasm32")]
                 if file_kind == FileScopeKind::Git {
                     GIT_CACHE.with(|cache| {
@@ -913,7 +1004,8 @@ code:
         if let Some(comment_sig) = comment_sig {
             if let Ok(sig) = node.sig() {
                 if !is_empty && !comment_sig.value.matches_sig(sig) {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "signature-mismatch",
                         format!("Line signature {sig} does not match comment"),
                         DiagnosticKind::Warning,
                         comment_sig.span.clone(),
@@ -961,6 +1053,11 @@ code:
         };
         Ok(SigNode::new(sig, node))
     }
+    /// Get the signature of some words as if they were compiled in the current scope
+    pub(crate) fn words_nodes_sig(&mut self, words: Vec<Sp<Word>>) -> Option<Signature> {
+        let node = self.words(words).ok()?;
+        nodes_sig(node.as_slice()).ok()
+    }
     fn words(&mut self, mut words: Vec<Sp<Word>>) -> UiuaResult<Node> {
         // Filter out non-code words
         words.retain(|word| word.value.is_code());
@@ -1019,7 +1116,8 @@ code:
                     )),
                     Some(Primitive::Flip),
                 ) => {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "redundant-stack-op",
                         format!(
                             "Prefer {} over {} {} here",
                             Primitive::On,
@@ -1103,6 +1201,21 @@ code:
                 _ => {}
             }
 
+            // Matches tested directly by an assert record a diff for failure reports
+            if self.mode != RunMode::Normal && modif.is_none() && prim == Some(Primitive::Assert) {
+                if let Some(last) = nodes.as_mut_slice().last_mut() {
+                    if let Node::Mod(Primitive::With, ops, span) = last {
+                        if let [SigNode {
+                            node: Node::Prim(Primitive::Match, _),
+                            ..
+                        }] = ops.as_slice()
+                        {
+                            *last = Node::ImplPrim(ImplPrimitive::TestMatch, *span);
+                        }
+                    }
+                }
+            }
+
             // Compile the word
             let node = self.word(word)?;
             let sig = node.sig().ok();
@@ -1218,7 +1331,8 @@ code:
                         |instr| matches!(instr, Node::Push(Value::Char(arr)) if arr.rank() == 0),
                     )
                 {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "char-array",
                         "Stranded characters should instead be written as a string",
                         DiagnosticKind::Advice,
                         word.span.clone(),
@@ -1315,7 +1429,8 @@ code:
                         |instr| matches!(instr, Node::Push(Value::Char(arr)) if arr.rank() == 0),
                     )
                 {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "char-array",
                         "An array of characters should instead be written as a string",
                         DiagnosticKind::Advice,
                         word.span.clone(),
@@ -1810,7 +1925,8 @@ code:
             } else {
                 format!(", {suggestion}")
             };
-            self.emit_diagnostic(
+            self.emit_lint(
+                "deprecated",
                 format!(
                     "{} is deprecated and will be removed in a future version{}",
                     prim.format(),
@@ -1838,6 +1954,10 @@ code:
     }
     fn primitive(&mut self, prim: Primitive, span: CodeSpan) -> Node {
         self.validate_primitive(prim, &span);
+        if prim == Primitive::Sys(crate::SysOp::Snapshot) {
+            // Snapshots are reported like test assertions
+            self.asm.test_assert_count += 1;
+        }
         let span = self.add_span(span);
         Node::Prim(prim, span)
     }
@@ -2056,6 +2176,21 @@ code:
             )
         });
     }
+    /// Get the names bound in the current scope
+    #[cfg_attr(not(feature = "native_sys"), allow(dead_code))]
+    pub(crate) fn scope_names(&self) -> impl Iterator<Item = (&Ident, &LocalName)> {
+        self.scope.names.iter()
+    }
+    /// Get the top level comment of the current scope
+    #[cfg_attr(not(feature = "native_sys"), allow(dead_code))]
+    pub(crate) fn scope_comment(&self) -> Option<&str> {
+        self.scope.comment.as_deref()
+    }
+    /// Get the modules that have been imported from files
+    #[cfg_attr(not(feature = "native_sys"), allow(dead_code))]
+    pub(crate) fn imported_modules(&self) -> &HashMap<PathBuf, Module> {
+        &self.imports
+    }
     /// Get all diagnostics
     pub fn diagnostics(&self) -> &BTreeSet<Diagnostic> {
         &self.diagnostics
@@ -2080,6 +2215,18 @@ code:
         let inputs = self.asm.inputs.clone();
         self.emit_diagnostic_impl(Diagnostic::new(message.into(), span, kind, inputs));
     }
+    /// Construct and add a diagnostic that belongs to a lint rule
+    pub(crate) fn emit_lint(
+        &mut self,
+        lint: &'static str,
+        message: impl Into<String>,
+        kind: DiagnosticKind,
+        span: impl Into<Span>,
+    ) {
+        let inputs = self.asm.inputs.clone();
+        let diagnostic = Diagnostic::new(message.into(), span, kind, inputs).with_lint(lint);
+        self.emit_diagnostic_impl(diagnostic);
+    }
     fn emit_diagnostic_impl(&mut self, diagnostic: Diagnostic) {
         if self.print_diagnostics {
             println!("{}", diagnostic.report()); // Allow println
@@ -2225,6 +2372,28 @@ code:
     }
 }
 
+/// Whether top-level words must be run because of later items
+fn item_must_run(item: &Item, later: &VecDeque<Item>, from_macro: bool) -> bool {
+    from_macro
+        || matches!(item, Item::Words(_))
+            && later.iter().any(|item| match item {
+                Item::Binding(binding)
+                    if (binding.words.iter())
+                        .filter(|word| word.value.is_code())
+                        .count()
+                        == 0 =>
+                {
+                    true
+                }
+                Item::Words(lines) => lines.iter().any(|line| {
+                    line.iter()
+                        .find(|w| w.value.is_code())
+                        .is_some_and(|w| matches!(w.value, Word::Primitive(Primitive::Assert)))
+                }),
+                _ => false,
+            })
+}
+
 fn words_look_pervasive(words: &[Sp<Word>]) -> bool {
     use Primitive::*;
     words.iter().all(|word| match &word.value {
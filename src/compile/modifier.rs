@@ -363,6 +363,10 @@ impl Compiler {
         };
 
         let span = self.add_span(modified.modifier.span.clone());
+        if prim == Primitive::Property {
+            // Properties are reported like test assertions
+            self.asm.test_assert_count += 1;
+        }
 
         // Compile operands
         let ops = self.args(modified.operands)?;
@@ -449,7 +453,8 @@ impl Compiler {
             Fork => {
                 let (f, g, f_span, _) = self.dyadic_modifier_ops(modified)?;
                 if !modified.pack_expansion && f.node.as_primitive() == Some(Primitive::Identity) {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "redundant-stack-op",
                         "Prefer `⟜` over `⊃∘` for clarity",
                         DiagnosticKind::Style,
                         modified.modifier.span.clone().merge(f_span),
@@ -496,7 +501,8 @@ impl Compiler {
                 let (SigNode { mut node, sig }, _) = self.monadic_modifier_op(modified)?;
                 match sig.args {
                     0 | 1 => {
-                        self.emit_diagnostic(
+                        self.emit_lint(
+                            "redundant-stack-op",
                             format!(
                                 "Remove {} here, as it does nothing for {} functions",
                                 Backward.format(),
@@ -809,7 +815,8 @@ impl Compiler {
                     && words_look_pervasive(slice::from_ref(&operand));
                 let sn = self.word_sig(operand)?;
                 if words_look_pervasive {
-                    self.emit_diagnostic(
+                    self.emit_lint(
+                        "redundant-modifier",
                         if let Some((prim, _)) = sn
                             .node
                             .as_flipped_primitive()
@@ -868,12 +875,14 @@ impl Compiler {
                 // Normal table compilation, but get some diagnostics
                 let (sn, span) = self.monadic_modifier_op(modified)?;
                 match sn.sig.args {
-                    0 => self.emit_diagnostic(
+                    0 => self.emit_lint(
+                        "redundant-modifier",
                         format!("{} of 0 arguments is redundant", Table.format()),
                         DiagnosticKind::Advice,
                         span,
                     ),
-                    1 => self.emit_diagnostic(
+                    1 => self.emit_lint(
+                        "redundant-modifier",
                         format!(
                             "{} with 1 argument is just {rows}. \
                             Use {rows} instead.",
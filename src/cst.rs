@@ -0,0 +1,420 @@
+//! Uiua's lossless concrete syntax tree
+//!
+//! Unlike the [AST](crate::ast), the CST keeps every token, including whitespace and comments.
+//! Printing a tree gives back exactly the source it was parsed from.
+
+use std::{fmt, iter::once};
+
+use ecow::EcoString;
+
+use crate::{
+    ast::*,
+    lex::{CodeSpan, Sp, Token},
+    parse::parse_with_tokens,
+    Diagnostic, Inputs, IntoInputSrc, ParseError, Signature,
+};
+
+/// A node in a concrete syntax tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    /// The kind of node
+    pub kind: NodeKind,
+    /// The child nodes and tokens
+    pub children: Vec<SyntaxElement>,
+}
+
+/// A child of a [`SyntaxNode`]
+#[derive(Debug, Clone, PartialEq)]
+#[allow(missing_docs)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// A token in a concrete syntax tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    /// The lexed token, or [`None`] for source text that the lexer skipped
+    pub kind: Option<Token>,
+    /// The text of the token
+    pub text: EcoString,
+    /// The span of the token, or [`None`] if it was not parsed from source
+    pub span: Option<CodeSpan>,
+}
+
+/// A kind of [`SyntaxNode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[allow(missing_docs)]
+pub enum NodeKind {
+    /// A whole file
+    Root,
+    Binding,
+    Import,
+    ImportLine,
+    Module,
+    Data,
+    DataField,
+    /// Code that could not be parsed
+    Error,
+    /// A line of code
+    Line,
+    Signature,
+    Number,
+    Char,
+    String,
+    MultilineString,
+    FormatString,
+    MultilineFormatString,
+    Label,
+    Ref,
+    IncompleteRef,
+    Strand,
+    Array,
+    Func,
+    Pack,
+    Primitive,
+    Modified,
+    Placeholder,
+    BreakLine,
+    FlipLine,
+    SemanticComment,
+    OutputComment,
+    Subscripted,
+    InlineMacro,
+}
+
+/// Parse Uiua code into a concrete syntax tree
+///
+/// The tree is built even if there are parse errors
+pub fn parse_cst(
+    input: &str,
+    src: impl IntoInputSrc,
+    inputs: &mut Inputs,
+) -> (SyntaxNode, Vec<Sp<ParseError>>, Vec<Diagnostic>) {
+    let (items, errors, diagnostics, tokens) = parse_with_tokens(input, src, inputs);
+    let mut builder = Builder {
+        input,
+        tokens: &tokens,
+        index: 0,
+        pos: 0,
+    };
+    let mut root = builder.node(NodeKind::Root, input.len(), items_protos(&items));
+    builder.gap(&mut root, input.len());
+    (root, errors, diagnostics)
+}
+
+impl SyntaxNode {
+    /// Create a new node
+    pub fn new(kind: NodeKind, children: impl IntoIterator<Item = SyntaxElement>) -> Self {
+        SyntaxNode {
+            kind,
+            children: children.into_iter().collect(),
+        }
+    }
+    /// Get the span of the node's source tokens
+    pub fn span(&self) -> Option<CodeSpan> {
+        let mut spans = self.tokens().filter_map(|token| token.span.clone());
+        let first = spans.next()?;
+        Some(spans.fold(first, CodeSpan::merge))
+    }
+    /// Iterate over all tokens in the node, in order
+    pub fn tokens(&self) -> Box<dyn Iterator<Item = &SyntaxToken> + '_> {
+        Box::new(self.children.iter().flat_map(|child| match child {
+            SyntaxElement::Node(node) => node.tokens(),
+            SyntaxElement::Token(token) => Box::new(once(token)),
+        }))
+    }
+    /// Iterate over the node and all of its descendant nodes, in order
+    pub fn nodes(&self) -> Box<dyn Iterator<Item = &SyntaxNode> + '_> {
+        Box::new(
+            once(self).chain(self.children.iter().flat_map(|child| match child {
+                SyntaxElement::Node(node) => node.nodes(),
+                SyntaxElement::Token(_) => Box::new([].into_iter()),
+            })),
+        )
+    }
+    /// Find the first node, in order, that matches a predicate
+    pub fn find_mut(&mut self, f: &impl Fn(&SyntaxNode) -> bool) -> Option<&mut SyntaxNode> {
+        if f(self) {
+            return Some(self);
+        }
+        self.children.iter_mut().find_map(|child| match child {
+            SyntaxElement::Node(node) => node.find_mut(f),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+    /// Get the text of the node without leading or trailing trivia
+    pub fn trimmed_text(&self) -> String {
+        let tokens: Vec<_> = self.tokens().collect();
+        let start = tokens.iter().position(|t| !t.is_trivia());
+        let end = tokens.iter().rposition(|t| !t.is_trivia());
+        let (Some(start), Some(end)) = (start, end) else {
+            return String::new();
+        };
+        tokens[start..=end]
+            .iter()
+            .map(|t| t.text.as_str())
+            .collect()
+    }
+}
+
+impl SyntaxToken {
+    /// Create a new token that was not parsed from source
+    pub fn new(kind: Option<Token>, text: impl Into<EcoString>) -> Self {
+        SyntaxToken {
+            kind,
+            text: text.into(),
+            span: None,
+        }
+    }
+    /// Whether the token is whitespace or a comment
+    pub fn is_trivia(&self) -> bool {
+        matches!(
+            self.kind,
+            None | Some(Token::Spaces | Token::Newline | Token::Comment)
+        )
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.tokens().try_for_each(|token| f.write_str(&token.text))
+    }
+}
+
+impl fmt::Display for SyntaxElement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SyntaxElement::Node(node) => node.fmt(f),
+            SyntaxElement::Token(token) => f.write_str(&token.text),
+        }
+    }
+}
+
+/// The shape of a node before tokens are attached
+struct Proto {
+    kind: NodeKind,
+    start: usize,
+    end: usize,
+    children: Vec<Proto>,
+}
+
+impl Proto {
+    fn new(kind: NodeKind, span: &CodeSpan, mut children: Vec<Proto>) -> Self {
+        children.sort_by_key(|child| child.start);
+        Proto {
+            kind,
+            start: span.start.byte_pos as usize,
+            end: span.end.byte_pos as usize,
+            children,
+        }
+    }
+}
+
+fn items_protos(items: &[Item]) -> Vec<Proto> {
+    let mut protos = Vec::new();
+    for item in items {
+        match item {
+            Item::Words(lines) => protos.extend(lines_protos(lines)),
+            Item::Binding(binding) => {
+                let mut children = signature_proto(&binding.signature);
+                children.extend(words_protos(&binding.words));
+                protos.push(Proto::new(NodeKind::Binding, &binding.span(), children));
+            }
+            Item::Import(import) => {
+                let lines = import.lines.iter().flatten();
+                let children = lines.clone().map(|line| {
+                    let span = (line.items.iter()).fold(line.tilde_span.clone(), |span, item| {
+                        span.merge(item.span.clone())
+                    });
+                    Proto::new(NodeKind::ImportLine, &span, Vec::new())
+                });
+                let span = (lines.map(|line| line.tilde_span.clone()))
+                    .fold(import.span(), CodeSpan::merge)
+                    .merge(import.tilde_span.clone());
+                protos.push(Proto::new(NodeKind::Import, &span, children.collect()));
+            }
+            Item::Module(module) => {
+                let children = items_protos(&module.value.items);
+                protos.push(Proto::new(NodeKind::Module, &module.span, children));
+            }
+            Item::Data(data) => {
+                let fields = data.fields.iter().flat_map(|fields| &fields.fields);
+                let mut children: Vec<_> = fields
+                    .map(|field| {
+                        let mut children = Vec::new();
+                        if let Some(validator) = &field.validator {
+                            children.extend(words_protos(&validator.words));
+                        }
+                        if let Some(init) = &field.init {
+                            children.extend(words_protos(&init.words));
+                        }
+                        Proto::new(NodeKind::DataField, &field.span(), children)
+                    })
+                    .collect();
+                if let Some(words) = &data.func {
+                    children.extend(words_protos(words));
+                }
+                protos.push(Proto::new(NodeKind::Data, &data.span(), children));
+            }
+            Item::Error(span) => protos.push(Proto::new(NodeKind::Error, span, Vec::new())),
+        }
+    }
+    protos
+}
+
+fn lines_protos(lines: &[Vec<Sp<Word>>]) -> Vec<Proto> {
+    (lines.iter())
+        .filter_map(|line| {
+            let span = (line.iter())
+                .map(|word| word.span.clone())
+                .reduce(CodeSpan::merge)?;
+            Some(Proto::new(NodeKind::Line, &span, words_protos(line)))
+        })
+        .collect()
+}
+
+fn signature_proto(sig: &Option<Sp<Signature>>) -> Vec<Proto> {
+    (sig.iter())
+        .map(|sig| Proto::new(NodeKind::Signature, &sig.span, Vec::new()))
+        .collect()
+}
+
+fn func_children(func: &Func) -> Vec<Proto> {
+    let mut children = signature_proto(&func.signature);
+    children.extend(lines_protos(&func.lines));
+    children
+}
+
+fn func_proto(func: &Sp<Func>) -> Proto {
+    Proto::new(NodeKind::Func, &func.span, func_children(&func.value))
+}
+
+fn words_protos(words: &[Sp<Word>]) -> Vec<Proto> {
+    words.iter().filter_map(word_proto).collect()
+}
+
+fn word_proto(word: &Sp<Word>) -> Option<Proto> {
+    let (kind, children) = match &word.value {
+        Word::Comment(_) | Word::Spaces => return None,
+        Word::Number(_) => (NodeKind::Number, Vec::new()),
+        Word::Char(_) => (NodeKind::Char, Vec::new()),
+        Word::String(_) => (NodeKind::String, Vec::new()),
+        Word::MultilineString(_) => (NodeKind::MultilineString, Vec::new()),
+        Word::FormatString(_) => (NodeKind::FormatString, Vec::new()),
+        Word::MultilineFormatString(_) => (NodeKind::MultilineFormatString, Vec::new()),
+        Word::Label(_) => (NodeKind::Label, Vec::new()),
+        Word::Ref(_) => (NodeKind::Ref, Vec::new()),
+        Word::IncompleteRef { .. } => (NodeKind::IncompleteRef, Vec::new()),
+        Word::Strand(items) => (NodeKind::Strand, words_protos(items)),
+        Word::Array(arr) => {
+            let mut children = signature_proto(&arr.signature);
+            children.extend(lines_protos(&arr.lines));
+            (NodeKind::Array, children)
+        }
+        Word::Func(func) => (NodeKind::Func, func_children(func)),
+        Word::Pack(pack) => (
+            NodeKind::Pack,
+            pack.branches.iter().map(func_proto).collect(),
+        ),
+        Word::Primitive(_) => (NodeKind::Primitive, Vec::new()),
+        Word::Modified(m) => (NodeKind::Modified, words_protos(&m.operands)),
+        Word::Placeholder(_) => (NodeKind::Placeholder, Vec::new()),
+        Word::BreakLine => (NodeKind::BreakLine, Vec::new()),
+        Word::FlipLine => (NodeKind::FlipLine, Vec::new()),
+        Word::SemanticComment(_) => (NodeKind::SemanticComment, Vec::new()),
+        Word::OutputComment { .. } => (NodeKind::OutputComment, Vec::new()),
+        Word::Subscripted(sub) => (
+            NodeKind::Subscripted,
+            word_proto(&sub.word).into_iter().collect(),
+        ),
+        Word::InlineMacro(mac) => (NodeKind::InlineMacro, vec![func_proto(&mac.func)]),
+    };
+    Some(Proto::new(kind, &word.span, children))
+}
+
+/// Attaches tokens to nodes
+///
+/// Tokens go to the innermost node whose span contains their start,
+/// and any source text between tokens goes to the node of the following token.
+struct Builder<'a> {
+    input: &'a str,
+    tokens: &'a [Sp<Token>],
+    index: usize,
+    /// The byte position up to which the source has been attached
+    pos: usize,
+}
+
+impl Builder<'_> {
+    fn node(&mut self, kind: NodeKind, end: usize, children: Vec<Proto>) -> SyntaxNode {
+        let mut node = SyntaxNode::new(kind, []);
+        let mut children = children.into_iter().peekable();
+        while let Some(token) = self.tokens.get(self.index) {
+            let start = token.span.start.byte_pos as usize;
+            if start >= end {
+                break;
+            }
+            while children.next_if(|child| child.end <= start).is_some() {}
+            if let Some(child) = children.next_if(|child| child.start <= start) {
+                let child = self.node(child.kind, child.end.min(end), child.children);
+                if !child.children.is_empty() {
+                    node.children.push(SyntaxElement::Node(child));
+                }
+                continue;
+            }
+            self.gap(&mut node, start);
+            let end = (token.span.end.byte_pos as usize).max(self.pos);
+            node.children.push(SyntaxElement::Token(SyntaxToken {
+                kind: Some(token.value.clone()),
+                text: self.input[self.pos..end].into(),
+                span: Some(token.span.clone()),
+            }));
+            self.pos = end;
+            self.index += 1;
+        }
+        node
+    }
+    /// Attach any unattached source text before a position
+    fn gap(&mut self, node: &mut SyntaxNode, end: usize) {
+        if end > self.pos {
+            let text = &self.input[self.pos..end];
+            node.children
+                .push(SyntaxElement::Token(SyntaxToken::new(None, text)));
+            self.pos = end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tests::test_files;
+
+    #[test]
+    fn cst_round_trip() {
+        for path in test_files(|_| true) {
+            let input = std::fs::read_to_string(&path).unwrap();
+            let (root, _, _) = parse_cst(&input, path.as_path(), &mut Inputs::default());
+            assert_eq!(
+                root.to_string(),
+                input,
+                "{} did not round-trip",
+                path.display()
+            );
+        }
+        let code = "F ← +1 # comment\n\n(×2\n  3";
+        let (mut root, errors, _) = parse_cst(code, (), &mut Inputs::default());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(root.to_string(), code);
+        let binding = root
+            .find_mut(&|node| node.kind == NodeKind::Binding)
+            .unwrap();
+        assert_eq!(binding.trimmed_text(), "F ← +1");
+        // Edit the tree and print it back
+        let num = binding
+            .find_mut(&|node| node.kind == NodeKind::Number)
+            .unwrap();
+        num.children = vec![SyntaxElement::Token(SyntaxToken::new(None, "2"))];
+        assert_eq!(root.to_string(), "F ← +2 # comment\n\n(×2\n  3");
+    }
+}
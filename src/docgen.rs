@@ -0,0 +1,720 @@
+//! Generate documentation for Uiua modules
+//!
+//! Documentation is generated from the public bindings of a module and all the modules it imports.
+//! Each module gets its own page. Doc comment examples are run and their output is included.
+
+use std::{
+    any::Any,
+    collections::{HashMap, VecDeque},
+    fmt::{self, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+
+use crate::{
+    BindingKind, Compiler, DocComment, DocCommentExample, GitTarget, Ident, LocalName, NativeSys,
+    PreEvalMode, RunMode, SafeSys, SysBackend, Uiua, UiuaResult, Value,
+};
+
+/// The output format of generated documentation
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DocFormat {
+    /// A static HTML site
+    #[default]
+    Html,
+    /// Markdown files
+    Markdown,
+}
+
+impl DocFormat {
+    /// The file extension used for pages
+    pub fn extension(&self) -> &'static str {
+        match self {
+            DocFormat::Html => "html",
+            DocFormat::Markdown => "md",
+        }
+    }
+}
+
+impl FromStr for DocFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "html" => Ok(DocFormat::Html),
+            "markdown" | "md" => Ok(DocFormat::Markdown),
+            _ => Err(format!("unknown doc format `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for DocFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DocFormat::Html => write!(f, "html"),
+            DocFormat::Markdown => write!(f, "markdown"),
+        }
+    }
+}
+
+/// A page of generated documentation
+#[derive(Debug, Clone)]
+pub struct DocPage {
+    /// The file name of the page
+    pub file_name: String,
+    /// The contents of the page
+    pub content: String,
+}
+
+/// Generate documentation for the module at a path and all the modules it imports
+///
+/// The page for the module itself is always first and is named `index`.
+pub fn generate_docs(path: &Path, format: DocFormat) -> UiuaResult<Vec<DocPage>> {
+    let mut comp = Compiler::with_backend(DocSys::compile());
+    comp.mode(RunMode::Normal)
+        .print_diagnostics(false)
+        .load_file(path)?;
+
+    // Collect modules
+    let mut modules = vec![ModuleSource {
+        path: path.to_path_buf(),
+        comment: comp.scope_comment().map(Into::into),
+        names: (comp.scope_names())
+            .map(|(name, local)| (name.clone(), *local))
+            .collect(),
+    }];
+    let mut queue: VecDeque<usize> = [0].into();
+    while let Some(i) = queue.pop_front() {
+        let imports: Vec<PathBuf> = (modules[i].names.iter())
+            .filter_map(
+                |(_, local)| match &comp.assembly().bindings[local.index].kind {
+                    BindingKind::Import(path) => Some(path.clone()),
+                    _ => None,
+                },
+            )
+            .collect();
+        for path in imports {
+            if modules.iter().any(|m| m.path == path) {
+                continue;
+            }
+            let Some(module) = comp.imported_modules().get(&path) else {
+                continue;
+            };
+            modules.push(ModuleSource {
+                path,
+                comment: module.comment.as_ref().map(Into::into),
+                names: (module.names.iter())
+                    .map(|(name, local)| (name.clone(), *local))
+                    .collect(),
+            });
+            queue.push_back(modules.len() - 1);
+        }
+    }
+
+    // Assign file names
+    let mut file_names: Vec<String> = Vec::new();
+    for (i, module) in modules.iter().enumerate() {
+        let stem = if i == 0 {
+            "index".into()
+        } else {
+            let stem = module.path.file_stem().unwrap_or_default();
+            let stem: String = (stem.to_string_lossy().chars())
+                .map(|c| if c.is_alphanumeric() { c } else { '_' })
+                .collect();
+            if stem == "index" {
+                "index_".into()
+            } else {
+                stem
+            }
+        };
+        let mut name = format!("{stem}.{}", format.extension());
+        let mut n = 2;
+        while file_names.contains(&name) {
+            name = format!("{stem}_{n}.{}", format.extension());
+            n += 1;
+        }
+        file_names.push(name);
+    }
+
+    // Generate pages
+    let mut pages = Vec::new();
+    for (i, module) in modules.iter().enumerate() {
+        let mut items = Vec::new();
+        collect_items(&comp, &module.names, "", &mut items);
+        let mut gen = PageGen {
+            comp: &comp,
+            format,
+            modules: &modules,
+            file_names: &file_names,
+            links: HashMap::new(),
+            runner: None,
+            out: String::new(),
+        };
+        gen.add_links(&items, &module.names);
+        gen.page(i, &items);
+        pages.push(DocPage {
+            file_name: file_names[i].clone(),
+            content: gen.out,
+        });
+    }
+    Ok(pages)
+}
+
+struct ModuleSource {
+    path: PathBuf,
+    comment: Option<String>,
+    names: Vec<(Ident, LocalName)>,
+}
+
+/// A documented binding
+struct DocItem {
+    /// The name, qualified with any scoped module names
+    name: String,
+    /// The index of the binding
+    index: usize,
+}
+
+fn collect_items(
+    comp: &Compiler,
+    names: &[(Ident, LocalName)],
+    prefix: &str,
+    items: &mut Vec<DocItem>,
+) {
+    for (name, local) in names {
+        if !local.public {
+            continue;
+        }
+        let name = format!("{prefix}{name}");
+        items.push(DocItem {
+            name: name.clone(),
+            index: local.index,
+        });
+        if let BindingKind::Module(module) = &comp.assembly().bindings[local.index].kind {
+            let names: Vec<_> = (module.names.iter())
+                .map(|(name, local)| (name.clone(), *local))
+                .collect();
+            collect_items(comp, &names, &format!("{name}~"), items);
+        }
+    }
+}
+
+/// The backend for compiling modules and running examples
+///
+/// Anything printed is captured rather than shown. When compiling, files and git
+/// modules are loaded like [`NativeSys`] does so that imports work. When running
+/// examples, the backend is sandboxed like [`SafeSys`].
+struct DocSys {
+    safe: SafeSys,
+    sandboxed: bool,
+}
+
+impl DocSys {
+    fn compile() -> Self {
+        DocSys {
+            safe: SafeSys::new(),
+            sandboxed: false,
+        }
+    }
+    fn sandboxed() -> Self {
+        DocSys {
+            safe: SafeSys::new(),
+            sandboxed: true,
+        }
+    }
+}
+
+impl SysBackend for DocSys {
+    fn any(&self) -> &dyn Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        self.safe.print_str_stdout(s)
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        self.safe.print_str_stderr(s)
+    }
+    fn print_str_trace(&self, _: &str) {}
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        if self.sandboxed {
+            self.safe.file_read_all(path)
+        } else {
+            NativeSys.file_read_all(path)
+        }
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        if self.sandboxed {
+            self.safe.load_git_module(url, target)
+        } else {
+            NativeSys.load_git_module(url, target)
+        }
+    }
+}
+
+/// Runs examples in the context of a module
+struct ExampleRunner {
+    comp: Compiler,
+    rt: Uiua,
+    error: Option<String>,
+}
+
+impl ExampleRunner {
+    fn new(path: &Path) -> Self {
+        let mut comp = Compiler::with_backend(DocSys::compile());
+        comp.pre_eval_mode(PreEvalMode::Line)
+            .print_diagnostics(false);
+        let mut rt =
+            Uiua::with_backend(DocSys::sandboxed()).with_execution_limit(Duration::from_secs(2));
+        let res = (comp.load_file(path).map(drop)).and_then(|()| rt.run_compiler(&mut comp));
+        comp.assembly_mut().root.clear();
+        rt.take_stack();
+        ExampleRunner {
+            comp,
+            rt,
+            error: res.err().map(|e| first_line(&e.to_string())),
+        }
+    }
+    fn run(&mut self, code: &str) -> Result<Vec<String>, String> {
+        if let Some(error) = &self.error {
+            return Err(format!("The module failed to run: {error}"));
+        }
+        let mut comp = self.comp.clone();
+        let res = (comp.load_str(code).map(drop)).and_then(|()| self.rt.run_compiler(&mut comp));
+        let stack = self.rt.take_stack();
+        match res {
+            Ok(()) => Ok(stack.iter().map(Value::show).collect()),
+            Err(e) => Err(first_line(&e.to_string())),
+        }
+    }
+}
+
+fn first_line(s: &str) -> String {
+    s.lines().next().unwrap_or_default().into()
+}
+
+struct PageGen<'a> {
+    comp: &'a Compiler,
+    format: DocFormat,
+    modules: &'a [ModuleSource],
+    file_names: &'a [String],
+    /// Map names that can be linked to their hrefs
+    links: HashMap<String, String>,
+    runner: Option<ExampleRunner>,
+    out: String,
+}
+
+impl<'a> PageGen<'a> {
+    fn add_links(&mut self, items: &[DocItem], names: &[(Ident, LocalName)]) {
+        for item in items {
+            self.links
+                .insert(item.name.clone(), format!("#{}", anchor(&item.name)));
+        }
+        // Link to the bindings of imported modules
+        for (name, local) in names {
+            let BindingKind::Import(path) = &self.comp.assembly().bindings[local.index].kind else {
+                continue;
+            };
+            let Some(i) = self.modules.iter().position(|m| &m.path == path) else {
+                continue;
+            };
+            let file = &self.file_names[i];
+            self.links.insert(name.to_string(), file.clone());
+            for (item_name, item_local) in &self.modules[i].names {
+                if item_local.public {
+                    let href = format!("{file}#{}", anchor(item_name));
+                    self.links.insert(format!("{name}~{item_name}"), href);
+                }
+            }
+        }
+    }
+    fn html(&self) -> bool {
+        self.format == DocFormat::Html
+    }
+    fn page(&mut self, index: usize, items: &[DocItem]) {
+        let module = &self.modules[index];
+        let title = (module.path.file_name())
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| module.path.display().to_string());
+        if self.html() {
+            _ = write!(
+                self.out,
+                "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n\
+                <title>{}</title>\n<style>{CSS}</style>\n</head>\n<body>\n",
+                escape_html(&title)
+            );
+        }
+
+        // Navigation
+        if self.modules.len() > 1 {
+            let mut nav = Vec::new();
+            for (module, file) in self.modules.iter().zip(self.file_names) {
+                let name = module
+                    .path
+                    .file_name()
+                    .unwrap_or_default()
+                    .to_string_lossy();
+                nav.push(self.link(&name, file));
+            }
+            if self.html() {
+                _ = writeln!(self.out, "<nav>{}</nav>", nav.join(" · "));
+            } else {
+                _ = writeln!(self.out, "{}\n", nav.join(" · "));
+            }
+        }
+
+        self.heading(1, &title, None);
+        if let Some(comment) = &module.comment {
+            self.comment(index, &DocComment::from(comment.as_str()));
+        }
+
+        // Table of contents
+        if !items.is_empty() {
+            self.heading(2, "Contents", None);
+            let entries: Vec<String> = (items.iter())
+                .map(|item| self.code_link(&item.name, &format!("#{}", anchor(&item.name))))
+                .collect();
+            self.list(&entries);
+        }
+
+        // Bindings
+        for item in items {
+            self.item(index, item);
+        }
+
+        if self.html() {
+            self.out.push_str("</body>\n</html>\n");
+        }
+    }
+    fn item(&mut self, module_index: usize, item: &DocItem) {
+        let binding = &self.comp.assembly().bindings[item.index];
+        let kind = match &binding.kind {
+            BindingKind::Const(_) => "constant",
+            BindingKind::Func(_) => "function",
+            BindingKind::Import(_) => "import",
+            BindingKind::Module(_) => "module",
+            BindingKind::IndexMacro(_) | BindingKind::CodeMacro(_) => "macro",
+        };
+        let sig = match &binding.kind {
+            BindingKind::Func(_) => binding.kind.sig(),
+            _ => None,
+        };
+        let mut heading = self.code(&item.name);
+        heading.push(' ');
+        heading.push_str(&self.faint(kind));
+        if let Some(sig) = sig {
+            heading.push(' ');
+            heading.push_str(&self.code(&sig.to_string()));
+        }
+        self.raw_heading(3, &heading, Some(&anchor(&item.name)));
+
+        match &binding.kind {
+            BindingKind::Import(path) => {
+                // The module's comment is shown on its own page
+                if let Some(i) = self.modules.iter().position(|m| &m.path == path) {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    let link = self.link(&name, &self.file_names[i]);
+                    self.paragraph(&format!("Imports {link}"));
+                }
+                return;
+            }
+            _ => {}
+        }
+        if let Some(comment) = &binding.comment {
+            self.comment(module_index, comment);
+        }
+        if let BindingKind::Module(module) = &binding.kind {
+            let entries: Vec<String> = (module.names.iter())
+                .filter(|(_, local)| local.public)
+                .map(|(name, _)| {
+                    let name = format!("{}~{name}", item.name);
+                    self.code_link(&name, &format!("#{}", anchor(&name)))
+                })
+                .collect();
+            self.list(&entries);
+        }
+    }
+    fn comment(&mut self, module_index: usize, comment: &DocComment) {
+        self.doc_sig(comment);
+        let examples = comment.examples();
+        let lines: Vec<&str> = comment.text.lines().collect();
+        let mut prose = String::new();
+        let mut i = 0;
+        while i < lines.len() {
+            if let Some(example) = examples.iter().find(|ex| ex.line == i) {
+                self.prose(&prose);
+                prose.clear();
+                self.example(module_index, example);
+                i += example.lines;
+            } else {
+                prose.push_str(lines[i]);
+                prose.push('\n');
+                i += 1;
+            }
+        }
+        self.prose(&prose);
+    }
+    fn doc_sig(&mut self, comment: &DocComment) {
+        let Some(sig) = &comment.sig else {
+            return;
+        };
+        self.paragraph(&self.code(sig.to_string().trim()));
+        let mut entries = Vec::new();
+        for (label, args) in [
+            ("argument", Some(&sig.args)),
+            ("output", sig.outputs.as_ref()),
+        ] {
+            for arg in args.into_iter().flatten() {
+                let mut entry = format!("{label} {}", self.code(&arg.name));
+                if let Some(ty) = &arg.ty {
+                    entry.push_str(&format!(": {}", self.code(ty)));
+                }
+                entries.push(entry);
+            }
+        }
+        self.list(&entries);
+    }
+    fn example(&mut self, module_index: usize, example: &DocCommentExample) {
+        let path = &self.modules[module_index].path;
+        let runner = self.runner.get_or_insert_with(|| ExampleRunner::new(path));
+        let output = runner.run(&example.code);
+        if self.html() {
+            _ = writeln!(
+                self.out,
+                "<pre class=\"example\"><code>{}</code></pre>",
+                escape_html(&example.code)
+            );
+            match output {
+                Ok(values) => {
+                    _ = writeln!(
+                        self.out,
+                        "<pre class=\"output\">{}</pre>",
+                        escape_html(&values.join("\n"))
+                    )
+                }
+                Err(e) => {
+                    _ = writeln!(
+                        self.out,
+                        "<pre class=\"output error\">{}</pre>",
+                        escape_html(&e)
+                    )
+                }
+            }
+        } else {
+            _ = writeln!(self.out, "```uiua\n{}\n```\n", example.code);
+            match output {
+                Ok(values) if values.is_empty() => {}
+                Ok(values) => _ = writeln!(self.out, "```\n{}\n```\n", values.join("\n")),
+                Err(e) => _ = writeln!(self.out, "```\n{e}\n```\n"),
+            }
+        }
+    }
+    fn heading(&mut self, level: usize, text: &str, id: Option<&str>) {
+        let text = if self.html() {
+            escape_html(text)
+        } else {
+            text.into()
+        };
+        self.raw_heading(level, &text, id)
+    }
+    fn raw_heading(&mut self, level: usize, text: &str, id: Option<&str>) {
+        if self.html() {
+            if let Some(id) = id {
+                _ = writeln!(self.out, "<h{level} id=\"{id}\">{text}</h{level}>");
+            } else {
+                _ = writeln!(self.out, "<h{level}>{text}</h{level}>");
+            }
+        } else {
+            if let Some(id) = id {
+                _ = writeln!(self.out, "<a id=\"{id}\"></a>\n");
+            }
+            _ = writeln!(self.out, "{} {text}\n", "#".repeat(level));
+        }
+    }
+    fn paragraph(&mut self, text: &str) {
+        if self.html() {
+            _ = writeln!(self.out, "<p>{text}</p>");
+        } else {
+            _ = writeln!(self.out, "{text}\n");
+        }
+    }
+    fn list(&mut self, entries: &[String]) {
+        if entries.is_empty() {
+            return;
+        }
+        if self.html() {
+            self.out.push_str("<ul>\n");
+            for entry in entries {
+                _ = writeln!(self.out, "<li>{entry}</li>");
+            }
+            self.out.push_str("</ul>\n");
+        } else {
+            for entry in entries {
+                _ = writeln!(self.out, "- {entry}");
+            }
+            self.out.push('\n');
+        }
+    }
+    /// Render paragraphs of comment text, linking code spans that name bindings
+    fn prose(&mut self, text: &str) {
+        for para in text.split("\n\n") {
+            let para = para.trim();
+            if para.is_empty() {
+                continue;
+            }
+            let mut rendered = String::new();
+            for (i, frag) in para.split('`').enumerate() {
+                if i % 2 == 0 {
+                    if self.html() {
+                        rendered.push_str(&escape_html(frag).replace('\n', "<br>\n"));
+                    } else {
+                        rendered.push_str(frag);
+                    }
+                } else if let Some(href) = self.links.get(frag) {
+                    rendered.push_str(&self.code_link(frag, href));
+                } else {
+                    rendered.push_str(&self.code(frag));
+                }
+            }
+            self.paragraph(&rendered);
+        }
+    }
+    fn code(&self, text: &str) -> String {
+        if self.html() {
+            format!("<code>{}</code>", escape_html(text))
+        } else {
+            format!("`{text}`")
+        }
+    }
+    fn faint(&self, text: &str) -> String {
+        if self.html() {
+            format!("<span class=\"kind\">{}</span>", escape_html(text))
+        } else {
+            format!("*{text}*")
+        }
+    }
+    fn link(&self, text: &str, href: &str) -> String {
+        if self.html() {
+            format!("<a href=\"{href}\">{}</a>", escape_html(text))
+        } else {
+            format!("[{text}]({href})")
+        }
+    }
+    fn code_link(&self, text: &str, href: &str) -> String {
+        if self.html() {
+            format!("<a href=\"{href}\">{}</a>", self.code(text))
+        } else {
+            format!("[{}]({href})", self.code(text))
+        }
+    }
+}
+
+/// The anchor id for a binding name
+fn anchor(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_whitespace() || c == '"' {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect()
+}
+
+fn escape_html(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+const CSS: &str = "
+body { max-width: 50em; margin: auto; padding: 1em; font-family: sans-serif; }
+code, pre { font-family: \"Uiua386\", \"DejaVu Sans Mono\", monospace; }
+pre { padding: 0.5em; border-radius: 0.3em; background-color: #f0f0f0; }
+pre.output { background-color: #e8f0e8; margin-top: -0.5em; }
+pre.error { background-color: #f8e0e0; }
+nav { margin-bottom: 1em; }
+h3 { border-top: 1px solid #ccc; padding-top: 0.5em; }
+.kind { font-size: 0.7em; font-weight: normal; color: #888; }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn docgen_markdown() {
+        let dir = std::env::temp_dir().join(format!(
+            "uiua_docgen_test_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("lib.ua");
+        std::fs::write(
+            &path,
+            "# A test library\n\n\
+            # Double a number, then use `Halve`\n\
+            # ? x\n\
+            # ex: Double 5\n\
+            Double ← ×2\n\
+            # Halve a number\n\
+            Halve ← ÷2\n",
+        )
+        .unwrap();
+        let pages = generate_docs(&path, DocFormat::Markdown).unwrap();
+        let page = &pages[0].content;
+        assert_eq!(pages[0].file_name, "index.md");
+        assert!(page.contains("A test library"), "{page}");
+        assert!(page.contains("[`Halve`](#Halve)"), "{page}");
+        assert!(
+            page.contains("```uiua\nDouble 5\n```\n\n```\n10\n```"),
+            "{page}"
+        );
+        assert!(page.contains("argument `x`"), "{page}");
+        _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn docgen_parent_import() {
+        let dir = std::env::temp_dir().join(format!(
+            "uiua_docgen_test_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(dir.join("sub")).unwrap();
+        std::fs::write(dir.join("lib.ua"), "Triple ← ×3\n").unwrap();
+        let path = dir.join("sub").join("m.ua");
+        std::fs::write(
+            &path,
+            "Lib ~ \"../lib.ua\"\n\
+            # Triple a number twice\n\
+            # ex: Nine 2\n\
+            Nine ← Lib~Triple Lib~Triple\n",
+        )
+        .unwrap();
+        let pages = generate_docs(&path, DocFormat::Markdown).unwrap();
+        let page = &pages[0].content;
+        assert!(
+            page.contains("```uiua\nNine 2\n```\n\n```\n18\n```"),
+            "{page}"
+        );
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
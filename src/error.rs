@@ -358,6 +358,8 @@ pub struct Diagnostic {
     pub kind: DiagnosticKind,
     /// The inputs of the program
     pub inputs: Inputs,
+    /// The name of the lint rule that produced the diagnostic, if any
+    lint: Option<&'static str>,
 }
 
 impl PartialEq for Diagnostic {
@@ -412,8 +414,18 @@ impl Diagnostic {
             span: span.into(),
             kind,
             inputs,
+            lint: None,
         }
     }
+    /// Set the lint rule that produced the diagnostic
+    pub fn with_lint(mut self, lint: &'static str) -> Self {
+        self.lint = Some(lint);
+        self
+    }
+    /// Get the name of the lint rule that produced the diagnostic, if any
+    pub fn lint(&self) -> Option<&'static str> {
+        self.lint
+    }
     /// Get a rich-text report for the diagnostic
     pub fn report(&self) -> Report {
         Report::new_multi(
@@ -5,8 +5,9 @@ use std::{
     collections::HashMap,
     env,
     fmt::Display,
-    fs,
+    fs, io,
     iter::repeat,
+    mem::take,
     path::{Path, PathBuf},
     time::Duration,
 };
@@ -19,9 +20,10 @@ use crate::{
     grid_fmt::GridFmt,
     is_ident_char, is_ident_start,
     lex::{CodeSpan, Loc, Sp},
+    literate,
     parse::{flip_unsplit_lines, parse, split_words, trim_spaces},
     Compiler, Ident, InputSrc, Inputs, PreEvalMode, Primitive, RunMode, SafeSys, Signature, Uiua,
-    UiuaErrorKind, UiuaResult, Value, SUBSCRIPT_DIGITS,
+    UiuaError, UiuaErrorKind, UiuaResult, Value, SUBSCRIPT_DIGITS,
 };
 
 trait ConfigValue: Sized {
@@ -221,6 +223,12 @@ create_config!(
     (align_comments, bool, true),
     /// Whether to indent item imports
     (indent_item_imports, bool, true),
+    /// The maximum line width before long strands, arrays, function packs,
+    /// and modifier arguments are split across multiple lines. `0` means no limit.
+    (max_line_width, usize, 0),
+    /// Whether to render primitives by their names or ASCII tokens instead of their glyphs,
+    /// for environments without the Uiua font
+    (ascii, bool, false),
 );
 
 /// The source from which to populate the formatter configuration.
@@ -338,6 +346,102 @@ impl FormatOutput {
         }
         (pos, pos)
     }
+    /// Get a unified diff of the changes made to the original code
+    ///
+    /// `name` is used in the diff header. The diff is empty if nothing changed.
+    pub fn diff(&self, input: &str, name: &str) -> String {
+        let old: Vec<&str> = input.split_inclusive('\n').collect();
+        let new: Vec<&str> = self.output.split_inclusive('\n').collect();
+        let lines = diff_lines(&old, &new);
+        let changes: Vec<usize> = (lines.iter().enumerate())
+            .filter(|(_, (tag, _))| *tag != ' ')
+            .map(|(i, _)| i)
+            .collect();
+        let mut diff = String::new();
+        if changes.is_empty() {
+            return diff;
+        }
+        diff.push_str(&format!("--- a/{name}\n+++ b/{name}\n"));
+        // The number of old and new lines before each diff line
+        let mut before = vec![(0, 0)];
+        for (tag, _) in &lines {
+            let (old, new) = *before.last().unwrap();
+            before.push((old + (*tag != '+') as usize, new + (*tag != '-') as usize));
+        }
+        let mut i = 0;
+        while i < changes.len() {
+            let start = changes[i].saturating_sub(DIFF_CONTEXT);
+            while i + 1 < changes.len() && changes[i + 1] - changes[i] <= 2 * DIFF_CONTEXT + 1 {
+                i += 1;
+            }
+            let end = (changes[i] + DIFF_CONTEXT + 1).min(lines.len());
+            i += 1;
+            let (old_start, new_start) = before[start];
+            let (old_end, new_end) = before[end];
+            let (old_len, new_len) = (old_end - old_start, new_end - new_start);
+            diff.push_str(&format!(
+                "@@ -{},{old_len} +{},{new_len} @@\n",
+                old_start + (old_len > 0) as usize,
+                new_start + (new_len > 0) as usize
+            ));
+            for (tag, line) in &lines[start..end] {
+                diff.push(*tag);
+                diff.push_str(line);
+                if !line.ends_with('\n') {
+                    diff.push_str("\n\\ No newline at end of file\n");
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// The number of unchanged lines shown around each change in a diff
+const DIFF_CONTEXT: usize = 3;
+
+/// Diff two lists of lines, tagging each with ` `, `-`, or `+`
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<(char, &'a str)> {
+    let prefix = (old.iter().zip(new)).take_while(|(a, b)| a == b).count();
+    let suffix = (old[prefix..].iter().rev())
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let a = &old[prefix..old.len() - suffix];
+    let b = &new[prefix..new.len() - suffix];
+    let mut lines: Vec<_> = old[..prefix].iter().map(|&line| (' ', line)).collect();
+    // Very large changed regions are shown as a single replacement
+    if a.len().saturating_mul(b.len()) > 1 << 24 {
+        lines.extend(a.iter().map(|&line| ('-', line)));
+        lines.extend(b.iter().map(|&line| ('+', line)));
+    } else {
+        // Longest common subsequence lengths of the changed region's suffixes
+        let mut lcs = vec![vec![0u32; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                lcs[i][j] = if a[i] == b[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && j < b.len() && a[i] == b[j] {
+                lines.push((' ', a[i]));
+                i += 1;
+                j += 1;
+            } else if j == b.len() || i < a.len() && lcs[i + 1][j] >= lcs[i][j + 1] {
+                lines.push(('-', a[i]));
+                i += 1;
+            } else {
+                lines.push(('+', b[j]));
+                j += 1;
+            }
+        }
+    }
+    lines.extend(old[old.len() - suffix..].iter().map(|&line| (' ', line)));
+    lines
 }
 
 #[test]
@@ -370,6 +474,39 @@ fn map_char_pos() {
     assert_eq!(output.map_char_pos(34), (31, 31));
 }
 
+#[test]
+#[cfg(test)]
+fn format_diff() {
+    let input = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj";
+    let output = FormatOutput {
+        output: "a\nB\nc\nd\ne\nf\ng\nh\ni\nj\n".into(),
+        glyph_map: Vec::new(),
+        inputs: Inputs::default(),
+    };
+    assert_eq!(
+        output.diff(input, "x.ua"),
+        "\
+--- a/x.ua
++++ b/x.ua
+@@ -1,5 +1,5 @@
+ a
+-b
++B
+ c
+ d
+ e
+@@ -7,4 +7,4 @@
+ g
+ h
+ i
+-j
+\\ No newline at end of file
++j
+"
+    );
+    assert_eq!(output.diff(&output.output, "x.ua"), "");
+}
+
 /// Format Uiua code
 ///
 /// The path is used for error reporting
@@ -378,7 +515,12 @@ pub fn format<P: AsRef<Path>>(
     path: P,
     config: &FormatConfig,
 ) -> UiuaResult<FormatOutput> {
-    format_impl(input, path.as_ref().into(), config)
+    let path = path.as_ref();
+    if literate::is_markdown_path(path) {
+        format_markdown(input, path, config)
+    } else {
+        format_impl(input, path.into(), config)
+    }
 }
 
 /// Format Uiua code without a path
@@ -388,9 +530,9 @@ pub fn format_str(input: &str, config: &FormatConfig) -> UiuaResult<FormatOutput
 
 fn format_impl(input: &str, src: InputSrc, config: &FormatConfig) -> UiuaResult<FormatOutput> {
     let mut inputs = Inputs::default();
-    let (items, errors, _) = parse(input, src.clone(), &mut inputs);
+    let (mut items, errors, _) = parse(input, src.clone(), &mut inputs);
     if errors.is_empty() {
-        let (output, glyph_map) = Formatter {
+        let mut formatter = Formatter {
             src,
             config,
             inputs: &inputs,
@@ -400,8 +542,18 @@ fn format_impl(input: &str, src: InputSrc, config: &FormatConfig) -> UiuaResult<
             prev_import_function: None,
             output_comments: None,
             eval_output_comments: true,
-        }
-        .format_top_items(&items);
+            too_long: Vec::new(),
+        };
+        // Wrapping a construct can move others, so format until nothing new is too long
+        let (output, glyph_map) = loop {
+            let formatted = formatter.format_top_items(&items);
+            let too_long: HashMap<_, _> = (take(&mut formatter.too_long).into_iter())
+                .map(|(span, width, _)| (span, width))
+                .collect();
+            if too_long.is_empty() || !wrap_items(&mut items, &too_long, &inputs) {
+                break formatted;
+            }
+        };
         let formatted = FormatOutput {
             output,
             glyph_map,
@@ -422,6 +574,28 @@ fn format_impl(input: &str, src: InputSrc, config: &FormatConfig) -> UiuaResult<
     }
 }
 
+/// Format the Uiua code blocks in a Markdown document
+fn format_markdown(input: &str, path: &Path, config: &FormatConfig) -> UiuaResult<FormatOutput> {
+    let blocks = literate::code_blocks(input);
+    let code = literate::extract_uiua_with_markers(input, &blocks);
+    let formatted = format_impl(&code, path.into(), config)?;
+    let output =
+        literate::replace_code_blocks(input, &blocks, &formatted.output).ok_or_else(|| {
+            UiuaError::format(
+                path.into(),
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "the formatted code could not be split back into Markdown code blocks",
+                ),
+            )
+        })?;
+    Ok(FormatOutput {
+        output,
+        glyph_map: Vec::new(),
+        inputs: formatted.inputs,
+    })
+}
+
 /// Format Uiua code in a file at the given path
 ///
 /// This modifies the file
@@ -458,6 +632,7 @@ pub(crate) fn format_words(words: &[Sp<Word>], inputs: &Inputs) -> String {
         prev_import_function: None,
         output_comments: None,
         eval_output_comments: false,
+        too_long: Vec::new(),
     };
     formatter.format_words(words, true, 0);
     formatter.output
@@ -474,6 +649,7 @@ pub(crate) fn format_word(word: &Sp<Word>, inputs: &Inputs) -> String {
         prev_import_function: None,
         output_comments: None,
         eval_output_comments: false,
+        too_long: Vec::new(),
     };
     formatter.format_word(word, 0);
     formatter.output
@@ -489,21 +665,26 @@ struct Formatter<'a> {
     prev_import_function: Option<Ident>,
     output_comments: Option<HashMap<usize, Vec<Vec<Value>>>>,
     eval_output_comments: bool,
+    /// Single-line words that went past the maximum line width,
+    /// along with the width available to their contents if wrapped
+    /// and where they start in the output
+    too_long: Vec<(CodeSpan, usize, usize)>,
 }
 
 type GlyphMap = Vec<(CodeSpan, (Loc, Loc))>;
 
 impl<'a> Formatter<'a> {
-    fn format_top_items(mut self, items: &[Item]) -> (String, GlyphMap) {
+    fn format_top_items(&mut self, items: &[Item]) -> (String, GlyphMap) {
+        self.prev_import_function = None;
         self.format_items(items, 0);
-        let mut output = self.output;
+        let mut output = take(&mut self.output);
         while output.ends_with('\n') {
             output.pop();
         }
         if self.config.trailing_newline && !output.trim().is_empty() {
             output.push('\n');
         }
-        (output, self.glyph_map)
+        (output, take(&mut self.glyph_map))
     }
     fn format_items(&mut self, items: &[Item], depth: usize) {
         let mut max_name_len = 0;
@@ -519,7 +700,9 @@ impl<'a> Formatter<'a> {
                             .iter()
                             .take_while(|item| matches!(item, Item::Binding(_)))
                             .map(|item| match item {
-                                Item::Binding(binding) => binding.name.value.chars().count(),
+                                Item::Binding(binding) => {
+                                    self.ident(&binding.name.value).chars().count()
+                                }
                                 _ => 0,
                             })
                             .max()
@@ -658,15 +841,21 @@ impl<'a> Formatter<'a> {
                     _ => self.prev_import_function = None,
                 }
 
-                self.output.push_str(&binding.name.value);
-                let len = binding.name.value.chars().count();
+                let name = self.ident(&binding.name.value);
+                let len = name.chars().count();
+                self.output.push_str(&name);
                 if len < max_name_len {
                     for _ in 0..max_name_len - len {
                         self.output.push(' ');
                     }
                 }
                 self.output
-                    .push_str(if binding.public { " ←" } else { " ↚" });
+                    .push_str(match (binding.public, self.config.ascii) {
+                        (true, false) => " ←",
+                        (false, false) => " ↚",
+                        (true, true) => " =",
+                        (false, true) => " =~",
+                    });
                 if binding.code_macro {
                     self.output.push('^');
                 }
@@ -731,7 +920,13 @@ impl<'a> Formatter<'a> {
                             parts.push((&validator.open_span, ": ", &validator.words));
                         }
                         if let Some(default) = &field.init {
-                            parts.push((&default.arrow_span, " ← ", &default.words));
+                            // After a validator, `=` would be parsed as part of its code
+                            let arrow = if self.config.ascii && field.validator.is_none() {
+                                " = "
+                            } else {
+                                " ← "
+                            };
+                            parts.push((&default.arrow_span, arrow, &default.words));
                         }
                         for (span, sep, words) in parts {
                             self.push(span, sep);
@@ -783,6 +978,10 @@ impl<'a> Formatter<'a> {
                     self.format_words(words, true, depth + 1);
                 }
             }
+            Item::Error(span) => {
+                let text = span.as_str(self.inputs, |s| s.to_string());
+                self.push(span, &text);
+            }
             Item::Import(import) => {
                 self.prev_import_function = None;
                 if let Some(name) = &import.name {
@@ -862,10 +1061,13 @@ impl<'a> Formatter<'a> {
         {
             self.output.push(' ');
         }
-        self.push(&r.name.span, &r.name.value);
+        let name = self.ident(&r.name.value);
+        self.ascii_separate(&name);
+        self.push(&r.name.span, &name);
     }
     fn format_ref_path(&mut self, comps: &[RefComponent]) {
         if let Some(first) = comps.first() {
+            self.ascii_separate(&first.module.value);
             if first.module.value.starts_with(|c: char| c.is_lowercase())
                 && (self.output.chars().last())
                     .is_some_and(|c| c.is_lowercase() && is_ident_start(c))
@@ -903,6 +1105,46 @@ impl<'a> Formatter<'a> {
         }
     }
     fn format_word(&mut self, word: &Sp<Word>, depth: usize) {
+        let max_width = self.config.max_line_width;
+        if max_width == 0 || !can_wrap(&word.value) {
+            return self.format_word_impl(word, depth);
+        }
+        let start = self.output.len();
+        let start_col = self.line_width();
+        let inner_flags = self.too_long.len();
+        self.format_word_impl(word, depth);
+        if self.output[start..].contains('\n') || self.line_width() <= max_width {
+            return;
+        }
+        // Only the outermost word and the first on a line are wrapped at a time
+        self.too_long.truncate(inner_flags);
+        if (self.too_long.last())
+            .is_some_and(|&(_, _, prev)| !self.output[prev..start].contains('\n'))
+        {
+            return;
+        }
+        // Find where the contents would start if wrapped
+        let sig_width = match &word.value {
+            Word::Array(Arr {
+                signature: Some(sig),
+                ..
+            }) => sig.value.to_string().chars().count(),
+            _ => 0,
+        };
+        let inner_col = start_col + 1 + sig_width;
+        let indent = self.config.multiline_indent * depth;
+        let content_col = if inner_col <= indent + 2 {
+            inner_col
+        } else {
+            indent + self.config.multiline_indent
+        };
+        let width = max_width.saturating_sub(content_col);
+        self.too_long.push((word.span.clone(), width, start));
+    }
+    fn line_width(&self) -> usize {
+        (self.output.rsplit('\n').next()).map_or(0, |line| line.chars().count())
+    }
+    fn format_word_impl(&mut self, word: &Sp<Word>, depth: usize) {
         match &word.value {
             Word::Number(Ok(n)) => {
                 let grid_str = n.grid_string(false);
@@ -950,7 +1192,13 @@ impl<'a> Formatter<'a> {
                 {
                     self.output.push(' ');
                 }
-                self.push(&word.span, &formatted);
+                if self.config.ascii {
+                    let formatted = ascii_number(&formatted);
+                    self.ascii_separate(&formatted);
+                    self.push(&word.span, &formatted);
+                } else {
+                    self.push(&word.span, &formatted);
+                }
             }
             Word::Number(Err(s)) => self.push(&word.span, s),
             Word::Label(label) => self.push(&word.span, &format!("${label}")),
@@ -1295,7 +1543,11 @@ impl<'a> Formatter<'a> {
         }
     }
     fn format_primitive(&mut self, prim: Primitive, span: &CodeSpan) {
-        let as_str = prim.to_string();
+        let as_str = if self.config.ascii {
+            ascii_primitive(prim)
+        } else {
+            prim.to_string()
+        };
         if span.end.char_pos - span.start.char_pos > 1
             && !as_str.starts_with(is_ident_char)
             && self.output.ends_with(' ')
@@ -1308,8 +1560,47 @@ impl<'a> Formatter<'a> {
             self.glyph_map.pop();
         }
         match prim {
+            Primitive::Utf8 if self.config.ascii => {
+                self.ascii_separate("utf");
+                self.push(span, "utf__8")
+            }
             Primitive::Utf8 => self.push(span, "utf₈"),
-            _ => self.push(span, &as_str),
+            _ => {
+                self.ascii_separate(&as_str);
+                self.push(span, &as_str)
+            }
+        }
+    }
+    /// Get the text for an identifier or subscript, spelling out subscripts and `‼`s in ASCII mode
+    fn ident<'b>(&self, ident: &'b str) -> Cow<'b, str> {
+        if !self.config.ascii || ident.is_ascii() {
+            return Cow::Borrowed(ident);
+        }
+        let mut ascii = String::new();
+        let mut in_subscript = false;
+        for c in ident.chars() {
+            let digit = SUBSCRIPT_DIGITS.iter().position(|&d| d == c);
+            if (digit.is_some() || c == '₋') && !in_subscript {
+                ascii.push_str("__");
+                in_subscript = true;
+            }
+            match (c, digit) {
+                (_, Some(d)) => ascii.push(char::from(b'0' + d as u8)),
+                ('₋', _) => ascii.push('`'),
+                ('‼', _) => ascii.push_str("!!"),
+                (c, _) => ascii.push(c),
+            }
+        }
+        Cow::Owned(ascii)
+    }
+    /// In ASCII mode, keep text that starts with a letter or digit from
+    /// running into a name or number before it
+    fn ascii_separate(&mut self, next: &str) {
+        if self.config.ascii
+            && next.starts_with(char::is_alphanumeric)
+            && self.output.ends_with(char::is_alphanumeric)
+        {
+            self.output.push(' ');
         }
     }
     fn format_multiline_words(
@@ -1385,7 +1676,8 @@ impl<'a> Formatter<'a> {
                 if let Some(span) = &mac.caret_span {
                     self.push(span, "^");
                 }
-                self.push(&mac.ident.span, &mac.ident.value);
+                let ident = self.ident(&mac.ident.value);
+                self.push(&mac.ident.span, &ident);
             }
         }
     }
@@ -1430,7 +1722,7 @@ impl<'a> Formatter<'a> {
             }
             values
         });
-        values.remove(&index).unwrap_or_default()
+        values.get(&index).cloned().unwrap_or_default()
     }
     fn func(&mut self, func: &Func, depth: usize) {
         let start_indent = (self.output.split('\n').last()).map_or(0, |line| line.chars().count());
@@ -1455,9 +1747,262 @@ impl<'a> Formatter<'a> {
     fn subscript(&mut self, sub: &Sp<Subscript>) {
         match &sub.value {
             Subscript::TooLarge => sub.span.as_str(self.inputs, |s| self.push(&sub.span, s)),
-            _ => self.push(&sub.span, &sub.value.to_string()),
+            _ => {
+                let s = sub.value.to_string();
+                let s = self.ident(&s).into_owned();
+                self.push(&sub.span, &s)
+            }
+        }
+    }
+}
+
+/// Get the name or ASCII token that parses back to a primitive
+///
+/// Primitives that have neither keep their glyph
+fn ascii_primitive(prim: Primitive) -> String {
+    let as_str = prim.to_string();
+    if as_str.is_ascii() {
+        return as_str;
+    }
+    let parses_to_prim = |name: &str| {
+        name.chars().all(|c| c.is_ascii_lowercase())
+            && Primitive::from_format_name_multi(name)
+                .is_some_and(|prims| prims.len() == 1 && prims[0].0 == prim)
+    };
+    let name = prim.name();
+    if parses_to_prim(name) {
+        name.into()
+    } else if let Some(ascii) = prim.ascii() {
+        ascii.to_string()
+    } else {
+        (name.split(' ').next())
+            .filter(|word| parses_to_prim(word))
+            .map_or(as_str, Into::into)
+    }
+}
+
+/// Replace the glyphs in a formatted number with their ASCII forms
+fn ascii_number(s: &str) -> String {
+    let mut ascii = String::new();
+    for c in s.chars() {
+        match c {
+            '¯' => ascii.push('`'),
+            'η' => ascii.push_str("eta"),
+            'π' => ascii.push_str("pi"),
+            'τ' => ascii.push_str("tau"),
+            '∞' => ascii.push_str("infinity"),
+            c => ascii.push(c),
+        }
+    }
+    ascii
+}
+
+/// Whether a word has a multi-line form that it could be wrapped into
+fn can_wrap(word: &Word) -> bool {
+    match word {
+        Word::Array(arr) => {
+            arr.lines.len() == 1 && !word_is_multiline(word) && line_atoms(&arr.lines[0]).len() > 1
+        }
+        Word::Strand(items) => {
+            items.len() > 1 && !(items.iter()).any(|item| word_is_multiline(&item.value))
+        }
+        Word::Pack(pack) => pack.branches.len() > 1 && !word_is_multiline(word),
+        Word::Modified(m) => {
+            let mut operands = m.operands.iter().filter(|w| w.value.is_code());
+            matches!(m.modifier.value, Modifier::Primitive(_))
+                && operands.clone().count() > 1
+                && operands.all(|w| matches!(&w.value, Word::Func(_)))
+                && !word_is_multiline(word)
+        }
+        _ => false,
+    }
+}
+
+/// Split a line of words into space-separated groups
+///
+/// Returns nothing if the line has words that prevent splitting
+fn line_atoms(words: &[Sp<Word>]) -> Vec<&[Sp<Word>]> {
+    let words = trim_spaces(words, true);
+    if (words.iter()).any(|w| !w.value.is_code() && !matches!(w.value, Word::Spaces)) {
+        return Vec::new();
+    }
+    (words.split(|w| matches!(w.value, Word::Spaces)))
+        .filter(|atom| !atom.is_empty())
+        .collect()
+}
+
+/// Wrap words that are too long into their multi-line forms
+///
+/// Returns whether anything was wrapped
+fn wrap_items(items: &mut [Item], too_long: &HashMap<CodeSpan, usize>, inputs: &Inputs) -> bool {
+    let mut wrapped = false;
+    for item in items {
+        wrapped |= match item {
+            Item::Words(lines) => wrap_lines(lines, too_long, inputs),
+            Item::Binding(binding) => wrap_words(&mut binding.words, too_long, inputs),
+            Item::Module(m) => wrap_items(&mut m.value.items, too_long, inputs),
+            Item::Data(data) => {
+                let fields = data.fields.iter_mut().flat_map(|fields| &mut fields.fields);
+                let mut wrapped = false;
+                for field in fields {
+                    if let Some(validator) = &mut field.validator {
+                        wrapped |= wrap_words(&mut validator.words, too_long, inputs);
+                    }
+                    if let Some(init) = &mut field.init {
+                        wrapped |= wrap_words(&mut init.words, too_long, inputs);
+                    }
+                }
+                if let Some(words) = &mut data.func {
+                    wrapped |= wrap_words(words, too_long, inputs);
+                }
+                wrapped
+            }
+            Item::Import(_) | Item::Error(_) => false,
+        };
+    }
+    wrapped
+}
+
+fn wrap_lines(
+    lines: &mut [Vec<Sp<Word>>],
+    too_long: &HashMap<CodeSpan, usize>,
+    inputs: &Inputs,
+) -> bool {
+    (lines.iter_mut()).fold(false, |wrapped, line| {
+        wrap_words(line, too_long, inputs) | wrapped
+    })
+}
+
+fn wrap_words(
+    words: &mut [Sp<Word>],
+    too_long: &HashMap<CodeSpan, usize>,
+    inputs: &Inputs,
+) -> bool {
+    (words.iter_mut()).fold(false, |wrapped, word| {
+        wrap_word(word, too_long, inputs) | wrapped
+    })
+}
+
+fn wrap_word(word: &mut Sp<Word>, too_long: &HashMap<CodeSpan, usize>, inputs: &Inputs) -> bool {
+    if let Some(wrapped) =
+        (too_long.get(&word.span)).and_then(|&width| wrapped(word, width, inputs))
+    {
+        word.value = wrapped;
+        return true;
+    }
+    match &mut word.value {
+        Word::Strand(items) => wrap_words(items, too_long, inputs),
+        Word::Array(arr) => wrap_lines(&mut arr.lines, too_long, inputs),
+        Word::Func(func) => wrap_lines(&mut func.lines, too_long, inputs),
+        Word::Pack(pack) => (pack.branches.iter_mut()).fold(false, |wrapped, br| {
+            wrap_lines(&mut br.value.lines, too_long, inputs) | wrapped
+        }),
+        Word::Modified(m) => wrap_words(&mut m.operands, too_long, inputs),
+        Word::Subscripted(sub) => wrap_word(&mut sub.word, too_long, inputs),
+        Word::InlineMacro(mac) => wrap_lines(&mut mac.func.value.lines, too_long, inputs),
+        _ => false,
+    }
+}
+
+/// Get the multi-line form of a word
+fn wrapped(word: &Sp<Word>, width: usize, inputs: &Inputs) -> Option<Word> {
+    Some(match &word.value {
+        Word::Array(arr) => Word::Array(Arr {
+            lines: fill_lines(&arr.lines[0], width, inputs)?,
+            ..arr.clone()
+        }),
+        Word::Strand(items) => {
+            let mut words = Vec::new();
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    let underscore = items[i - 1].span.clone().end_to(&item.span);
+                    words.push(underscore.sp(Word::Spaces));
+                }
+                words.push(item.clone());
+            }
+            Word::Array(Arr {
+                signature: None,
+                lines: fill_lines(&words, width, inputs)?,
+                boxes: false,
+                closed: true,
+            })
+        }
+        Word::Pack(pack) => Word::Pack(FunctionPack {
+            branches: multiline_branches(pack.branches.clone()),
+            closed: pack.closed,
+        }),
+        Word::Modified(m) => {
+            let funcs: Vec<_> = (m.operands.iter())
+                .filter_map(|w| match &w.value {
+                    Word::Func(func) => Some(w.span.clone().sp(func.clone())),
+                    _ => None,
+                })
+                .collect();
+            let span = (funcs.iter().map(|f| f.span.clone())).reduce(CodeSpan::merge)?;
+            let pack = FunctionPack {
+                branches: multiline_branches(funcs),
+                closed: true,
+            };
+            Word::Modified(Box::new(Modified {
+                modifier: m.modifier.clone(),
+                operands: vec![span.sp(Word::Pack(pack))],
+                pack_expansion: m.pack_expansion,
+            }))
+        }
+        _ => return None,
+    })
+}
+
+/// Fill lines with space-separated groups of words up to a width
+///
+/// There are always at least two lines so that the result stays multi-line
+fn fill_lines(words: &[Sp<Word>], width: usize, inputs: &Inputs) -> Option<Vec<Vec<Sp<Word>>>> {
+    let atoms = line_atoms(words);
+    if atoms.len() < 2 {
+        return None;
+    }
+    let widths: Vec<usize> = (atoms.iter())
+        .map(|atom| format_words(atom, inputs).chars().count())
+        .collect();
+    let mut breaks = Vec::new();
+    let mut line_width = widths[0];
+    for (i, &atom_width) in widths.iter().enumerate().skip(1) {
+        if line_width + 1 + atom_width > width {
+            breaks.push(i);
+            line_width = atom_width;
+        } else {
+            line_width += 1 + atom_width;
         }
     }
+    if breaks.is_empty() {
+        breaks.push(atoms.len() - 1);
+    }
+    let mut lines = vec![Vec::new()];
+    for (i, atom) in atoms.iter().enumerate() {
+        if breaks.contains(&i) {
+            lines.push(Vec::new());
+        } else if i > 0 {
+            let prev = &atoms[i - 1][atoms[i - 1].len() - 1];
+            let space = prev.span.clone().end_to(&atom[0].span);
+            lines.last_mut().unwrap().push(space.sp(Word::Spaces));
+        }
+        lines.last_mut().unwrap().extend_from_slice(atom);
+    }
+    Some(lines)
+}
+
+/// Make function pack branches that are formatted on multiple lines
+fn multiline_branches(mut branches: Vec<Sp<Func>>) -> Vec<Sp<Func>> {
+    let last = branches.len().saturating_sub(1);
+    for (i, br) in branches.iter_mut().enumerate() {
+        if i == 0 {
+            br.value.lines.insert(0, Vec::new());
+        }
+        if i < last {
+            br.value.lines.push(Vec::new());
+        }
+    }
+    branches
 }
 
 fn words_are_multiline(words: &[Sp<Word>]) -> bool {
@@ -1641,3 +2186,65 @@ F₁
     let formatted = format_str(input, &FormatConfig::default()).unwrap().output;
     assert_eq!(formatted, output);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        lex::{lex, Token},
+        tests::test_files,
+    };
+
+    #[test]
+    fn format_wrapping() {
+        let config = FormatConfig::default().with_max_line_width(30);
+        let code = "\
+[1 2 3 4 5 6 7 8 9 10 11 12 13 14 15 16 17 18 19 20]
+⊃(+1|×2|-3|÷4|◿5|ⁿ6|↥7|↧8|⇌) 5
+{\"hello\" \"world\" \"this\" \"is\" \"long\"}";
+        let formatted = format_str(code, &config).unwrap().output;
+        assert!(formatted.lines().all(|line| line.chars().count() <= 30));
+        let reformatted = format_str(&formatted, &config).unwrap().output;
+        assert_eq!(formatted, reformatted);
+        let run = |code: &str| {
+            let mut env = Uiua::with_safe_sys();
+            env.run_str(code).unwrap();
+            env.take_stack()
+        };
+        assert_eq!(run(code), run(&formatted));
+    }
+
+    #[test]
+    fn format_ascii_round_trip() {
+        let glyphs = FormatConfig::default();
+        let ascii = FormatConfig::default().with_ascii(true);
+        let formatted = format_str("X ← ⊃(/+|⧻)≡⊂₂⇡12 ¯3 ≠1 ⌵π", &ascii).unwrap();
+        assert_eq!(
+            formatted.output,
+            "X = fork(/+|length)rows join__2 range 12 `3 !=1 absolute pi\n"
+        );
+        // Spacing added to separate names may remain, but nothing else should change
+        let tokens = |s: &str| {
+            let mut inputs = Inputs::default();
+            let (tokens, _, _) = lex(s, (), &mut inputs);
+            (tokens.into_iter())
+                .filter(|token| token.value != Token::Spaces)
+                .map(|token| (token.span.as_str(&inputs, str::to_string), token.value))
+                .collect::<Vec<_>>()
+        };
+        for path in test_files(|_| true) {
+            let input = std::fs::read_to_string(&path).unwrap();
+            let Ok(formatted) = format_str(&input, &glyphs) else {
+                continue;
+            };
+            let ascii = format_str(&formatted.output, &ascii).unwrap().output;
+            let round_trip = format_str(&ascii, &glyphs).unwrap().output;
+            assert_eq!(
+                tokens(&formatted.output),
+                tokens(&round_trip),
+                "{} did not round-trip through ASCII",
+                path.display()
+            );
+        }
+    }
+}
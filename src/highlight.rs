@@ -0,0 +1,425 @@
+//! Export syntax-highlighted Uiua code
+//!
+//! Code is classified with the same [`Spans`] used by the language server and
+//! colored with the scheme used on the website.
+
+use std::{
+    fmt::{self, Write},
+    str::FromStr,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+
+use crate::{
+    lsp::{BindingDocsKind, SpanKind, Spans},
+    PrimClass, Primitive, SafeSys, SysBackend,
+};
+
+/// The output format of highlighted code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HighlightFormat {
+    /// Text with ANSI escape codes for terminals
+    #[default]
+    Ansi,
+    /// An HTML fragment with an embedded stylesheet
+    Html,
+    /// A standalone SVG image with the Uiua386 font embedded
+    Svg,
+}
+
+impl FromStr for HighlightFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(HighlightFormat::Ansi),
+            "html" => Ok(HighlightFormat::Html),
+            "svg" => Ok(HighlightFormat::Svg),
+            _ => Err(format!("unknown highlight format `{s}`")),
+        }
+    }
+}
+
+impl fmt::Display for HighlightFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HighlightFormat::Ansi => write!(f, "ansi"),
+            HighlightFormat::Html => write!(f, "html"),
+            HighlightFormat::Svg => write!(f, "svg"),
+        }
+    }
+}
+
+/// Highlight Uiua code
+pub fn highlight(input: &str, format: HighlightFormat) -> String {
+    highlight_with_backend(input, SafeSys::default(), format)
+}
+
+/// Highlight Uiua code, using a custom backend to resolve imports
+pub fn highlight_with_backend(
+    input: &str,
+    backend: impl SysBackend,
+    format: HighlightFormat,
+) -> String {
+    let spans = Spans::with_backend(input, backend);
+    let frags = fragments(input, &spans);
+    match format {
+        HighlightFormat::Ansi => ansi(&frags),
+        HighlightFormat::Html => html(&frags),
+        HighlightFormat::Svg => svg(&frags),
+    }
+}
+
+/// A color class, named as on the website
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Class {
+    Stack,
+    Noadic,
+    Monadic,
+    Dyadic,
+    Triadic,
+    Tetradic,
+    MonadicModifier,
+    DyadicModifier,
+    TriadicModifier,
+    Module,
+    Number,
+    String,
+    Strand,
+    Comment,
+}
+
+/// The default text and background colors for dark and light themes
+const DARK: (&str, &str) = ("#d1daec", "#19232d");
+const LIGHT: (&str, &str) = ("#344", "#dff2f3");
+
+impl Class {
+    const ALL: [Class; 14] = [
+        Class::Stack,
+        Class::Noadic,
+        Class::Monadic,
+        Class::Dyadic,
+        Class::Triadic,
+        Class::Tetradic,
+        Class::MonadicModifier,
+        Class::DyadicModifier,
+        Class::TriadicModifier,
+        Class::Module,
+        Class::Number,
+        Class::String,
+        Class::Strand,
+        Class::Comment,
+    ];
+    fn css_class(self) -> &'static str {
+        match self {
+            Class::Stack => "stack-function",
+            Class::Noadic => "noadic-function",
+            Class::Monadic => "monadic-function",
+            Class::Dyadic => "dyadic-function",
+            Class::Triadic => "triadic-function",
+            Class::Tetradic => "tetradic-function",
+            Class::MonadicModifier => "monadic-modifier",
+            Class::DyadicModifier => "dyadic-modifier",
+            Class::TriadicModifier => "triadic-modifier",
+            Class::Module => "module",
+            Class::Number => "number-literal",
+            Class::String => "string-literal-span",
+            Class::Strand => "strand-span",
+            Class::Comment => "comment-span",
+        }
+    }
+    /// The class's colors for dark and light themes
+    fn colors(self) -> (&'static str, &'static str) {
+        match self {
+            Class::Stack => (DARK.0, LIGHT.0),
+            Class::Noadic => ("#ed5e6a", "#ed5e6a"),
+            Class::Monadic => ("#95d16a", "#95d16a"),
+            Class::Dyadic => ("#54b0fc", "#54b0fc"),
+            Class::Triadic => ("#8078f1", "#8078f1"),
+            Class::Tetradic => ("#f576d8", "#f576d8"),
+            Class::MonadicModifier => ("#f0c36f", "#f0c36f"),
+            Class::DyadicModifier => ("#cc6be9", "#cc6be9"),
+            Class::TriadicModifier => ("#f5a9b8", "#f5a9b8"),
+            Class::Module => ("#d7be8c", "#d7be8c"),
+            Class::Number => ("#f85", "#f85"),
+            Class::String => ("#20f9fc", "#1c9"),
+            Class::Strand => ("#fff8", "#0008"),
+            Class::Comment => ("#888", "#888"),
+        }
+    }
+    /// The class's color on the dark background, with any transparency blended away
+    fn dark_rgb(self) -> [u8; 3] {
+        blend(self.colors().0, DARK.1)
+    }
+}
+
+fn sig_class(args: usize) -> Option<Class> {
+    Some(match args {
+        0 => Class::Noadic,
+        1 => Class::Monadic,
+        2 => Class::Dyadic,
+        3 => Class::Triadic,
+        4 => Class::Tetradic,
+        _ => return None,
+    })
+}
+
+fn modifier_class(margs: usize) -> Class {
+    match margs {
+        0 | 1 => Class::MonadicModifier,
+        2 => Class::DyadicModifier,
+        _ => Class::TriadicModifier,
+    }
+}
+
+fn prim_class(prim: Primitive, sub: Option<i32>) -> Option<Class> {
+    match prim {
+        Primitive::Identity => Some(Class::Stack),
+        prim if matches!(prim.class(), PrimClass::Stack | PrimClass::Debug)
+            && prim.modifier_args().is_none() =>
+        {
+            Some(Class::Stack)
+        }
+        prim if prim.class() == PrimClass::Constant => Some(Class::Number),
+        prim => {
+            if let Some(margs) = prim.modifier_args() {
+                Some(modifier_class(margs))
+            } else {
+                (prim.subscript_sig(sub).or(prim.sig())).and_then(|sig| sig_class(sig.args))
+            }
+        }
+    }
+}
+
+fn span_class(kind: &SpanKind) -> Option<Class> {
+    match kind {
+        SpanKind::Primitive(prim, sub) => prim_class(*prim, *sub),
+        SpanKind::Obverse(_) => prim_class(Primitive::Obverse, None),
+        SpanKind::Subscript(Some(prim), sub) => prim_class(*prim, *sub),
+        SpanKind::Number | SpanKind::Subscript(None, _) => Some(Class::Number),
+        SpanKind::String | SpanKind::ImportSrc(_) => Some(Class::String),
+        SpanKind::Comment | SpanKind::OutputComment => Some(Class::Comment),
+        SpanKind::Strand => Some(Class::Strand),
+        SpanKind::MacroDelim(margs) => Some(modifier_class(*margs)),
+        SpanKind::Ident {
+            docs: Some(docs), ..
+        } => match docs.kind {
+            BindingDocsKind::Function { sig, .. } => sig_class(sig.args),
+            BindingDocsKind::Modifier(margs) => Some(modifier_class(margs)),
+            BindingDocsKind::Module { .. } => Some(Class::Module),
+            BindingDocsKind::Constant(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// Split the input into pieces of text and their classes
+fn fragments<'a>(input: &'a str, spans: &Spans) -> Vec<(&'a str, Option<Class>)> {
+    let mut frags = Vec::new();
+    let mut end = 0;
+    for span in &spans.spans {
+        let start = span.span.start.byte_pos as usize;
+        let span_end = (span.span.end.byte_pos as usize).min(input.len());
+        if start < end || span_end <= start {
+            continue;
+        }
+        if end < start {
+            frags.push((&input[end..start], None));
+        }
+        frags.push((&input[start..span_end], span_class(&span.value)));
+        end = span_end;
+    }
+    if end < input.len() {
+        frags.push((&input[end..], None));
+    }
+    frags
+}
+
+fn ansi(frags: &[(&str, Option<Class>)]) -> String {
+    let mut s = String::new();
+    for (text, class) in frags {
+        if let Some(class) = class {
+            let [r, g, b] = class.dark_rgb();
+            _ = write!(s, "\x1b[38;2;{r};{g};{b}m{text}\x1b[0m");
+        } else {
+            s.push_str(text);
+        }
+    }
+    s
+}
+
+fn html(frags: &[(&str, Option<Class>)]) -> String {
+    let mut s = String::from("<style>\n");
+    _ = writeln!(
+        s,
+        ".uiua {{ color: {}; background-color: {}; padding: 0.5em; border-radius: 0.3em; \
+        font-family: \"Uiua386\", monospace; }}",
+        LIGHT.0, LIGHT.1
+    );
+    for class in Class::ALL {
+        _ = writeln!(
+            s,
+            ".uiua .{} {{ color: {}; }}",
+            class.css_class(),
+            class.colors().1
+        );
+    }
+    s.push_str("@media (prefers-color-scheme: dark) {\n");
+    _ = writeln!(
+        s,
+        "  .uiua {{ color: {}; background-color: {}; }}",
+        DARK.0, DARK.1
+    );
+    for class in Class::ALL {
+        _ = writeln!(
+            s,
+            "  .uiua .{} {{ color: {}; }}",
+            class.css_class(),
+            class.colors().0
+        );
+    }
+    s.push_str("}\n</style>\n<pre class=\"uiua\"><code>");
+    for (text, class) in frags {
+        if let Some(class) = class {
+            _ = write!(
+                s,
+                "<span class=\"{}\">{}</span>",
+                class.css_class(),
+                escape(text)
+            );
+        } else {
+            s.push_str(&escape(text));
+        }
+    }
+    s.push_str("</code></pre>\n");
+    s
+}
+
+const SVG_FONT_SIZE: f64 = 16.0;
+/// Uiua386 metrics relative to the font size
+const SVG_CHAR_WIDTH: f64 = 0.6;
+const SVG_ASCENT: f64 = 1.15;
+const SVG_LINE_HEIGHT: f64 = 1.65;
+const SVG_PADDING: f64 = 0.75;
+
+fn svg(frags: &[(&str, Option<Class>)]) -> String {
+    // Lay the fragments out into lines
+    let mut lines: Vec<Vec<(&str, Option<Class>)>> = vec![Vec::new()];
+    for &(text, class) in frags {
+        for (i, part) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Vec::new());
+            }
+            let part = part.trim_end_matches('\r');
+            if !part.is_empty() {
+                lines.last_mut().unwrap().push((part, class));
+            }
+        }
+    }
+    while lines.len() > 1 && lines.last().is_some_and(|line| line.is_empty()) {
+        lines.pop();
+    }
+    let max_width = (lines.iter())
+        .map(|line| line.iter().map(|(text, _)| text.chars().count()).sum())
+        .max()
+        .unwrap_or(0);
+    let width = (max_width as f64 * SVG_CHAR_WIDTH + 2.0 * SVG_PADDING) * SVG_FONT_SIZE;
+    let height = (lines.len() as f64 * SVG_LINE_HEIGHT + 2.0 * SVG_PADDING) * SVG_FONT_SIZE;
+
+    let mut s = String::new();
+    _ = writeln!(
+        s,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" \
+        width=\"{width:.1}\" height=\"{height:.1}\" viewBox=\"0 0 {width:.1} {height:.1}\">"
+    );
+    s.push_str("<style>\n@font-face { font-family: \"Uiua386\"; src: url(data:font/ttf;base64,");
+    s.push_str(&BASE64.encode(include_bytes!("algorithm/Uiua386.ttf")));
+    s.push_str(") format(\"truetype\"); }\n");
+    _ = writeln!(
+        s,
+        "text {{ font-family: \"Uiua386\", monospace; font-size: {SVG_FONT_SIZE}px; \
+        white-space: pre; fill: {}; }}",
+        DARK.0
+    );
+    s.push_str("</style>\n");
+    _ = writeln!(
+        s,
+        "<rect width=\"100%\" height=\"100%\" rx=\"{:.1}\" fill=\"{}\"/>",
+        0.3 * SVG_FONT_SIZE,
+        DARK.1
+    );
+    for (i, line) in lines.iter().enumerate() {
+        if line.is_empty() {
+            continue;
+        }
+        let x = SVG_PADDING * SVG_FONT_SIZE;
+        let y = (SVG_PADDING + i as f64 * SVG_LINE_HEIGHT + SVG_ASCENT) * SVG_FONT_SIZE;
+        _ = write!(s, "<text x=\"{x:.1}\" y=\"{y:.1}\" xml:space=\"preserve\">");
+        for (text, class) in line {
+            if let Some(class) = class {
+                let [r, g, b] = class.dark_rgb();
+                _ = write!(
+                    s,
+                    "<tspan fill=\"#{r:02x}{g:02x}{b:02x}\">{}</tspan>",
+                    escape(text)
+                );
+            } else {
+                s.push_str(&escape(text));
+            }
+        }
+        s.push_str("</text>\n");
+    }
+    s.push_str("</svg>\n");
+    s
+}
+
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Parse a CSS hex color and blend away its transparency over a background
+fn blend(color: &str, background: &str) -> [u8; 3] {
+    fn parse(color: &str) -> [u8; 4] {
+        let digits: Vec<u8> = (color.trim_start_matches('#').chars())
+            .map(|c| c.to_digit(16).unwrap_or(0) as u8)
+            .collect();
+        let mut rgba = [255; 4];
+        match digits.len() {
+            3 | 4 => (digits.iter().zip(&mut rgba)).for_each(|(d, c)| *c = d * 17),
+            _ => (digits.chunks(2).zip(&mut rgba)).for_each(|(d, c)| *c = d[0] * 16 + d[1]),
+        }
+        rgba
+    }
+    let [r, g, b, a] = parse(color);
+    let [br, bg, bb, _] = parse(background);
+    let mix = |c: u8, bc: u8| ((c as u32 * a as u32 + bc as u32 * (255 - a as u32)) / 255) as u8;
+    [mix(r, br), mix(g, bg), mix(b, bb)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let code = "F ← +1 # inc\nF \"a<b\"";
+        let html = highlight(code, HighlightFormat::Html);
+        assert!(html.contains("<span class=\"monadic-function\">F</span> ← "));
+        assert!(html.contains("<span class=\"dyadic-function\">+</span>"));
+        assert!(html.contains("<span class=\"string-literal-span\">&quot;a&lt;b&quot;</span>"));
+        let ansi = highlight(code, HighlightFormat::Ansi);
+        assert!(ansi.contains("\x1b[38;2;136;136;136m# inc\x1b[0m"));
+        let svg = highlight(code, HighlightFormat::Svg);
+        assert!(svg.contains("data:font/ttf;base64,AAEAAA"));
+        assert_eq!(svg.matches("<text ").count(), 2);
+    }
+}
@@ -0,0 +1,1077 @@
+//! A Jupyter kernel for Uiua
+//!
+//! The kernel keeps a single runtime and compiler alive for the whole session,
+//! so bindings made in one cell are available in the next.
+//! Values left on the stack are displayed as the cell's result.
+//! Images, GIFs, and audio are sent as rich display data.
+
+mod zmq;
+
+use std::{
+    env, fs, io,
+    mem::take,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use crossbeam_channel::select;
+use hmac::{Hmac, Mac};
+use parking_lot::Mutex;
+use serde::*;
+use serde_json::{json, Value as Json};
+use sha2::Sha256;
+
+use crate::{
+    encode::SmartOutput, is_ident_char, lex, parse, AsciiToken, Compiler, GitTarget, Handle,
+    Inputs, NativeSys, PreEvalMode, Primitive, SysBackend, Token, Uiua, UiuaError, UiuaErrorKind,
+    UiuaResult, Value, CONSTANTS,
+};
+
+use self::zmq::{Socket, SocketType};
+
+const PROTOCOL_VERSION: &str = "5.3";
+const DELIMITER: &[u8] = b"<IDS|MSG>";
+
+/// The contents of a Jupyter connection file
+#[derive(Debug, Clone, Deserialize)]
+struct ConnectionInfo {
+    ip: String,
+    #[serde(default)]
+    transport: String,
+    shell_port: u16,
+    control_port: u16,
+    stdin_port: u16,
+    iopub_port: u16,
+    hb_port: u16,
+    #[serde(default)]
+    key: String,
+    #[serde(default)]
+    signature_scheme: String,
+}
+
+/// The header of a Jupyter message
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Header {
+    /// The unique id of the message
+    pub msg_id: String,
+    /// The id of the session that sent the message
+    pub session: String,
+    /// The user that sent the message
+    pub username: String,
+    /// When the message was created, in ISO 8601 format
+    pub date: String,
+    /// The kind of message
+    pub msg_type: String,
+    /// The message protocol version
+    pub version: String,
+}
+
+/// A Jupyter message
+#[derive(Debug, Clone, Default)]
+pub struct Message {
+    /// The routing identities of the message
+    pub identities: Vec<Vec<u8>>,
+    /// The message header
+    pub header: Header,
+    /// The header of the message this one is responding to
+    pub parent_header: Json,
+    /// Message metadata
+    pub metadata: Json,
+    /// The message content
+    pub content: Json,
+    /// Extra binary buffers
+    pub buffers: Vec<Vec<u8>>,
+}
+
+impl Message {
+    /// Create a new request message
+    pub fn new(msg_type: &str, content: Json) -> Self {
+        Message {
+            identities: Vec::new(),
+            header: Header::new(msg_type, &new_id()),
+            parent_header: json!({}),
+            metadata: json!({}),
+            content,
+            buffers: Vec::new(),
+        }
+    }
+    /// Get the message type
+    pub fn msg_type(&self) -> &str {
+        &self.header.msg_type
+    }
+    /// Create a reply to a message that will be routed back to its sender
+    fn reply(parent: &Message, session: &str, msg_type: &str, content: Json) -> Self {
+        Message {
+            identities: parent.identities.clone(),
+            ..Message::child(parent, session, msg_type, content)
+        }
+    }
+    /// Create a message for the IOPub channel in response to a message
+    fn broadcast(parent: &Message, session: &str, msg_type: &str, content: Json) -> Self {
+        Message {
+            identities: vec![msg_type.as_bytes().to_vec()],
+            ..Message::child(parent, session, msg_type, content)
+        }
+    }
+    fn child(parent: &Message, session: &str, msg_type: &str, content: Json) -> Self {
+        Message {
+            identities: Vec::new(),
+            header: Header::new(msg_type, session),
+            parent_header: serde_json::to_value(&parent.header).unwrap_or_default(),
+            metadata: json!({}),
+            content,
+            buffers: Vec::new(),
+        }
+    }
+    /// Decode a message from its wire frames, verifying its signature
+    pub fn from_frames(mut frames: Vec<Vec<u8>>, key: &[u8]) -> Result<Self, String> {
+        let delim = (frames.iter().position(|frame| frame == DELIMITER))
+            .ok_or("Message has no delimiter")?;
+        if frames.len() < delim + 6 {
+            return Err("Message is missing frames".into());
+        }
+        let buffers = frames.split_off(delim + 6);
+        let parts = frames.split_off(delim + 1);
+        frames.pop();
+        if !key.is_empty() {
+            let signature = decode_hex(&parts[0]).ok_or("Message signature is not valid hex")?;
+            signer(key, &parts[1..])
+                .verify_slice(&signature)
+                .map_err(|_| "Message signature is invalid")?;
+        }
+        fn parse<T: de::DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+            serde_json::from_slice(bytes).map_err(|e| e.to_string())
+        }
+        Ok(Message {
+            identities: frames,
+            header: parse(&parts[1])?,
+            parent_header: parse(&parts[2])?,
+            metadata: parse(&parts[3])?,
+            content: parse(&parts[4])?,
+            buffers,
+        })
+    }
+    /// Encode a message into its wire frames, signing it with a key
+    pub fn to_frames(&self, key: &[u8]) -> Vec<Vec<u8>> {
+        let parts = [
+            serde_json::to_vec(&self.header).unwrap_or_default(),
+            serde_json::to_vec(&self.parent_header).unwrap_or_default(),
+            serde_json::to_vec(&self.metadata).unwrap_or_default(),
+            serde_json::to_vec(&self.content).unwrap_or_default(),
+        ];
+        let signature = if key.is_empty() {
+            String::new()
+        } else {
+            let bytes = signer(key, &parts).finalize().into_bytes();
+            bytes.iter().map(|b| format!("{b:02x}")).collect()
+        };
+        let mut frames = self.identities.clone();
+        frames.push(DELIMITER.to_vec());
+        frames.push(signature.into_bytes());
+        frames.extend(parts);
+        frames.extend(self.buffers.iter().cloned());
+        frames
+    }
+}
+
+impl Header {
+    fn new(msg_type: &str, session: &str) -> Self {
+        Header {
+            msg_id: new_id(),
+            session: session.into(),
+            username: "kernel".into(),
+            date: now_iso8601(),
+            msg_type: msg_type.into(),
+            version: PROTOCOL_VERSION.into(),
+        }
+    }
+}
+
+fn new_id() -> String {
+    format!("{:032x}", rand::random::<u128>())
+}
+
+fn now_iso8601() -> String {
+    let now = time::OffsetDateTime::now_utc();
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:06}Z",
+        now.year(),
+        now.month() as u8,
+        now.day(),
+        now.hour(),
+        now.minute(),
+        now.second(),
+        now.microsecond()
+    )
+}
+
+fn signer(key: &[u8], parts: &[Vec<u8>]) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+fn decode_hex(hex: &[u8]) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 {
+        return None;
+    }
+    let digit = |b: u8| (b as char).to_digit(16).map(|d| d as u8);
+    (hex.chunks(2))
+        .map(|pair| Some(digit(pair[0])? << 4 | digit(pair[1])?))
+        .collect()
+}
+
+/// Output captured while running a cell
+enum KernelOutput {
+    Stdout(String),
+    Stderr(String),
+    Display(SmartOutput),
+}
+
+/// A system backend that captures output so it can be sent to the frontend
+///
+/// Everything else is delegated to the native backend.
+#[derive(Default)]
+pub struct KernelSys {
+    outputs: Mutex<Vec<KernelOutput>>,
+}
+
+impl KernelSys {
+    fn take_outputs(&self) -> Vec<KernelOutput> {
+        take(&mut *self.outputs.lock())
+    }
+}
+
+impl SysBackend for KernelSys {
+    fn any(&self) -> &dyn std::any::Any {
+        self
+    }
+    fn any_mut(&mut self) -> &mut dyn std::any::Any {
+        self
+    }
+    fn print_str_stdout(&self, s: &str) -> Result<(), String> {
+        (self.outputs.lock()).push(KernelOutput::Stdout(s.into()));
+        Ok(())
+    }
+    fn print_str_stderr(&self, s: &str) -> Result<(), String> {
+        (self.outputs.lock()).push(KernelOutput::Stderr(s.into()));
+        Ok(())
+    }
+    fn print_str_trace(&self, s: &str) {
+        (self.outputs.lock()).push(KernelOutput::Stderr(s.into()));
+    }
+    fn show(&self, value: Value) -> Result<(), String> {
+        let output = SmartOutput::from_value(value, self);
+        self.outputs.lock().push(KernelOutput::Display(output));
+        Ok(())
+    }
+    #[cfg(feature = "image")]
+    fn show_image(&self, image: image::DynamicImage, label: Option<&str>) -> Result<(), String> {
+        let bytes = crate::encode::image_to_bytes(&image, image::ImageOutputFormat::Png)?;
+        let output = SmartOutput::Png(bytes, label.map(Into::into));
+        self.outputs.lock().push(KernelOutput::Display(output));
+        Ok(())
+    }
+    fn show_gif(&self, gif_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        let output = SmartOutput::Gif(gif_bytes, label.map(Into::into));
+        self.outputs.lock().push(KernelOutput::Display(output));
+        Ok(())
+    }
+    fn play_audio(&self, wave_bytes: Vec<u8>, label: Option<&str>) -> Result<(), String> {
+        let output = SmartOutput::Wav(wave_bytes, label.map(Into::into));
+        self.outputs.lock().push(KernelOutput::Display(output));
+        Ok(())
+    }
+    fn var(&self, name: &str) -> Option<String> {
+        NativeSys.var(name)
+    }
+    fn file_exists(&self, path: &str) -> bool {
+        NativeSys.file_exists(path)
+    }
+    fn list_dir(&self, path: &str) -> Result<Vec<String>, String> {
+        NativeSys.list_dir(path)
+    }
+    fn is_file(&self, path: &str) -> Result<bool, String> {
+        NativeSys.is_file(path)
+    }
+    fn delete(&self, path: &str) -> Result<(), String> {
+        NativeSys.delete(path)
+    }
+    fn trash(&self, path: &str) -> Result<(), String> {
+        NativeSys.trash(path)
+    }
+    fn read(&self, handle: Handle, count: usize) -> Result<Vec<u8>, String> {
+        NativeSys.read(handle, count)
+    }
+    fn read_all(&self, handle: Handle) -> Result<Vec<u8>, String> {
+        NativeSys.read_all(handle)
+    }
+    fn write(&self, handle: Handle, contents: &[u8]) -> Result<(), String> {
+        NativeSys.write(handle, contents)
+    }
+    fn create_file(&self, path: &Path) -> Result<Handle, String> {
+        NativeSys.create_file(path)
+    }
+    fn open_file(&self, path: &Path, write: bool) -> Result<Handle, String> {
+        NativeSys.open_file(path, write)
+    }
+    fn make_dir(&self, path: &Path) -> Result<(), String> {
+        NativeSys.make_dir(path)
+    }
+    fn file_read_all(&self, path: &Path) -> Result<Vec<u8>, String> {
+        NativeSys.file_read_all(path)
+    }
+    fn file_write_all(&self, path: &Path, contents: &[u8]) -> Result<(), String> {
+        NativeSys.file_write_all(path, contents)
+    }
+    fn clipboard(&self) -> Result<String, String> {
+        NativeSys.clipboard()
+    }
+    fn set_clipboard(&self, contents: &str) -> Result<(), String> {
+        NativeSys.set_clipboard(contents)
+    }
+    fn sleep(&self, seconds: f64) -> Result<(), String> {
+        NativeSys.sleep(seconds)
+    }
+    fn allow_thread_spawning(&self) -> bool {
+        true
+    }
+    fn audio_sample_rate(&self) -> u32 {
+        NativeSys.audio_sample_rate()
+    }
+    fn now(&self) -> f64 {
+        NativeSys.now()
+    }
+    fn tcp_listen(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_listen(addr)
+    }
+    fn tls_listen(&self, addr: &str, cert: &[u8], key: &[u8]) -> Result<Handle, String> {
+        NativeSys.tls_listen(addr, cert, key)
+    }
+    fn tcp_accept(&self, handle: Handle) -> Result<Handle, String> {
+        NativeSys.tcp_accept(handle)
+    }
+    fn tcp_connect(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tcp_connect(addr)
+    }
+    fn tls_connect(&self, addr: &str) -> Result<Handle, String> {
+        NativeSys.tls_connect(addr)
+    }
+    fn tcp_addr(&self, handle: Handle) -> Result<std::net::SocketAddr, String> {
+        NativeSys.tcp_addr(handle)
+    }
+    fn tcp_set_non_blocking(&self, handle: Handle, non_blocking: bool) -> Result<(), String> {
+        NativeSys.tcp_set_non_blocking(handle, non_blocking)
+    }
+    fn tcp_set_read_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_read_timeout(handle, timeout)
+    }
+    fn tcp_set_write_timeout(
+        &self,
+        handle: Handle,
+        timeout: Option<std::time::Duration>,
+    ) -> Result<(), String> {
+        NativeSys.tcp_set_write_timeout(handle, timeout)
+    }
+    fn close(&self, handle: Handle) -> Result<(), String> {
+        NativeSys.close(handle)
+    }
+    fn invoke(&self, path: &str) -> Result<(), String> {
+        NativeSys.invoke(path)
+    }
+    fn run_command_capture(
+        &self,
+        command: &str,
+        args: &[&str],
+    ) -> Result<(i32, String, String), String> {
+        NativeSys.run_command_capture(command, args)
+    }
+    fn run_command_stream(&self, command: &str, args: &[&str]) -> Result<[Handle; 3], String> {
+        NativeSys.run_command_stream(command, args)
+    }
+    fn change_directory(&self, path: &str) -> Result<(), String> {
+        NativeSys.change_directory(path)
+    }
+    fn https_get(&self, request: &str, handle: Handle) -> Result<String, String> {
+        NativeSys.https_get(request, handle)
+    }
+    fn ffi(
+        &self,
+        file: &str,
+        result_ty: crate::FfiType,
+        name: &str,
+        arg_tys: &[crate::FfiType],
+        args: &[Value],
+    ) -> Result<Value, String> {
+        NativeSys.ffi(file, result_ty, name, arg_tys, args)
+    }
+    fn mem_copy(&self, ty: crate::FfiType, ptr: *const (), len: usize) -> Result<Value, String> {
+        NativeSys.mem_copy(ty, ptr, len)
+    }
+    fn mem_free(&self, ptr: *const ()) -> Result<(), String> {
+        NativeSys.mem_free(ptr)
+    }
+    fn load_git_module(&self, url: &str, target: GitTarget) -> Result<PathBuf, String> {
+        NativeSys.load_git_module(url, target)
+    }
+    fn timezone(&self) -> Result<f64, String> {
+        NativeSys.timezone()
+    }
+}
+
+/// A Uiua Jupyter kernel session
+///
+/// The kernel does not do any IO itself. Requests are passed to [`Kernel::handle`],
+/// which returns the reply and emits any IOPub messages through a callback.
+pub struct Kernel {
+    rt: Uiua,
+    comp: Compiler,
+    session: String,
+    execution_count: usize,
+    interrupted: Arc<AtomicBool>,
+}
+
+impl Default for Kernel {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Kernel {
+    /// Create a new kernel session
+    pub fn new() -> Self {
+        let mut comp = Compiler::with_backend(KernelSys::default());
+        comp.pre_eval_mode(PreEvalMode::Line)
+            .print_diagnostics(false);
+        let interrupted = Arc::new(AtomicBool::new(false));
+        let flag = interrupted.clone();
+        let rt = Uiua::with_backend(comp.backend())
+            .with_interrupt_hook(move || flag.swap(false, Ordering::Relaxed));
+        Kernel {
+            rt,
+            comp,
+            session: new_id(),
+            execution_count: 0,
+            interrupted,
+        }
+    }
+    /// Get a flag that interrupts the currently running cell when set
+    pub fn interrupter(&self) -> Arc<AtomicBool> {
+        self.interrupted.clone()
+    }
+    /// Handle a request
+    ///
+    /// IOPub messages are passed to `emit` as they are produced.
+    /// Returns the reply, if the request has one.
+    pub fn handle(&mut self, request: &Message, mut emit: impl FnMut(Message)) -> Option<Message> {
+        let session = self.session.clone();
+        let status = |state: &str| {
+            let content = json!({ "execution_state": state });
+            Message::broadcast(request, &session, "status", content)
+        };
+        emit(status("busy"));
+        let reply = match request.msg_type() {
+            "kernel_info_request" => Some(("kernel_info_reply", kernel_info())),
+            "execute_request" => Some(("execute_reply", self.execute(request, &mut emit))),
+            "complete_request" => Some(("complete_reply", self.complete(&request.content))),
+            "inspect_request" => Some(("inspect_reply", self.inspect(&request.content))),
+            "is_complete_request" => Some(("is_complete_reply", is_complete(&request.content))),
+            "comm_info_request" => Some(("comm_info_reply", json!({"status": "ok", "comms": {}}))),
+            "history_request" => Some(("history_reply", json!({"status": "ok", "history": []}))),
+            "interrupt_request" => {
+                self.interrupted.store(true, Ordering::Relaxed);
+                Some(("interrupt_reply", json!({ "status": "ok" })))
+            }
+            "shutdown_request" => Some(("shutdown_reply", shutdown_reply(request))),
+            _ => None,
+        };
+        emit(status("idle"));
+        reply.map(|(msg_type, content)| Message::reply(request, &session, msg_type, content))
+    }
+    fn execute(&mut self, request: &Message, emit: &mut impl FnMut(Message)) -> Json {
+        let content = &request.content;
+        let code = content["code"].as_str().unwrap_or_default();
+        let silent = content["silent"].as_bool().unwrap_or(false);
+        let store_history = content["store_history"].as_bool().unwrap_or(true);
+        if !silent && store_history {
+            self.execution_count += 1;
+        }
+        let count = self.execution_count;
+        let session = self.session.clone();
+        let mut emit = |msg_type: &str, content: Json| {
+            if !silent {
+                emit(Message::broadcast(request, &session, msg_type, content))
+            }
+        };
+        emit(
+            "execute_input",
+            json!({ "code": code, "execution_count": count }),
+        );
+
+        self.interrupted.store(false, Ordering::Relaxed);
+        let res = self.run(code);
+
+        // Send captured output
+        let sys = self.rt.downcast_backend::<KernelSys>();
+        let outputs = sys.map(KernelSys::take_outputs).unwrap_or_default();
+        for output in merge_streams(outputs) {
+            match output {
+                KernelOutput::Stdout(text) => {
+                    emit("stream", json!({ "name": "stdout", "text": text }))
+                }
+                KernelOutput::Stderr(text) => {
+                    emit("stream", json!({ "name": "stderr", "text": text }))
+                }
+                KernelOutput::Display(SmartOutput::Normal(value)) => emit(
+                    "stream",
+                    json!({ "name": "stdout", "text": format!("{}\n", value.show()) }),
+                ),
+                KernelOutput::Display(output) => {
+                    if let Some(data) = mime_bundle(output) {
+                        emit("display_data", display_content(data))
+                    }
+                }
+            }
+        }
+        for report in self.rt.take_reports() {
+            emit(
+                "stream",
+                json!({ "name": "stdout", "text": format!("{report}\n") }),
+            )
+        }
+        let diagnostics = self.comp.take_diagnostics();
+        if !diagnostics.is_empty() {
+            let text: String = (diagnostics.iter())
+                .map(|diag| format!("{}\n", diag.report()))
+                .collect();
+            emit("stream", json!({ "name": "stderr", "text": text }));
+        }
+
+        // Send the stack or the error
+        match res {
+            Ok(stack) => {
+                let mut text = Vec::new();
+                let backend = self.comp.backend();
+                for value in stack {
+                    match SmartOutput::from_value(value, &*backend) {
+                        SmartOutput::Normal(value) => text.push(value.show()),
+                        output => {
+                            if let Some(data) = mime_bundle(output) {
+                                emit("display_data", display_content(data))
+                            }
+                        }
+                    }
+                }
+                if !text.is_empty() {
+                    emit(
+                        "execute_result",
+                        json!({
+                            "execution_count": count,
+                            "data": { "text/plain": text.join("\n") },
+                            "metadata": {},
+                        }),
+                    );
+                }
+                json!({
+                    "status": "ok",
+                    "execution_count": count,
+                    "payload": [],
+                    "user_expressions": {},
+                })
+            }
+            Err(e) => {
+                let error = error_content(&e);
+                emit("error", error.clone());
+                let mut reply = error;
+                reply["status"] = "error".into();
+                reply["execution_count"] = count.into();
+                reply
+            }
+        }
+    }
+    /// Run a cell, returning the values it left on the stack
+    fn run(&mut self, code: &str) -> UiuaResult<Vec<Value>> {
+        if code.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let backup = self.comp.clone();
+        let res = (self.comp.load_str(code).map(drop))
+            .and_then(|()| self.rt.run_compiler(&mut self.comp));
+        let stack = self.rt.take_stack();
+        if res.is_err() {
+            self.comp = backup;
+        }
+        self.comp.assembly_mut().root.clear();
+        res.map(|()| stack)
+    }
+    fn complete(&self, content: &Json) -> Json {
+        let code = content["code"].as_str().unwrap_or_default();
+        let cursor = content["cursor_pos"].as_u64().unwrap_or(0) as usize;
+        let (start, end) = ident_bounds(code, cursor, false);
+        let token: String = code.chars().skip(start).take(end - start).collect();
+        let token = token.to_lowercase();
+        let mut matches = Vec::new();
+        if !token.is_empty() {
+            // Primitives are completed to their glyphs if they have one
+            for prim in Primitive::non_deprecated() {
+                if prim.name().starts_with(&token) {
+                    matches.push(match prim.glyph() {
+                        Some(glyph) => glyph.to_string(),
+                        None => prim.name().to_string(),
+                    });
+                }
+            }
+            for (name, _) in self.comp.scope_names() {
+                if name.to_lowercase().starts_with(&token) {
+                    matches.push(name.to_string());
+                }
+            }
+            for constant in &CONSTANTS {
+                if constant.name.to_lowercase().starts_with(&token) {
+                    matches.push(constant.name.into());
+                }
+            }
+        }
+        json!({
+            "status": "ok",
+            "matches": matches,
+            "cursor_start": start,
+            "cursor_end": end,
+            "metadata": {},
+        })
+    }
+    fn inspect(&self, content: &Json) -> Json {
+        let code = content["code"].as_str().unwrap_or_default();
+        let cursor = content["cursor_pos"].as_u64().unwrap_or(0) as usize;
+        let (start, end) = ident_bounds(code, cursor, true);
+        let word: String = code.chars().skip(start).take(end - start).collect();
+        let text = if word.is_empty() {
+            // Look for a glyph under or just before the cursor
+            (code.chars().nth(cursor))
+                .and_then(Primitive::from_glyph)
+                .or_else(|| {
+                    (cursor.checked_sub(1))
+                        .and_then(|i| code.chars().nth(i))
+                        .and_then(Primitive::from_glyph)
+                })
+                .map(prim_text)
+        } else {
+            self.binding_text(&word)
+                .or_else(|| Primitive::from_format_name(&word).map(prim_text))
+                .or_else(|| {
+                    (CONSTANTS.iter())
+                        .find(|constant| constant.name == word)
+                        .map(|constant| format!("{}\n\n{}", constant.name, constant.doc()))
+                })
+        };
+        json!({
+            "status": "ok",
+            "found": text.is_some(),
+            "data": text.map(|text| json!({ "text/plain": text })).unwrap_or(json!({})),
+            "metadata": {},
+        })
+    }
+    fn binding_text(&self, name: &str) -> Option<String> {
+        let (_, local) = self.comp.scope_names().find(|(n, _)| *n == name)?;
+        let binding = self.comp.assembly().bindings.get(local.index)?;
+        let mut text = name.to_string();
+        if let Some(sig) = binding.kind.sig() {
+            text.push_str(&format!(" {sig}"));
+        }
+        if let Some(comment) = &binding.comment {
+            if let Some(sig) = &comment.sig {
+                text.push_str(&format!("\n{sig}"));
+            }
+            text.push_str(&format!("\n\n{}", comment.text));
+        }
+        Some(text)
+    }
+}
+
+fn prim_text(prim: Primitive) -> String {
+    let mut text = prim.format().to_string();
+    if let Some(sig) = prim.sig() {
+        text.push_str(&format!(" {sig}"));
+    }
+    text.push_str(&format!("\n\n{}", prim.doc().short_text()));
+    text
+}
+
+/// Find the bounds, in characters, of the identifier that ends at the cursor
+///
+/// If `extend` is set, the identifier may continue past the cursor.
+fn ident_bounds(code: &str, cursor: usize, extend: bool) -> (usize, usize) {
+    let chars: Vec<char> = code.chars().collect();
+    let cursor = cursor.min(chars.len());
+    let is_ident = |c: char| is_ident_char(c) || c == '&';
+    let mut start = cursor;
+    while start > 0 && is_ident(chars[start - 1]) {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while extend && end < chars.len() && is_ident(chars[end]) {
+        end += 1;
+    }
+    (start, end)
+}
+
+fn kernel_info() -> Json {
+    json!({
+        "status": "ok",
+        "protocol_version": PROTOCOL_VERSION,
+        "implementation": "uiua",
+        "implementation_version": crate::VERSION,
+        "language_info": {
+            "name": "uiua",
+            "version": crate::VERSION,
+            "mimetype": "text/x-uiua",
+            "file_extension": ".ua",
+            "pygments_lexer": "text",
+            "codemirror_mode": "text",
+        },
+        "banner": format!("Uiua {}", crate::VERSION),
+        "help_links": [{ "text": "Uiua Docs", "url": "https://uiua.org/docs" }],
+    })
+}
+
+fn shutdown_reply(request: &Message) -> Json {
+    let restart = request.content["restart"].as_bool().unwrap_or(false);
+    json!({ "status": "ok", "restart": restart })
+}
+
+fn is_complete(content: &Json) -> Json {
+    let code = content["code"].as_str().unwrap_or_default();
+    let mut inputs = Inputs::default();
+    let (tokens, _, _) = lex(code, (), &mut inputs);
+    let mut depth = 0;
+    for token in &tokens {
+        match &token.value {
+            Token::Simple(
+                AsciiToken::OpenParen | AsciiToken::OpenBracket | AsciiToken::OpenCurly,
+            )
+            | Token::OpenModule => depth += 1,
+            Token::Simple(
+                AsciiToken::CloseParen | AsciiToken::CloseBracket | AsciiToken::CloseCurly,
+            )
+            | Token::CloseModule => depth -= 1,
+            _ => {}
+        }
+    }
+    if depth > 0 {
+        return json!({ "status": "incomplete", "indent": "  ".repeat(depth as usize) });
+    }
+    let (_, errors, _) = parse(code, (), &mut inputs);
+    if errors.is_empty() {
+        json!({ "status": "complete" })
+    } else {
+        json!({ "status": "invalid" })
+    }
+}
+
+fn error_content(error: &UiuaError) -> Json {
+    let ename = match &error.kind {
+        UiuaErrorKind::Interrupted => "Interrupted",
+        UiuaErrorKind::Timeout(..) => "Timeout",
+        UiuaErrorKind::Parse(..) => "ParseError",
+        _ => "Error",
+    };
+    let evalue = error.report().color(false).to_string();
+    let traceback: Vec<String> = (error.report().to_string().lines())
+        .map(Into::into)
+        .collect();
+    json!({ "ename": ename, "evalue": evalue, "traceback": traceback })
+}
+
+/// Join consecutive writes to the same stream
+fn merge_streams(outputs: Vec<KernelOutput>) -> Vec<KernelOutput> {
+    let mut merged = Vec::new();
+    for output in outputs {
+        match (merged.last_mut(), output) {
+            (Some(KernelOutput::Stdout(a)), KernelOutput::Stdout(b))
+            | (Some(KernelOutput::Stderr(a)), KernelOutput::Stderr(b)) => a.push_str(&b),
+            (_, output) => merged.push(output),
+        }
+    }
+    merged
+}
+
+/// Get the MIME bundle for a rich output
+fn mime_bundle(output: SmartOutput) -> Option<Json> {
+    Some(match output {
+        SmartOutput::Normal(_) => return None,
+        SmartOutput::Png(bytes, label) => json!({
+            "image/png": BASE64.encode(bytes),
+            "text/plain": label.unwrap_or_else(|| "<image>".into()),
+        }),
+        SmartOutput::Gif(bytes, label) => json!({
+            "image/gif": BASE64.encode(bytes),
+            "text/plain": label.unwrap_or_else(|| "<gif>".into()),
+        }),
+        SmartOutput::Wav(bytes, label) => json!({
+            "text/html": format!(
+                "<audio controls src=\"data:audio/wav;base64,{}\"></audio>",
+                BASE64.encode(bytes)
+            ),
+            "text/plain": label.unwrap_or_else(|| "<audio>".into()),
+        }),
+    })
+}
+
+fn display_content(data: Json) -> Json {
+    json!({ "data": data, "metadata": {}, "transient": {} })
+}
+
+fn invalid_data(e: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, e.to_string())
+}
+
+/// Run a kernel using the given connection file
+///
+/// This blocks until the kernel is shut down.
+pub fn run_kernel(connection_file: &Path) -> io::Result<()> {
+    let info: ConnectionInfo =
+        serde_json::from_str(&fs::read_to_string(connection_file)?).map_err(invalid_data)?;
+    if !info.transport.is_empty() && info.transport != "tcp" {
+        return Err(invalid_data(format!(
+            "Unsupported transport `{}`",
+            info.transport
+        )));
+    }
+    if !info.key.is_empty() && !["", "hmac-sha256"].contains(&info.signature_scheme.as_str()) {
+        return Err(invalid_data(format!(
+            "Unsupported signature scheme `{}`",
+            info.signature_scheme
+        )));
+    }
+    let key = Arc::new(info.key.into_bytes());
+    let addr = |port: u16| format!("{}:{port}", info.ip);
+    let shell = Socket::bind(&addr(info.shell_port), SocketType::Router)?;
+    let control = Socket::bind(&addr(info.control_port), SocketType::Router)?;
+    let _stdin = Socket::bind(&addr(info.stdin_port), SocketType::Router)?;
+    let iopub = Arc::new(Socket::bind(&addr(info.iopub_port), SocketType::Pub)?);
+    let _heartbeat = Socket::bind(&addr(info.hb_port), SocketType::Rep)?;
+
+    let mut kernel = Kernel::new();
+
+    // Control requests are handled on their own thread so that
+    // they can be answered while a cell is running
+    let (shutdown_send, shutdown_recv) = crossbeam_channel::bounded(1);
+    let interrupted = kernel.interrupter();
+    let session = kernel.session.clone();
+    let control_key = key.clone();
+    thread::spawn(move || {
+        for (peer, frames) in control.incoming() {
+            let request = match Message::from_frames(frames, &control_key) {
+                Ok(request) => request,
+                Err(e) => {
+                    eprintln!("Invalid control message: {e}");
+                    continue;
+                }
+            };
+            let (msg_type, content) = match request.msg_type() {
+                "kernel_info_request" => ("kernel_info_reply", kernel_info()),
+                "interrupt_request" => {
+                    interrupted.store(true, Ordering::Relaxed);
+                    ("interrupt_reply", json!({ "status": "ok" }))
+                }
+                "shutdown_request" => ("shutdown_reply", shutdown_reply(&request)),
+                _ => continue,
+            };
+            let reply = Message::reply(&request, &session, msg_type, content);
+            _ = control.send(peer, &reply.to_frames(&control_key));
+            if msg_type == "shutdown_reply" {
+                _ = shutdown_send.send(());
+                break;
+            }
+        }
+    });
+
+    let starting = Message::broadcast(
+        &Message::default(),
+        &kernel.session,
+        "status",
+        json!({ "execution_state": "starting" }),
+    );
+    iopub.broadcast(&starting.to_frames(&key));
+
+    loop {
+        select! {
+            recv(shell.incoming()) -> msg => {
+                let Ok((peer, frames)) = msg else {
+                    break;
+                };
+                let request = match Message::from_frames(frames, &key) {
+                    Ok(request) => request,
+                    Err(e) => {
+                        eprintln!("Invalid shell message: {e}");
+                        continue;
+                    }
+                };
+                let reply = kernel.handle(&request, |msg| iopub.broadcast(&msg.to_frames(&key)));
+                if let Some(reply) = reply {
+                    if let Err(e) = shell.send(peer, &reply.to_frames(&key)) {
+                        eprintln!("Failed to send reply: {e}");
+                    }
+                }
+                if request.msg_type() == "shutdown_request" {
+                    break;
+                }
+            }
+            recv(shutdown_recv) -> _ => break,
+        }
+    }
+    Ok(())
+}
+
+/// Install the kernel spec so that Jupyter can find the kernel
+///
+/// Returns the directory the spec was written to
+pub fn install_kernel_spec() -> io::Result<PathBuf> {
+    let data_dir = if let Ok(dir) = env::var("JUPYTER_DATA_DIR") {
+        PathBuf::from(dir)
+    } else if cfg!(windows) {
+        PathBuf::from(env::var("APPDATA").map_err(invalid_data)?).join("jupyter")
+    } else if cfg!(target_os = "macos") {
+        PathBuf::from(env::var("HOME").map_err(invalid_data)?).join("Library/Jupyter")
+    } else {
+        PathBuf::from(env::var("HOME").map_err(invalid_data)?).join(".local/share/jupyter")
+    };
+    let dir = data_dir.join("kernels").join("uiua");
+    fs::create_dir_all(&dir)?;
+    let exe = env::current_exe()?;
+    let spec = json!({
+        "argv": [exe.to_string_lossy(), "jupyter", "{connection_file}"],
+        "display_name": "Uiua",
+        "language": "uiua",
+        "interrupt_mode": "message",
+    });
+    let spec = serde_json::to_string_pretty(&spec).map_err(invalid_data)?;
+    fs::write(dir.join("kernel.json"), spec)?;
+    Ok(dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use super::*;
+
+    fn execute(kernel: &mut Kernel, code: &str) -> (Message, Vec<Message>) {
+        let request = Message::new("execute_request", json!({ "code": code }));
+        let mut emitted = Vec::new();
+        let reply = kernel.handle(&request, |msg| emitted.push(msg)).unwrap();
+        (reply, emitted)
+    }
+
+    fn result_text(emitted: &[Message]) -> Option<String> {
+        let result = emitted
+            .iter()
+            .find(|msg| msg.msg_type() == "execute_result")?;
+        Some(result.content["data"]["text/plain"].as_str()?.into())
+    }
+
+    #[test]
+    fn execute_persists_bindings() {
+        let mut kernel = Kernel::new();
+        let (reply, emitted) = execute(&mut kernel, "X ← 5\nF ← +1");
+        assert_eq!(reply.content["status"], "ok");
+        assert_eq!(emitted.first().unwrap().content["execution_state"], "busy");
+        assert_eq!(emitted.last().unwrap().content["execution_state"], "idle");
+        let (reply, emitted) = execute(&mut kernel, "F X");
+        assert_eq!(reply.content["status"], "ok");
+        assert_eq!(reply.content["execution_count"], 2);
+        assert_eq!(result_text(&emitted).as_deref(), Some("6"));
+        let (_, emitted) = execute(&mut kernel, "&p \"hi\"");
+        let stream = emitted.iter().find(|msg| msg.msg_type() == "stream");
+        assert_eq!(stream.unwrap().content["text"], "hi\n");
+    }
+
+    #[test]
+    fn execute_error_restores_session() {
+        let mut kernel = Kernel::new();
+        execute(&mut kernel, "X ← 1");
+        let (reply, emitted) = execute(&mut kernel, "Y ← 2\n⊢[]");
+        assert_eq!(reply.content["status"], "error");
+        assert!(emitted.iter().any(|msg| msg.msg_type() == "error"));
+        let (reply, _) = execute(&mut kernel, "Y");
+        assert_eq!(reply.content["status"], "error");
+        let (_, emitted) = execute(&mut kernel, "X");
+        assert_eq!(result_text(&emitted).as_deref(), Some("1"));
+    }
+
+    #[cfg(feature = "image")]
+    #[test]
+    fn execute_image() {
+        let mut kernel = Kernel::new();
+        let (_, emitted) = execute(&mut kernel, "÷100 ⊞+.⇡100");
+        let display = emitted.iter().find(|msg| msg.msg_type() == "display_data");
+        assert!(display.unwrap().content["data"]["image/png"].is_string());
+    }
+
+    #[test]
+    fn complete_and_inspect() {
+        let mut kernel = Kernel::new();
+        execute(&mut kernel, "# Adds one\nIncrement ← +1");
+        let reply = kernel.complete(&json!({ "code": "rev", "cursor_pos": 3 }));
+        assert!(reply["matches"].as_array().unwrap().contains(&json!("⇌")));
+        assert_eq!(reply["cursor_start"], 0);
+        let reply = kernel.complete(&json!({ "code": "1 Incr", "cursor_pos": 6 }));
+        assert_eq!(reply["matches"], json!(["Increment"]));
+        assert_eq!(reply["cursor_start"], 2);
+        let reply = kernel.inspect(&json!({ "code": "Increment 5", "cursor_pos": 3 }));
+        let text = reply["data"]["text/plain"].as_str().unwrap();
+        assert!(text.contains("Adds one"), "{text}");
+        let reply = kernel.inspect(&json!({ "code": "⇌", "cursor_pos": 1 }));
+        assert_eq!(reply["found"], true);
+    }
+
+    #[test]
+    fn is_complete_status() {
+        let check = |code: &str| is_complete(&json!({ "code": code }))["status"].clone();
+        assert_eq!(check("+1 2"), "complete");
+        assert_eq!(check("[1 2"), "incomplete");
+        assert_eq!(check("(+1"), "incomplete");
+        assert_eq!(check("1)"), "invalid");
+    }
+
+    #[test]
+    fn signed_round_trip() {
+        let key = b"secret";
+        let message = Message::new("kernel_info_request", json!({}));
+        let mut frames = message.to_frames(key);
+        let decoded = Message::from_frames(frames.clone(), key).unwrap();
+        assert_eq!(decoded.header.msg_id, message.header.msg_id);
+        let last = frames.len() - 1;
+        frames[last] = b"{\"tampered\": true}".to_vec();
+        assert!(Message::from_frames(frames, key).is_err());
+    }
+
+    #[test]
+    fn zmq_loopback() {
+        let socket = Socket::bind("127.0.0.1:0", SocketType::Router).unwrap();
+        let mut client = TcpStream::connect(socket.addr()).unwrap();
+        zmq::handshake(&mut client, SocketType::Dealer).unwrap();
+        let request = Message::new("kernel_info_request", json!({}));
+        zmq::write_message(&mut client, &request.to_frames(b"key")).unwrap();
+        let (peer, frames) = socket.incoming().recv().unwrap();
+        let request = Message::from_frames(frames, b"key").unwrap();
+        let reply = Kernel::new().handle(&request, drop).unwrap();
+        socket.send(peer, &reply.to_frames(b"key")).unwrap();
+        let frames = zmq::read_message(&mut client).unwrap();
+        let reply = Message::from_frames(frames, b"key").unwrap();
+        assert_eq!(reply.msg_type(), "kernel_info_reply");
+        assert_eq!(reply.content["language_info"]["name"], "uiua");
+    }
+}
@@ -0,0 +1,255 @@
+//! A minimal ZeroMQ transport
+//!
+//! This implements just enough of ZMTP 3.0 over TCP to talk to Jupyter frontends.
+//! Only the `NULL` security mechanism is supported, which is what Jupyter uses
+//! when messages are authenticated with HMAC signatures instead.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::Arc,
+    thread,
+};
+
+use crossbeam_channel::{unbounded, Receiver, Sender};
+use parking_lot::Mutex;
+
+const FLAG_MORE: u8 = 0x01;
+const FLAG_LONG: u8 = 0x02;
+const FLAG_COMMAND: u8 = 0x04;
+
+/// The largest frame a peer may send
+const MAX_FRAME_SIZE: u64 = 1 << 28;
+
+/// The kinds of sockets a kernel binds
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocketType {
+    /// Receives requests from any number of peers and replies to a specific one
+    Router,
+    /// Broadcasts messages to all peers
+    Pub,
+    /// Echoes every message back to its sender
+    Rep,
+    /// The client side of a `Router`, used for testing
+    #[allow(dead_code)]
+    Dealer,
+}
+
+impl SocketType {
+    fn name(self) -> &'static str {
+        match self {
+            SocketType::Router => "ROUTER",
+            SocketType::Pub => "PUB",
+            SocketType::Rep => "REP",
+            SocketType::Dealer => "DEALER",
+        }
+    }
+}
+
+type Peers = Arc<Mutex<HashMap<u64, TcpStream>>>;
+
+/// A bound ZeroMQ socket
+pub(crate) struct Socket {
+    addr: SocketAddr,
+    peers: Peers,
+    incoming: Receiver<(u64, Vec<Vec<u8>>)>,
+}
+
+impl Socket {
+    /// Bind a socket to an address and start accepting peers
+    pub fn bind(addr: &str, ty: SocketType) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let addr = listener.local_addr()?;
+        let peers = Peers::default();
+        let (send, incoming) = unbounded();
+        let accept_peers = peers.clone();
+        thread::spawn(move || {
+            for (id, stream) in (0..).zip(listener.incoming()) {
+                let Ok(stream) = stream else {
+                    continue;
+                };
+                let peers = accept_peers.clone();
+                let send = send.clone();
+                thread::spawn(move || {
+                    _ = serve_peer(id, stream, ty, &peers, &send);
+                    peers.lock().remove(&id);
+                });
+            }
+        });
+        Ok(Socket {
+            addr,
+            peers,
+            incoming,
+        })
+    }
+    /// The address the socket is bound to
+    #[allow(dead_code)]
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+    /// Messages received from peers, tagged with the peer's id
+    pub fn incoming(&self) -> &Receiver<(u64, Vec<Vec<u8>>)> {
+        &self.incoming
+    }
+    /// Send a message to a specific peer
+    pub fn send(&self, peer: u64, frames: &[Vec<u8>]) -> io::Result<()> {
+        let stream = self.peers.lock().get(&peer).map(TcpStream::try_clone);
+        let mut stream = stream
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotConnected, "Peer disconnected"))??;
+        write_message(&mut stream, frames)
+    }
+    /// Send a message to all peers
+    ///
+    /// Peers are written to without holding the lock so that a stalled peer
+    /// does not block the others from connecting or disconnecting.
+    pub fn broadcast(&self, frames: &[Vec<u8>]) {
+        let streams: Vec<_> = (self.peers.lock().iter())
+            .filter_map(|(id, stream)| Some((*id, stream.try_clone().ok()?)))
+            .collect();
+        for (id, mut stream) in streams {
+            if write_message(&mut stream, frames).is_err() {
+                self.peers.lock().remove(&id);
+            }
+        }
+    }
+}
+
+fn serve_peer(
+    id: u64,
+    mut stream: TcpStream,
+    ty: SocketType,
+    peers: &Peers,
+    send: &Sender<(u64, Vec<Vec<u8>>)>,
+) -> io::Result<()> {
+    handshake(&mut stream, ty)?;
+    peers.lock().insert(id, stream.try_clone()?);
+    loop {
+        let frames = read_message(&mut stream)?;
+        match ty {
+            // Heartbeats are echoed with their envelope intact
+            SocketType::Rep => write_message(&mut stream, &frames)?,
+            // Subscriptions are ignored because everything is published to everyone
+            SocketType::Pub => {}
+            SocketType::Router | SocketType::Dealer => {
+                if send.send((id, frames)).is_err() {
+                    return Ok(());
+                }
+            }
+        }
+    }
+}
+
+/// Perform the ZMTP greeting and `NULL` mechanism handshake
+pub(crate) fn handshake(stream: &mut TcpStream, ty: SocketType) -> io::Result<()> {
+    let mut greeting = [0u8; 64];
+    greeting[0] = 0xff;
+    greeting[9] = 0x7f;
+    greeting[10] = 3;
+    greeting[12..16].copy_from_slice(b"NULL");
+    stream.write_all(&greeting)?;
+
+    let mut peer = [0u8; 64];
+    stream.read_exact(&mut peer)?;
+    if peer[0] != 0xff || peer[9] & 1 == 0 {
+        return Err(invalid("Peer did not send a ZMTP greeting"));
+    }
+    if peer[10] < 3 {
+        return Err(invalid(format!(
+            "Peer uses ZMTP version {}, but at least 3 is required",
+            peer[10]
+        )));
+    }
+    if &peer[12..16] != b"NULL" || peer[16..32].iter().any(|&b| b != 0) {
+        return Err(invalid("Only the NULL security mechanism is supported"));
+    }
+
+    let mut ready = vec![5];
+    ready.extend_from_slice(b"READY");
+    let name = b"Socket-Type";
+    ready.push(name.len() as u8);
+    ready.extend_from_slice(name);
+    ready.extend_from_slice(&(ty.name().len() as u32).to_be_bytes());
+    ready.extend_from_slice(ty.name().as_bytes());
+    write_frame(stream, &ready, FLAG_COMMAND)?;
+
+    let (flags, body) = read_frame(stream)?;
+    if flags & FLAG_COMMAND == 0 || !body.starts_with(b"\x05READY") {
+        return Err(invalid("Peer did not send a READY command"));
+    }
+    Ok(())
+}
+
+fn invalid(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+fn read_frame(stream: &mut TcpStream) -> io::Result<(u8, Vec<u8>)> {
+    let mut flags = 0;
+    stream.read_exact(std::slice::from_mut(&mut flags))?;
+    let size = if flags & FLAG_LONG != 0 {
+        let mut size = [0; 8];
+        stream.read_exact(&mut size)?;
+        u64::from_be_bytes(size)
+    } else {
+        let mut size = 0;
+        stream.read_exact(std::slice::from_mut(&mut size))?;
+        size as u64
+    };
+    if size > MAX_FRAME_SIZE {
+        return Err(invalid(format!(
+            "Frame of {size} bytes exceeds the maximum of {MAX_FRAME_SIZE}"
+        )));
+    }
+    // The buffer grows as data arrives rather than trusting the declared size
+    let mut body = Vec::new();
+    Read::by_ref(stream).take(size).read_to_end(&mut body)?;
+    if body.len() as u64 != size {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok((flags, body))
+}
+
+fn write_frame(stream: &mut TcpStream, body: &[u8], flags: u8) -> io::Result<()> {
+    let mut buffer = Vec::with_capacity(body.len() + 9);
+    encode_frame(&mut buffer, body, flags);
+    stream.write_all(&buffer)
+}
+
+fn encode_frame(buffer: &mut Vec<u8>, body: &[u8], mut flags: u8) {
+    if body.len() > u8::MAX as usize {
+        flags |= FLAG_LONG;
+        buffer.push(flags);
+        buffer.extend_from_slice(&(body.len() as u64).to_be_bytes());
+    } else {
+        buffer.push(flags);
+        buffer.push(body.len() as u8);
+    }
+    buffer.extend_from_slice(body);
+}
+
+/// Read a multipart message, skipping any commands
+pub(crate) fn read_message(stream: &mut TcpStream) -> io::Result<Vec<Vec<u8>>> {
+    let mut frames = Vec::new();
+    loop {
+        let (flags, body) = read_frame(stream)?;
+        if flags & FLAG_COMMAND != 0 {
+            continue;
+        }
+        frames.push(body);
+        if flags & FLAG_MORE == 0 {
+            return Ok(frames);
+        }
+    }
+}
+
+/// Write a multipart message
+pub(crate) fn write_message(stream: &mut TcpStream, frames: &[Vec<u8>]) -> io::Result<()> {
+    let mut buffer = Vec::new();
+    for (i, frame) in frames.iter().enumerate() {
+        let flags = if i + 1 < frames.len() { FLAG_MORE } else { 0 };
+        encode_frame(&mut buffer, frame, flags);
+    }
+    stream.write_all(&buffer)?;
+    stream.flush()
+}
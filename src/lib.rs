@@ -133,6 +133,7 @@ The `uiua` crate has the following noteable feature flags:
 - `invoke`: Enables the `&invk` system function
 - `trash`: Enables the `&ftr` system function
 - `raw_mode`: Enables the `&raw` system function
+- `jupyter`: Enables the `uiua jupyter` kernel for Jupyter notebooks
 */
 
 #![allow(
@@ -147,18 +148,29 @@ mod algorithm;
 mod array;
 mod assembly;
 pub mod ast;
+pub mod bench;
 mod boxed;
 mod check;
 mod compile;
 mod complex;
 mod cowslice;
+pub mod cst;
+#[cfg(feature = "native_sys")]
+pub mod docgen;
 mod error;
 mod ffi;
 mod fill;
 pub mod format;
 mod function;
 mod grid_fmt;
+#[cfg(feature = "native_sys")]
+pub mod highlight;
+#[cfg(feature = "jupyter")]
+#[doc(hidden)]
+pub mod jupyter;
 mod lex;
+pub mod lint;
+mod literate;
 pub mod lsp;
 mod parse;
 mod primitive;
@@ -170,6 +182,7 @@ mod shape;
 #[doc(hidden)]
 pub mod stand;
 mod sys;
+pub mod testing;
 mod tree;
 mod types;
 mod value;
@@ -213,7 +226,7 @@ pub type Ident = EcoString;
 #[cfg(test)]
 mod tests {
     use std::path::*;
-    fn test_files(filter: impl Fn(&Path) -> bool) -> impl Iterator<Item = PathBuf> {
+    pub(crate) fn test_files(filter: impl Fn(&Path) -> bool) -> impl Iterator<Item = PathBuf> {
         std::fs::read_dir("tests")
             .unwrap()
             .map(|entry| entry.unwrap().path())
@@ -310,6 +323,32 @@ mod tests {
         env.run_asm(asm).unwrap();
     }
 
+    #[test]
+    #[cfg(feature = "native_sys")]
+    fn suite_infer_types() {
+        use super::*;
+        for path in test_files(|path| {
+            !(path.file_stem().unwrap())
+                .to_string_lossy()
+                .contains("error")
+        }) {
+            let code = std::fs::read_to_string(&path).unwrap();
+            let mut comp = Compiler::new();
+            if let Err(e) = comp.infer_types(true).load_str_src(&code, &path) {
+                panic!("Test failed in {}:\n{}", path.display(), e.report());
+            }
+            if let Some(diag) =
+                (comp.take_diagnostics().into_iter()).find(|diag| diag.lint() == Some("type-error"))
+            {
+                panic!(
+                    "Type inference false positive in {}:\n{}",
+                    path.display(),
+                    diag.report()
+                );
+            }
+        }
+    }
+
     #[test]
     fn lsp_spans() {
         use super::*;
@@ -0,0 +1,406 @@
+//! A configurable lint engine with named rules
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use crate::{
+    ast::{Item, ModuleKind},
+    lex::{lex, Token},
+    parse, BindingKind, CodeSpan, Compiler, Diagnostic, DiagnosticKind, Ident, InputSrc, Inputs,
+    IntoSysBackend, Report, ReportKind, Sp, Span, UiuaResult,
+};
+
+/// The name of the lint configuration file
+pub const LINT_FILE: &str = ".uiua-lint";
+
+/// How a lint rule is treated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LintLevel {
+    /// The rule is not checked
+    Allow,
+    /// Violations are reported as warnings
+    Warn,
+    /// Violations are reported as errors
+    Deny,
+}
+
+impl FromStr for LintLevel {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "allow" => Ok(LintLevel::Allow),
+            "warn" => Ok(LintLevel::Warn),
+            "deny" => Ok(LintLevel::Deny),
+            _ => Err(format!("unknown lint level `{s}`")),
+        }
+    }
+}
+
+/// A lint rule
+#[derive(Debug, Clone, Copy)]
+pub struct LintRule {
+    /// The name of the rule
+    pub name: &'static str,
+    /// What the rule checks for
+    pub description: &'static str,
+    /// The level of the rule if it is not configured
+    pub default: LintLevel,
+}
+
+/// All lint rules
+pub const LINT_RULES: &[LintRule] = &[
+    LintRule {
+        name: "unused-binding",
+        description: "A private binding is never used",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "unused-public-binding",
+        description: "A public binding is never used in its own file",
+        default: LintLevel::Allow,
+    },
+    LintRule {
+        name: "shadowed-binding",
+        description: "A binding has the same name as an earlier binding in the same scope",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "deprecated",
+        description: "A deprecated primitive or feature is used",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "redundant-stack-op",
+        description: "Stack manipulation that can be simplified or removed",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "redundant-modifier",
+        description: "A modifier that does nothing for its function",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "char-array",
+        description: "Characters that should be written as a string",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "long-line",
+        description: "A line that should be split into multiple lines",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "signature-mismatch",
+        description: "A signature comment does not match the code",
+        default: LintLevel::Deny,
+    },
+    LintRule {
+        name: "missing-signature",
+        description: "A function binding has no signature comment",
+        default: LintLevel::Allow,
+    },
+    LintRule {
+        name: "type-error",
+        description: "An operation that will likely fail given the inferred types of its arguments",
+        default: LintLevel::Warn,
+    },
+    LintRule {
+        name: "compiler-warning",
+        description: "Any other compiler warning",
+        default: LintLevel::Warn,
+    },
+];
+
+/// Lint rule levels loaded from a `.uiua-lint` file
+///
+/// Each line of the file is a rule name and a level, like `missing-signature = warn`.
+/// The name `all` sets the level of every rule.
+#[derive(Debug, Clone, Default)]
+pub struct LintConfig {
+    all: Option<LintLevel>,
+    levels: HashMap<String, LintLevel>,
+}
+
+impl FromStr for LintConfig {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut config = LintConfig::default();
+        for (i, line) in s.lines().enumerate() {
+            let line = line.split('#').next().unwrap().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, level) = (line.split_once('='))
+                .ok_or_else(|| format!("line {}: expected `rule = level`", i + 1))?;
+            let (name, level) = (name.trim(), level.trim());
+            let level = level.parse().map_err(|e| format!("line {}: {e}", i + 1))?;
+            if name == "all" {
+                config.all = Some(level);
+            } else if LINT_RULES.iter().any(|rule| rule.name == name) {
+                config.levels.insert(name.into(), level);
+            } else {
+                return Err(format!("line {}: unknown lint rule `{name}`", i + 1));
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl LintConfig {
+    /// Find the lint configuration for a file
+    ///
+    /// This searches the file's directory and its ancestors for a `.uiua-lint` file.
+    pub fn find(path: &Path) -> Result<Self, String> {
+        let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+        let dir = dir.map(PathBuf::from).unwrap_or_else(|| ".".into());
+        let dir = dir.canonicalize().unwrap_or(dir);
+        for dir in dir.ancestors() {
+            let path = dir.join(LINT_FILE);
+            if path.exists() {
+                let text = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+                return text.parse().map_err(|e| format!("{}: {e}", path.display()));
+            }
+        }
+        Ok(Self::default())
+    }
+    /// Set the level of a rule
+    pub fn set(&mut self, rule: &str, level: LintLevel) -> &mut Self {
+        self.levels.insert(rule.into(), level);
+        self
+    }
+    /// Get the level of a rule
+    pub fn level(&self, rule: &str) -> LintLevel {
+        if let Some(level) = self.levels.get(rule) {
+            return *level;
+        }
+        self.all.unwrap_or_else(|| {
+            (LINT_RULES.iter())
+                .find(|r| r.name == rule)
+                .map_or(LintLevel::Warn, |r| r.default)
+        })
+    }
+}
+
+/// A violation of a lint rule
+#[derive(Debug, Clone)]
+pub struct Lint {
+    /// The level of the rule
+    pub level: LintLevel,
+    /// The diagnostic describing the violation
+    pub diagnostic: Diagnostic,
+}
+
+impl Lint {
+    /// Get the name of the violated rule
+    pub fn rule(&self) -> &'static str {
+        self.diagnostic.lint().unwrap_or("compiler-warning")
+    }
+    /// Get a rich-text report for the lint
+    pub fn report(&self) -> Report {
+        let kind = match self.level {
+            LintLevel::Deny => ReportKind::Error,
+            _ => ReportKind::Diagnostic(DiagnosticKind::Warning),
+        };
+        let message = format!("{} [{}]", self.diagnostic.message, self.rule());
+        Report::new_multi(
+            kind,
+            &self.diagnostic.inputs,
+            [(message, self.diagnostic.span.clone())],
+        )
+    }
+}
+
+/// Lint a file
+pub fn lint_file(
+    path: &Path,
+    config: &LintConfig,
+    backend: impl IntoSysBackend,
+) -> UiuaResult<Vec<Lint>> {
+    let input =
+        fs::read_to_string(path).map_err(|e| crate::UiuaErrorKind::Load(path.into(), e.into()))?;
+    lint(&input, path, config, backend)
+}
+
+/// Lint some code as if it were in the file at the given path
+///
+/// Rules can be suppressed with a `# lint: allow(name)` comment
+/// on the line before the violation or at the end of its line.
+pub fn lint(
+    input: &str,
+    path: &Path,
+    config: &LintConfig,
+    backend: impl IntoSysBackend,
+) -> UiuaResult<Vec<Lint>> {
+    let mut comp = Compiler::with_backend(backend);
+    comp.print_diagnostics(false).infer_types(true);
+    comp.load_str_src(input, path)?;
+    let src = InputSrc::File(path.into());
+    let inputs = comp.asm.inputs.clone();
+    let mut diagnostics: Vec<Diagnostic> = (comp.take_diagnostics().into_iter())
+        .filter(|diag| diag.lint().is_some() || diag.kind == DiagnosticKind::Warning)
+        .collect();
+    let mut emit = |lint: &'static str, message: String, span: CodeSpan| {
+        let diag = Diagnostic::new(message, span, DiagnosticKind::Warning, inputs.clone());
+        diagnostics.push(diag.with_lint(lint));
+    };
+
+    // Rules that need the syntax tree
+    let (items, _, _) = parse(input, src.clone(), &mut Inputs::default());
+    let mut names = Vec::new();
+    collect_binding_names(&items, &mut names, &mut emit);
+    for (name, public, explicit_sig) in names {
+        let Some(index) = (comp.asm.bindings.iter()).position(|info| info.span == name.span) else {
+            continue;
+        };
+        let referenced = (comp.code_meta.global_references.values()).any(|&i| i == index);
+        if !referenced {
+            emit(
+                if public {
+                    "unused-public-binding"
+                } else {
+                    "unused-binding"
+                },
+                format!("`{}` is never used", name.value),
+                name.span.clone(),
+            );
+        }
+        let info = &comp.asm.bindings[index];
+        if let BindingKind::Func(f) = &info.kind {
+            let commented = (info.comment.as_ref()).is_some_and(|comment| comment.sig.is_some());
+            if f.sig.args > 0 && !commented && !explicit_sig {
+                emit(
+                    "missing-signature",
+                    format!("`{}` has no signature comment", name.value),
+                    name.span.clone(),
+                );
+            }
+        }
+    }
+
+    // Apply levels and suppressions
+    let allowed = allow_comments(input);
+    let mut lints: Vec<Lint> = (diagnostics.into_iter())
+        .filter_map(|diagnostic| {
+            let Span::Code(span) = &diagnostic.span else {
+                return None;
+            };
+            if span.src != src {
+                return None;
+            }
+            let rule = diagnostic.lint().unwrap_or("compiler-warning");
+            let line = span.start.line as usize;
+            if (allowed.get(&line)).is_some_and(|rules| rules.contains(rule)) {
+                return None;
+            }
+            let level = config.level(rule);
+            (level > LintLevel::Allow).then_some(Lint { level, diagnostic })
+        })
+        .collect();
+    lints.sort_by(|a, b| a.diagnostic.span.cmp(&b.diagnostic.span));
+    Ok(lints)
+}
+
+/// Collect the names of bindings and check for shadowing
+fn collect_binding_names(
+    items: &[Item],
+    names: &mut Vec<(Sp<Ident>, bool, bool)>,
+    emit: &mut impl FnMut(&'static str, String, CodeSpan),
+) {
+    let mut scope = HashSet::new();
+    let mut shadow = |name: &Sp<Ident>, emit: &mut dyn FnMut(&'static str, String, CodeSpan)| {
+        if !scope.insert(name.value.clone()) {
+            emit(
+                "shadowed-binding",
+                format!("`{}` shadows an earlier binding", name.value),
+                name.span.clone(),
+            );
+        }
+    };
+    for item in items {
+        match item {
+            Item::Binding(binding) => {
+                shadow(&binding.name, emit);
+                names.push((
+                    binding.name.clone(),
+                    binding.public,
+                    binding.signature.is_some(),
+                ));
+            }
+            Item::Module(module) => {
+                if let ModuleKind::Named(name) = &module.value.kind {
+                    shadow(name, emit);
+                }
+                collect_binding_names(&module.value.items, names, emit);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Get the rules allowed on each line by `# lint: allow(...)` comments
+///
+/// A comment on its own line applies to the next line.
+fn allow_comments(input: &str) -> HashMap<usize, HashSet<&str>> {
+    let mut allowed = HashMap::new();
+    let (tokens, _, _) = lex(input, (), &mut Inputs::default());
+    let mut code_line = 0;
+    for token in tokens {
+        match token.value {
+            Token::Comment => {}
+            Token::Spaces | Token::Newline => continue,
+            _ => {
+                code_line = token.span.start.line;
+                continue;
+            }
+        }
+        let text = &input[token.span.byte_range()];
+        let Some(rest) = (text.strip_prefix('#').map(str::trim_start))
+            .and_then(|text| text.strip_prefix("lint: allow("))
+        else {
+            continue;
+        };
+        let Some((rules, _)) = rest.split_once(')') else {
+            continue;
+        };
+        let rules = rules.split(',').map(str::trim).filter(|s| !s.is_empty());
+        let line = token.span.start.line as usize;
+        let line = if code_line == token.span.start.line {
+            line
+        } else {
+            line + 1
+        };
+        allowed.insert(line, rules.collect());
+    }
+    allowed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SafeSys;
+
+    #[test]
+    fn lint() {
+        let code = "\
+X ↚ 5
+Y ← 6
+Y ← ⊃∘+ # lint: allow(redundant-stack-op)
+# lint: allow(unused-binding)
+Z ↚ 7
+W ↚ \"# lint: allow(unused-binding)\"";
+        let path = Path::new("lint.ua");
+        let lints = super::lint(code, path, &LintConfig::default(), SafeSys::default()).unwrap();
+        let rules: Vec<_> = lints.iter().map(Lint::rule).collect();
+        assert_eq!(
+            rules,
+            ["unused-binding", "shadowed-binding", "unused-binding"]
+        );
+        let config: LintConfig = "all = allow\nunused-public-binding = deny".parse().unwrap();
+        let lints = super::lint(code, path, &config, SafeSys::default()).unwrap();
+        assert!(lints.len() == 2 && lints.iter().all(|l| l.level == LintLevel::Deny));
+    }
+}
@@ -0,0 +1,275 @@
+//! Literate Uiua
+//!
+//! Uiua code can be embedded in Markdown files in fenced code blocks tagged `uiua`.
+//! All such blocks in a file are compiled together as a single module.
+//!
+//! Extracted code keeps the line structure of the Markdown file. Lines outside
+//! of Uiua blocks are blanked out and code lines keep their indentation, so spans
+//! in the extracted code point at the same lines and columns as in the Markdown file.
+
+use std::path::Path;
+
+/// The comment that marks the end of each code block when formatting
+const BLOCK_END_MARKER: &str = "# ─── end of Markdown code block ───";
+
+/// A fenced Uiua code block in a Markdown document
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct CodeBlock {
+    /// The indentation of the opening fence
+    indent: usize,
+    /// The index of the first line of code
+    start: usize,
+    /// The index of the closing fence line
+    ///
+    /// This is the number of lines if the block is never closed
+    end: usize,
+}
+
+/// Whether a path has a Markdown file extension
+pub(crate) fn is_markdown_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown"))
+}
+
+/// Find all fenced Uiua code blocks in a Markdown document
+pub(crate) fn code_blocks(markdown: &str) -> Vec<CodeBlock> {
+    struct Fence {
+        c: char,
+        len: usize,
+        indent: usize,
+        start: usize,
+        is_uiua: bool,
+    }
+    let mut blocks = Vec::new();
+    let mut open: Option<Fence> = None;
+    let mut line_count = 0;
+    for (i, line) in markdown.lines().enumerate() {
+        line_count += 1;
+        let trimmed = line.trim_start_matches(' ');
+        let indent = line.len() - trimmed.len();
+        let c = trimmed.chars().next().unwrap_or(' ');
+        let len = trimmed.chars().take_while(|&d| d == c).count();
+        let is_fence = indent <= 3 && (c == '`' || c == '~') && len >= 3;
+        let info = if is_fence { trimmed[len..].trim() } else { "" };
+        match &open {
+            None if is_fence => {
+                let lang = info.split_whitespace().next().unwrap_or_default();
+                open = Some(Fence {
+                    c,
+                    len,
+                    indent,
+                    start: i + 1,
+                    is_uiua: lang.eq_ignore_ascii_case("uiua"),
+                });
+            }
+            Some(fence) if is_fence && c == fence.c && len >= fence.len && info.is_empty() => {
+                if fence.is_uiua {
+                    blocks.push(CodeBlock {
+                        indent: fence.indent,
+                        start: fence.start,
+                        end: i,
+                    });
+                }
+                open = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(fence) = open.filter(|fence| fence.is_uiua) {
+        blocks.push(CodeBlock {
+            indent: fence.indent,
+            start: fence.start,
+            end: line_count,
+        });
+    }
+    blocks
+}
+
+/// Extract the Uiua code from a Markdown document
+pub(crate) fn extract_uiua(markdown: &str) -> String {
+    extract_impl(markdown, &code_blocks(markdown), false)
+}
+
+/// Extract the Uiua code from a Markdown document,
+/// marking the end of each block so that it can be split up again after formatting
+pub(crate) fn extract_uiua_with_markers(markdown: &str, blocks: &[CodeBlock]) -> String {
+    extract_impl(markdown, blocks, true)
+}
+
+fn extract_impl(markdown: &str, blocks: &[CodeBlock], markers: bool) -> String {
+    let mut code = String::new();
+    let mut blocks = blocks.iter().peekable();
+    let mut line_count = 0;
+    for (i, line) in markdown.lines().enumerate() {
+        line_count += 1;
+        if let Some(block) = blocks.peek() {
+            if (block.start..block.end).contains(&i) {
+                code.push_str(line);
+            } else if i == block.end {
+                if markers {
+                    code.push_str(BLOCK_END_MARKER);
+                }
+                blocks.next();
+            }
+        }
+        code.push('\n');
+    }
+    if markers && blocks.next().is_some_and(|block| block.end == line_count) {
+        code.push_str(BLOCK_END_MARKER);
+        code.push('\n');
+    }
+    code
+}
+
+/// Replace the code blocks of a Markdown document with formatted code
+///
+/// `formatted` should be the formatted version of the code
+/// returned by [`extract_uiua_with_markers`].
+/// Returns `None` if the formatted code does not have a segment for every block.
+pub(crate) fn replace_code_blocks(
+    markdown: &str,
+    blocks: &[CodeBlock],
+    formatted: &str,
+) -> Option<String> {
+    let mut segments = vec![Vec::new()];
+    for line in formatted.lines() {
+        if line.trim() == BLOCK_END_MARKER {
+            segments.push(Vec::new());
+        } else {
+            segments.last_mut().unwrap().push(line);
+        }
+    }
+    if segments.len() != blocks.len() + 1 {
+        return None;
+    }
+    for segment in &mut segments {
+        while segment.first().is_some_and(|line| line.trim().is_empty()) {
+            segment.remove(0);
+        }
+        while segment.last().is_some_and(|line| line.trim().is_empty()) {
+            segment.pop();
+        }
+    }
+
+    let lines: Vec<&str> = markdown.lines().collect();
+    let mut output = String::new();
+    let mut i = 0;
+    for (block, segment) in blocks.iter().zip(&segments) {
+        for line in &lines[i..block.start] {
+            output.push_str(line);
+            output.push('\n');
+        }
+        for line in segment {
+            if !line.is_empty() {
+                output.push_str(&" ".repeat(block.indent));
+            }
+            output.push_str(line);
+            output.push('\n');
+        }
+        i = block.end;
+    }
+    for line in &lines[i..] {
+        output.push_str(line);
+        output.push('\n');
+    }
+    if !markdown.ends_with('\n') {
+        output.pop();
+    }
+    Some(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MARKDOWN: &str = "\
+# Notes
+
+Some prose.
+
+```uiua
+X ← 5
+```
+
+```rust
+fn main() {}
+```
+
+  ```uiua
+  +1 X
+  ```
+";
+
+    #[test]
+    fn extract_keeps_lines() {
+        let code = extract_uiua(MARKDOWN);
+        let lines: Vec<&str> = code.lines().collect();
+        assert_eq!(lines.len(), MARKDOWN.lines().count());
+        assert_eq!(lines[5], "X ← 5");
+        assert_eq!(lines[13], "  +1 X");
+        assert!(lines.iter().all(|line| !line.contains("fn main")));
+    }
+
+    #[test]
+    fn replace_blocks() {
+        let blocks = code_blocks(MARKDOWN);
+        assert_eq!(blocks.len(), 2);
+        // The formatter removes the indentation of top-level code
+        let code = extract_uiua_with_markers(MARKDOWN, &blocks).replace("  +1 X", "+1 X");
+        let formatted = code.replace("X ← 5", "X ← 5\nY ← 6");
+        let replaced = replace_code_blocks(MARKDOWN, &blocks, &formatted).unwrap();
+        assert!(replaced.contains("```uiua\nX ← 5\nY ← 6\n```"));
+        assert!(replaced.contains("  ```uiua\n  +1 X\n  ```\n"));
+        assert!(replaced.contains("fn main() {}"));
+        let unchanged = replace_code_blocks(MARKDOWN, &blocks, &code).unwrap();
+        assert_eq!(unchanged, MARKDOWN);
+    }
+
+    #[test]
+    fn indented_block_errors() {
+        use crate::{
+            format::{format, FormatConfig},
+            Compiler, Span, Uiua, UiuaErrorKind,
+        };
+        let dir = std::env::temp_dir().join(format!(
+            "uiua_literate_test_{}_{}",
+            std::process::id(),
+            std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .unwrap()
+                .as_nanos()
+        ));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.md");
+        let markdown = "# Notes\n\n- An item\n\n  ```uiua\n  X = 5\n  ⍤\"oops\" =6 X\n  ```\n";
+        std::fs::write(&path, markdown).unwrap();
+
+        // Runtime errors point at the Markdown file's columns
+        let err = (Compiler::new().load_file(&path))
+            .and_then(|comp| Uiua::with_safe_sys().run_asm(comp.finish()))
+            .unwrap_err();
+        let UiuaErrorKind::Throw(_, Span::Code(span), _) = &err.kind else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!((span.start.line, span.start.col), (7, 3));
+
+        // Formatting keeps the block's indentation
+        let formatted = format(markdown, &path, &FormatConfig::default()).unwrap();
+        assert_eq!(formatted.output, markdown.replace("X = 5", "X ← 5"));
+
+        // Syntax errors point at the Markdown file's columns
+        let broken = markdown.replace("X = 5", "X = 5 )");
+        let Err(err) = format(&broken, &path, &FormatConfig::default()) else {
+            panic!("formatting should fail");
+        };
+        let UiuaErrorKind::Parse(errors, _) = &err.kind else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(
+            (errors[0].span.start.line, errors[0].span.start.col),
+            (6, 9)
+        );
+        _ = std::fs::remove_dir_all(&dir);
+    }
+}
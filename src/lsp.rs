@@ -15,7 +15,8 @@ use crate::{
     lex::{CodeSpan, Sp},
     parse::parse,
     Assembly, BindingInfo, BindingKind, Compiler, DocComment, Ident, InputSrc, Inputs, PreEvalMode,
-    Primitive, Purity, SafeSys, Shape, Signature, SysBackend, UiuaError, Value, CONSTANTS,
+    Primitive, Purity, SafeSys, Shape, Signature, SysBackend, UiuaError, Value, ValueType,
+    CONSTANTS,
 };
 
 /// Kinds of span in Uiua code, meant to be used in the language server or other IDE tools
@@ -176,6 +177,11 @@ pub struct CodeMeta {
     pub import_srcs: HashMap<CodeSpan, ImportSrc>,
     /// A map of obverse spans to their set inverses
     pub obverses: HashMap<CodeSpan, SetInverses>,
+    /// A map of binding names to the inferred types of their values
+    ///
+    /// Function outputs are listed from the top of the stack down.
+    /// Only populated if type inference is enabled.
+    pub inferred_types: HashMap<CodeSpan, Vec<ValueType>>,
 }
 
 /// Data for the signature of a function
@@ -414,6 +420,7 @@ impl Spanner {
                         }
                     }
                 }
+                Item::Error(_) => {}
             }
         }
         spans.sort_by_key(|sp| sp.span.start);
@@ -792,9 +799,21 @@ pub use server::run_language_server;
 
 #[cfg(feature = "lsp")]
 mod server {
-    use std::{env::current_dir, path::Path, sync::Arc};
+    use std::{
+        any::Any,
+        env::current_dir,
+        panic::{catch_unwind, AssertUnwindSafe},
+        path::Path,
+        pin::pin,
+        sync::{mpsc, Arc},
+        thread,
+        time::Duration,
+    };
 
     use dashmap::DashMap;
+    use enum_iterator::all;
+    use parking_lot::Mutex;
+    use tokio::sync::Notify;
     use tower_lsp::{
         jsonrpc::{Error, Result},
         lsp_types::{
@@ -807,24 +826,39 @@ mod server {
     use super::*;
 
     use crate::{
+        ast::Binding,
+        compile::{test_scope_name, ImportCache, ItemCheckpoint},
         format::{format_str, FormatConfig},
         is_ident_char,
-        lex::{lex, Loc},
+        lex::{lex, Loc, SemanticComment, SUBSCRIPT_DIGITS},
         primitive::{PrimClass, PrimDocFragment},
-        AsciiToken, Assembly, BindingInfo, NativeSys, PrimDocLine, Span, Token, UiuaErrorKind,
+        AsciiToken, Assembly, BindingInfo, NativeSys, Node, PrimDocLine, RunMode, Span, Token,
+        Uiua, UiuaErrorKind, UiuaResult,
     };
 
     pub struct LspDoc {
         pub input: String,
+        pub src: InputSrc,
+        pub items: Vec<Item>,
         pub spans: Vec<Sp<SpanKind>>,
         pub asm: Assembly,
         pub code_meta: CodeMeta,
         pub errors: Vec<UiuaError>,
         pub diagnostics: Vec<crate::Diagnostic>,
+        checkpoints: Vec<ItemCheckpoint>,
     }
 
     impl LspDoc {
-        fn new(path: &Path, input: String) -> Self {
+        /// Analyze a document, reusing the work done for its previous version
+        ///
+        /// Returns [`None`] if analysis was canceled
+        fn analyze(
+            path: &Path,
+            input: String,
+            prev: Option<&LspDoc>,
+            import_cache: &ImportCache,
+            cancel: &dyn Fn() -> bool,
+        ) -> Option<Self> {
             let path = path
                 .to_string_lossy()
                 .strip_prefix("\\\\?\\")
@@ -836,17 +870,197 @@ mod server {
                 .unwrap_or(path);
             let src = InputSrc::File(path.into());
             let (items, _, _) = parse(&input, src.clone(), &mut Inputs::default());
-            let spanner = Spanner::new(src, &input, NativeSys);
+            let (checkpoints, first_changed_line) = match prev {
+                Some(prev) if prev.src == src => {
+                    let unchanged = (prev.input.split('\n').zip(input.split('\n')))
+                        .take_while(|(a, b)| a == b)
+                        .count();
+                    (prev.checkpoints.as_slice(), unchanged + 1)
+                }
+                _ => (&[][..], 1),
+            };
+            let mut compiler = Compiler::with_backend(NativeSys);
+            compiler
+                .pre_eval_mode(PreEvalMode::Lsp)
+                .infer_types(true)
+                .import_cache(import_cache.clone());
+            let (res, checkpoints) = compiler.load_incremental(
+                &input,
+                src.clone(),
+                checkpoints,
+                first_changed_line,
+                cancel,
+            )?;
+            let errors = match res {
+                Ok(_) => Vec::new(),
+                Err(e) => e.into_multi(),
+            };
+            let diagnostics = compiler.take_diagnostics().into_iter().collect();
+            let spanner = Spanner {
+                src: src.clone(),
+                asm: compiler.asm,
+                code_meta: compiler.code_meta,
+                errors,
+                diagnostics,
+            };
             let spans = spanner.items_spans(&items);
-            Self {
+            Some(Self {
                 input,
+                src,
+                items,
                 spans,
                 asm: spanner.asm,
                 code_meta: spanner.code_meta,
                 errors: spanner.errors,
                 diagnostics: spanner.diagnostics,
+                checkpoints,
+            })
+        }
+    }
+
+    /// How long to wait after a change before analyzing a document
+    const ANALYSIS_DEBOUNCE: Duration = Duration::from_millis(150);
+
+    /// The text of a document that has changed but not yet been analyzed
+    #[derive(Clone)]
+    struct PendingDoc {
+        version: i32,
+        text: String,
+    }
+
+    /// A request for the analysis thread
+    struct AnalysisJob {
+        uri: Url,
+        /// Whether to wait for further changes before analyzing
+        debounce: bool,
+    }
+
+    /// Analyzed documents and changes waiting to be analyzed
+    ///
+    /// Analysis happens on a single background thread so that it can reuse
+    /// the compiler's thread-local pre-evaluation cache
+    struct Docs {
+        shared: Arc<DocsShared>,
+        jobs: Mutex<mpsc::Sender<AnalysisJob>>,
+    }
+
+    #[derive(Default)]
+    struct DocsShared {
+        analyzed: DashMap<Url, Arc<LspDoc>>,
+        pending: DashMap<Url, PendingDoc>,
+        import_cache: ImportCache,
+        /// Notified whenever a pending change is resolved
+        resolved: Notify,
+    }
+
+    impl Docs {
+        fn new() -> Self {
+            let shared = Arc::new(DocsShared::default());
+            let (send, recv) = mpsc::channel();
+            let worker = shared.clone();
+            thread::spawn(move || worker.run(recv));
+            Docs {
+                shared,
+                jobs: Mutex::new(send),
+            }
+        }
+        /// Record a document's new text and schedule its analysis
+        fn update(&self, uri: Url, version: i32, text: String, debounce: bool) {
+            (self.shared.pending).insert(uri.clone(), PendingDoc { version, text });
+            _ = self.jobs.lock().send(AnalysisJob { uri, debounce });
+        }
+        /// Get an analyzed document, waiting for any pending change to be analyzed
+        async fn get(&self, uri: &Url) -> Option<Arc<LspDoc>> {
+            self.wait_for(uri).await;
+            self.shared.analyzed.get(uri).map(|doc| Arc::clone(&doc))
+        }
+        async fn all(&self) -> Vec<(Url, Arc<LspDoc>)> {
+            let uris: Vec<Url> = (self.shared.pending.iter())
+                .map(|entry| entry.key().clone())
+                .collect();
+            for uri in &uris {
+                self.wait_for(uri).await;
+            }
+            (self.shared.analyzed.iter())
+                .map(|entry| (entry.key().clone(), entry.value().clone()))
+                .collect()
+        }
+        async fn wait_for(&self, uri: &Url) {
+            if !self.shared.pending.contains_key(uri) {
+                return;
+            }
+            let job = AnalysisJob {
+                uri: uri.clone(),
+                debounce: false,
+            };
+            if self.jobs.lock().send(job).is_err() {
+                return;
+            }
+            loop {
+                // Register for notification before checking so that none are missed
+                let mut resolved = pin!(self.shared.resolved.notified());
+                resolved.as_mut().enable();
+                if !self.shared.pending.contains_key(uri) {
+                    return;
+                }
+                resolved.await;
+            }
+        }
+    }
+
+    impl DocsShared {
+        fn run(&self, jobs: mpsc::Receiver<AnalysisJob>) {
+            while let Ok(job) = jobs.recv() {
+                let mut uris = vec![job.uri];
+                let mut debounce = job.debounce;
+                while debounce {
+                    match jobs.recv_timeout(ANALYSIS_DEBOUNCE) {
+                        Ok(job) => {
+                            debounce = job.debounce;
+                            if !uris.contains(&job.uri) {
+                                uris.push(job.uri);
+                            }
+                        }
+                        Err(mpsc::RecvTimeoutError::Timeout) => break,
+                        Err(mpsc::RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+                for uri in uris {
+                    self.analyze(&uri);
+                }
             }
         }
+        /// Analyze a document's pending change
+        ///
+        /// Analysis is canceled if a newer change arrives
+        fn analyze(&self, uri: &Url) {
+            let Some(pending) = self.pending.get(uri).map(|pending| pending.clone()) else {
+                return;
+            };
+            let prev = self.analyzed.get(uri).map(|doc| doc.clone());
+            let cancel =
+                || self.pending.get(uri).map(|pending| pending.version) != Some(pending.version);
+            let doc = catch_unwind(AssertUnwindSafe(|| {
+                LspDoc::analyze(
+                    &uri_path(uri),
+                    pending.text,
+                    prev.as_deref(),
+                    &self.import_cache,
+                    &cancel,
+                )
+            }));
+            let doc = match doc {
+                Ok(Some(doc)) => Some(doc),
+                Ok(None) => return,
+                // Keep the previous analysis rather than waiting forever
+                Err(_) => None,
+            };
+            if let Some(doc) = doc {
+                self.analyzed.insert(uri.clone(), doc.into());
+            }
+            (self.pending).remove_if(uri, |_, p| p.version == pending.version);
+            self.resolved.notify_waiters();
+        }
     }
 
     #[doc(hidden)]
@@ -865,7 +1079,8 @@ mod server {
 
                 let (service, socket) = LspService::new(|client| Backend {
                     client,
-                    docs: DashMap::new(),
+                    docs: Docs::new(),
+                    lens_results: DashMap::new(),
                 });
                 Server::new(stdin, stdout, socket)
                     .concurrency_level(1)
@@ -876,7 +1091,8 @@ mod server {
 
     struct Backend {
         client: Client,
-        docs: DashMap<Url, Arc<LspDoc>>,
+        docs: Docs,
+        lens_results: DashMap<Url, Vec<LensResult>>,
     }
 
     const UIUA_NUMBER_STT: SemanticTokenType = SemanticTokenType::new("uiua_number");
@@ -985,8 +1201,28 @@ mod server {
                         },
                     )),
                     code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                    code_lens_provider: Some(CodeLensOptions {
+                        resolve_provider: Some(false),
+                    }),
+                    execute_command_provider: Some(ExecuteCommandOptions {
+                        commands: [RUN_TEST_COMMAND, RUN_LINE_COMMAND, DEBUG_LINE_COMMAND]
+                            .map(Into::into)
+                            .into(),
+                        ..Default::default()
+                    }),
                     inlay_hint_provider: Some(OneOf::Left(true)),
                     inline_value_provider: Some(OneOf::Left(true)),
+                    document_symbol_provider: Some(OneOf::Left(true)),
+                    workspace_symbol_provider: Some(OneOf::Left(true)),
+                    folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                    selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                    document_highlight_provider: Some(OneOf::Left(true)),
+                    call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                    signature_help_provider: Some(SignatureHelpOptions {
+                        trigger_characters: Some(vec![" ".into(), "(".into(), "|".into()]),
+                        retrigger_characters: None,
+                        work_done_progress_options: WorkDoneProgressOptions::default(),
+                    }),
                     ..Default::default()
                 },
                 ..Default::default()
@@ -998,17 +1234,16 @@ mod server {
         }
 
         async fn did_open(&self, params: DidOpenTextDocumentParams) {
-            let path = uri_path(&params.text_document.uri);
-            self.docs.insert(
-                params.text_document.uri,
-                LspDoc::new(&path, params.text_document.text).into(),
-            );
+            let doc = params.text_document;
+            self.lens_results.remove(&doc.uri);
+            self.docs.update(doc.uri, doc.version, doc.text, false);
         }
 
-        async fn did_change(&self, params: DidChangeTextDocumentParams) {
-            let path = uri_path(&params.text_document.uri);
-            let doc = LspDoc::new(&path, params.content_changes[0].text.clone());
-            self.docs.insert(params.text_document.uri, doc.into());
+        async fn did_change(&self, mut params: DidChangeTextDocumentParams) {
+            let doc = params.text_document;
+            let text = params.content_changes.swap_remove(0).text;
+            self.lens_results.remove(&doc.uri);
+            self.docs.update(doc.uri, doc.version, text, true);
         }
 
         async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
@@ -1018,8 +1253,9 @@ mod server {
                 "[`⍜ under`](https://uiua.org/docs/under)",
             ];
 
-            let Some(doc) =
-                (self.docs).get(&params.text_document_position_params.text_document.uri)
+            let Some(doc) = self
+                .doc(&params.text_document_position_params.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -1107,6 +1343,17 @@ mod server {
                         _ => {}
                     }
                     value.push_str("\n```");
+                    if let Some(types) = (doc.code_meta.inferred_types.get(&docs.src_span))
+                        .filter(|types| types.iter().any(ValueType::is_known))
+                    {
+                        if matches!(
+                            docs.kind,
+                            BindingDocsKind::Function { .. } | BindingDocsKind::Constant(None)
+                        ) {
+                            let types: Vec<_> = types.iter().map(|ty| format!("`{ty}`")).collect();
+                            value.push_str(&format!("\n\ninferred: {}", types.join(" ")));
+                        }
+                    }
                     if let Some(escape) = &docs.escape {
                         value.push_str(&format!("\n`{escape}`"));
                     }
@@ -1252,7 +1499,7 @@ mod server {
             }
 
             let doc_uri = &params.text_document_position.text_document.uri;
-            let doc = if let Some(doc) = self.doc(doc_uri) {
+            let doc = if let Some(doc) = self.doc(doc_uri).await {
                 doc
             } else {
                 return Ok(None);
@@ -1270,7 +1517,7 @@ mod server {
                         let mut completions = Vec::new();
                         let mut span = span.clone();
                         span.start = span.end;
-                        for binding in self.bindings_in_file(doc_uri, module) {
+                        for binding in self.bindings_in_file(doc_uri, module).await {
                             if !binding.public {
                                 continue;
                             }
@@ -1367,13 +1614,13 @@ mod server {
                 .collect();
 
             // Collect binding completions
-            for binding in self.bindings_in_file(doc_uri, &uri_path(doc_uri)) {
+            for binding in self.bindings_in_file(doc_uri, &uri_path(doc_uri)).await {
                 let Some(name) = binding.span.try_as_str(&doc.asm.inputs, |s| s.to_string()) else {
                     continue;
                 };
 
                 if let BindingKind::Import(module) = &binding.kind {
-                    for binding in self.bindings_in_file(doc_uri, module) {
+                    for binding in self.bindings_in_file(doc_uri, module).await {
                         if !binding.public {
                             continue;
                         }
@@ -1424,7 +1671,7 @@ mod server {
             &self,
             params: DocumentFormattingParams,
         ) -> Result<Option<Vec<TextEdit>>> {
-            let doc = if let Some(doc) = self.doc(&params.text_document.uri) {
+            let doc = if let Some(doc) = self.doc(&params.text_document.uri).await {
                 doc
             } else {
                 return Ok(None);
@@ -1470,8 +1717,8 @@ mod server {
 
             // Get document
             let Some(doc) = self
-                .docs
-                .get(&params.text_document_position.text_document.uri)
+                .doc(&params.text_document_position.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -1558,7 +1805,7 @@ mod server {
             &self,
             params: SemanticTokensParams,
         ) -> Result<Option<SemanticTokensResult>> {
-            let Some(doc) = self.doc(&params.text_document.uri) else {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
                 return Ok(None);
             };
 
@@ -1677,7 +1924,7 @@ mod server {
             &self,
             params: CodeActionParams,
         ) -> Result<Option<CodeActionResponse>> {
-            let Some(doc) = self.doc(&params.text_document.uri) else {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
                 return Ok(None);
             };
             let (line, col) = lsp_pos_to_uiua(params.range.start, &doc.input);
@@ -1845,6 +2092,55 @@ mod server {
                 }));
             }
 
+            // Extract selection into a new binding
+            let sel_start = (line, col);
+            let sel_end = lsp_pos_to_uiua(params.range.end, &doc.input);
+            if let Some((words, item_span)) = (sel_start != sel_end)
+                .then(|| items_selected_words(&doc.items, sel_start, sel_end))
+                .flatten()
+                .filter(|(_, item_span)| item_span.src == path)
+            {
+                actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                    title: "Extract selection into a new binding".into(),
+                    kind: Some(CodeActionKind::REFACTOR_EXTRACT),
+                    edit: Some(WorkspaceEdit {
+                        changes: Some(
+                            [(
+                                params.text_document.uri.clone(),
+                                extract_binding_edits(&doc, words, &item_span),
+                            )]
+                            .into(),
+                        ),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }));
+            }
+
+            // Inline binding
+            if let Some(index) = binding_at(&doc, &path, line, col) {
+                let binding = &doc.asm.bindings[index];
+                // Keep the definition if another open file uses it
+                let used_elsewhere = binding.public
+                    && (self.open_docs().await.iter()).any(|(uri, other)| {
+                        *uri != params.text_document.uri
+                            && (other.code_meta.global_references.values())
+                                .any(|&i| other.asm.bindings[i].span == binding.span)
+                    });
+                if let Some(edits) = inline_binding_edits(&doc, index, &path, !used_elsewhere) {
+                    let name = binding.span.as_str(&doc.asm.inputs, str::to_string);
+                    actions.push(CodeActionOrCommand::CodeAction(CodeAction {
+                        title: format!("Inline binding {name}"),
+                        kind: Some(CodeActionKind::REFACTOR_INLINE),
+                        edit: Some(WorkspaceEdit {
+                            changes: Some([(params.text_document.uri.clone(), edits)].into()),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }));
+                }
+            }
+
             // Add experimental
             if !doc.input.contains("# Experimental!") {
                 for error in &doc.errors {
@@ -1886,7 +2182,9 @@ mod server {
         }
 
         async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
-            let Some(doc) = (self.docs).get(&params.text_document_position.text_document.uri)
+            let Some(doc) = self
+                .doc(&params.text_document_position.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -1926,9 +2224,7 @@ mod server {
                     new_text: params.new_name.clone(),
                 }],
             );
-            for entry in &self.docs {
-                let uri = entry.key();
-                let doc = entry.value();
+            for (uri, doc) in self.open_docs().await {
                 for (name_span, idx) in &doc.code_meta.global_references {
                     if *idx == index {
                         let uri = match &name_span.src {
@@ -1953,8 +2249,9 @@ mod server {
             &self,
             params: GotoDefinitionParams,
         ) -> Result<Option<GotoDefinitionResponse>> {
-            let Some(doc) =
-                (self.docs).get(&params.text_document_position_params.text_document.uri)
+            let Some(doc) = self
+                .doc(&params.text_document_position_params.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -1998,8 +2295,9 @@ mod server {
             &self,
             params: GotoDeclarationParams,
         ) -> Result<Option<GotoDeclarationResponse>> {
-            let Some(doc) =
-                (self.docs).get(&params.text_document_position_params.text_document.uri)
+            let Some(doc) = self
+                .doc(&params.text_document_position_params.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -2029,7 +2327,7 @@ mod server {
             params: DocumentDiagnosticParams,
         ) -> Result<DocumentDiagnosticReportResult> {
             let mut diagnostics = Vec::new();
-            let Some(doc) = self.doc(&params.text_document.uri) else {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
                 return Ok(DocumentDiagnosticReportResult::Report(
                     DocumentDiagnosticReport::Full(RelatedFullDocumentDiagnosticReport {
                         related_documents: None,
@@ -2133,7 +2431,7 @@ mod server {
         }
 
         async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
-            let Some(doc) = self.doc(&params.text_document.uri) else {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
                 return Ok(None);
             };
             let config = self
@@ -2242,48 +2540,22 @@ mod server {
                     if span.src != path {
                         continue;
                     }
-                    let mut shown: Vec<String> = values.iter().map(Value::show).collect();
-                    let mut md = "```uiua\n".to_string();
-                    for shown in &shown {
-                        md.push_str(shown);
-                        md.push('\n');
-                    }
-                    md.push_str("\n```");
-                    let tooltip = InlayHintTooltip::MarkupContent(MarkupContent {
-                        kind: MarkupKind::Markdown,
-                        value: md,
-                    });
-                    let label = if shown.iter().any(|s| s.lines().count() > 1) {
-                        let mut shapes = String::new();
-                        for (i, val) in values.iter().rev().enumerate() {
-                            if i > 0 {
-                                shapes.push_str(" | ");
-                            }
-                            shapes.push_str(&val.shape_string());
-                        }
-                        InlayHintLabel::String(shapes)
-                    } else {
-                        shown.reverse();
-                        InlayHintLabel::String(shown.join(" "))
-                    };
-                    hints.push(InlayHint {
-                        text_edits: None,
-                        position: uiua_span_to_lsp(span, &doc.asm.inputs).end,
-                        label,
-                        kind: None,
-                        tooltip: Some(tooltip),
-                        padding_left: Some(true),
-                        padding_right: None,
-                        data: None,
-                    });
+                    let position = uiua_span_to_lsp(span, &doc.asm.inputs).end;
+                    hints.push(values_hint(position, values));
                 }
             }
+            // Results of code run from lenses
+            if let Some(results) = self.lens_results.get(&params.text_document.uri) {
+                hints.extend(results.iter().map(LensResult::hint));
+            }
 
             Ok(Some(hints))
         }
 
         async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-            let Some(doc) = (self.docs).get(&params.text_document_position.text_document.uri)
+            let Some(doc) = self
+                .doc(&params.text_document_position.text_document.uri)
+                .await
             else {
                 return Ok(None);
             };
@@ -2292,9 +2564,7 @@ mod server {
             for (i, binfo) in doc.asm.bindings.iter().enumerate() {
                 if binfo.span.contains_line_col(line, col) && binfo.span.src == path {
                     let mut locations = Vec::new();
-                    for entry in &self.docs {
-                        let uri = entry.key();
-                        let doc = entry.value();
+                    for (uri, doc) in self.open_docs().await {
                         for (name_span, idx) in &doc.code_meta.global_references {
                             if *idx == i {
                                 let uri = match &name_span.src {
@@ -2312,11 +2582,252 @@ mod server {
             Ok(None)
         }
 
+        async fn document_symbol(
+            &self,
+            params: DocumentSymbolParams,
+        ) -> Result<Option<DocumentSymbolResponse>> {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
+                return Ok(None);
+            };
+            let symbols = items_symbols(&doc.items, &doc);
+            Ok(Some(DocumentSymbolResponse::Nested(symbols)))
+        }
+
+        #[allow(deprecated)]
+        async fn symbol(
+            &self,
+            params: WorkspaceSymbolParams,
+        ) -> Result<Option<Vec<SymbolInformation>>> {
+            let query = params.query.to_lowercase();
+            Ok(Some(workspace_symbols(&self.open_docs().await, &query)))
+        }
+
+        async fn signature_help(
+            &self,
+            params: SignatureHelpParams,
+        ) -> Result<Option<SignatureHelp>> {
+            let Some(doc) = self
+                .doc(&params.text_document_position_params.text_document.uri)
+                .await
+            else {
+                return Ok(None);
+            };
+            let (line, col) =
+                lsp_pos_to_uiua(params.text_document_position_params.position, &doc.input);
+            let Some((callee, is_modifier, active)) =
+                items_signature_target(&doc.items, line, col, &doc)
+            else {
+                return Ok(None);
+            };
+            let mut info = callee_signature(&callee, is_modifier, &doc);
+            let params = info.parameters.as_ref().map_or(0, Vec::len);
+            if params == 0 {
+                return Ok(None);
+            }
+            let active = active.min(params - 1) as u32;
+            info.active_parameter = Some(active);
+            Ok(Some(SignatureHelp {
+                signatures: vec![info],
+                active_signature: Some(0),
+                active_parameter: Some(active),
+            }))
+        }
+
+        async fn folding_range(
+            &self,
+            params: FoldingRangeParams,
+        ) -> Result<Option<Vec<FoldingRange>>> {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
+                return Ok(None);
+            };
+            let mut ranges = Vec::new();
+            items_folding_ranges(&doc.items, &mut ranges);
+            // Consecutive comment lines
+            let mut comments = (doc.spans.iter())
+                .filter(|sp| sp.value == SpanKind::Comment)
+                .map(|sp| &sp.span)
+                .peekable();
+            while let Some(start) = comments.next() {
+                let mut end = start;
+                while let Some(next) = comments.next_if(|next| next.start.line == end.end.line + 1)
+                {
+                    end = next;
+                }
+                push_folding_range(start, end, Some(FoldingRangeKind::Comment), &mut ranges);
+            }
+            ranges.sort_by_key(|range| (range.start_line, range.end_line));
+            Ok(Some(ranges))
+        }
+
+        async fn selection_range(
+            &self,
+            params: SelectionRangeParams,
+        ) -> Result<Option<Vec<SelectionRange>>> {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
+                return Ok(None);
+            };
+            let mut selections = Vec::with_capacity(params.positions.len());
+            for position in params.positions {
+                let (line, col) = lsp_pos_to_uiua(position, &doc.input);
+                let mut spans = Vec::new();
+                items_selection_spans(&doc.items, line, col, &mut spans);
+                let mut selection: Option<SelectionRange> = None;
+                for span in spans {
+                    let range = uiua_span_to_lsp(&span, &doc.asm.inputs);
+                    if selection.as_ref().is_some_and(|sel| sel.range == range) {
+                        continue;
+                    }
+                    selection = Some(SelectionRange {
+                        range,
+                        parent: selection.map(Box::new),
+                    });
+                }
+                selections.push(selection.unwrap_or(SelectionRange {
+                    range: Range::new(position, position),
+                    parent: None,
+                }));
+            }
+            Ok(Some(selections))
+        }
+
+        async fn document_highlight(
+            &self,
+            params: DocumentHighlightParams,
+        ) -> Result<Option<Vec<DocumentHighlight>>> {
+            let uri = &params.text_document_position_params.text_document.uri;
+            let Some(doc) = self.doc(uri).await else {
+                return Ok(None);
+            };
+            let path = uri_path(uri);
+            let (line, col) =
+                lsp_pos_to_uiua(params.text_document_position_params.position, &doc.input);
+            let Some(index) = binding_at(&doc, &path, line, col) else {
+                return Ok(None);
+            };
+            let mut highlights = Vec::new();
+            let binding = &doc.asm.bindings[index];
+            if binding.span.src == path {
+                highlights.push(DocumentHighlight {
+                    range: uiua_span_to_lsp(&binding.span, &doc.asm.inputs),
+                    kind: Some(DocumentHighlightKind::WRITE),
+                });
+            }
+            for (span, &i) in &doc.code_meta.global_references {
+                if i == index && span.src == path {
+                    highlights.push(DocumentHighlight {
+                        range: uiua_span_to_lsp(span, &doc.asm.inputs),
+                        kind: Some(DocumentHighlightKind::READ),
+                    });
+                }
+            }
+            highlights.sort_by_key(|hl| hl.range.start);
+            Ok(Some(highlights))
+        }
+
+        async fn prepare_call_hierarchy(
+            &self,
+            params: CallHierarchyPrepareParams,
+        ) -> Result<Option<Vec<CallHierarchyItem>>> {
+            let uri = &params.text_document_position_params.text_document.uri;
+            let Some(doc) = self.doc(uri).await else {
+                return Ok(None);
+            };
+            let path = uri_path(uri);
+            let (line, col) =
+                lsp_pos_to_uiua(params.text_document_position_params.position, &doc.input);
+            let Some(index) = binding_at(&doc, &path, line, col) else {
+                return Ok(None);
+            };
+            Ok(binding_call_item(&doc, uri, index).map(|item| vec![item]))
+        }
+
+        async fn incoming_calls(
+            &self,
+            params: CallHierarchyIncomingCallsParams,
+        ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+            let item = &params.item;
+            let mut groups = Vec::new();
+            // Every open document that can see the binding may call it
+            for (doc_uri, doc) in self.open_docs().await {
+                let Some(index) = (0..doc.asm.bindings.len()).find(|&i| {
+                    binding_call_item(&doc, &doc_uri, i).is_some_and(|other| {
+                        other.uri == item.uri && other.selection_range == item.selection_range
+                    })
+                }) else {
+                    continue;
+                };
+                for edge in call_edges(&doc) {
+                    if edge.callee != index {
+                        continue;
+                    }
+                    let Some(call_uri) = span_uri(&edge.span, &doc_uri) else {
+                        continue;
+                    };
+                    let from = match edge.caller {
+                        Some(caller) => binding_call_item(&doc, &doc_uri, caller),
+                        None => Some(file_call_item(call_uri)),
+                    };
+                    if let Some(from) = from {
+                        let range = uiua_span_to_lsp(&edge.span, &doc.asm.inputs);
+                        group_call(&mut groups, from, range);
+                    }
+                }
+            }
+            Ok(Some(
+                (groups.into_iter())
+                    .map(|(from, from_ranges)| CallHierarchyIncomingCall { from, from_ranges })
+                    .collect(),
+            ))
+        }
+
+        async fn outgoing_calls(
+            &self,
+            params: CallHierarchyOutgoingCallsParams,
+        ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+            let item = &params.item;
+            for (doc_uri, doc) in self.open_docs().await {
+                let caller = if item.kind == SymbolKind::FILE {
+                    None
+                } else {
+                    let index = (0..doc.asm.bindings.len()).find(|&i| {
+                        binding_call_item(&doc, &doc_uri, i).is_some_and(|other| {
+                            other.uri == item.uri && other.selection_range == item.selection_range
+                        })
+                    });
+                    if index.is_none() {
+                        continue;
+                    }
+                    index
+                };
+                let mut groups = Vec::new();
+                for edge in call_edges(&doc) {
+                    if edge.caller != caller
+                        || span_uri(&edge.span, &doc_uri).as_ref() != Some(&item.uri)
+                    {
+                        continue;
+                    }
+                    if let Some(to) = binding_call_item(&doc, &doc_uri, edge.callee) {
+                        let range = uiua_span_to_lsp(&edge.span, &doc.asm.inputs);
+                        group_call(&mut groups, to, range);
+                    }
+                }
+                if caller.is_none() && groups.is_empty() {
+                    continue;
+                }
+                return Ok(Some(
+                    (groups.into_iter())
+                        .map(|(to, from_ranges)| CallHierarchyOutgoingCall { to, from_ranges })
+                        .collect(),
+                ));
+            }
+            Ok(None)
+        }
+
         async fn inline_value(
             &self,
             params: InlineValueParams,
         ) -> Result<Option<Vec<InlineValue>>> {
-            let Some(doc) = self.doc(&params.text_document.uri) else {
+            let Some(doc) = self.doc(&params.text_document.uri).await else {
                 return Ok(None);
             };
             let mut inline_values = Vec::new();
@@ -2329,37 +2840,96 @@ mod server {
                     text: value.show(),
                 }));
             }
+            if let Some(results) = self.lens_results.get(&params.text_document.uri) {
+                for result in results.iter() {
+                    inline_values.push(InlineValue::Text(InlineValueText {
+                        range: Range::new(result.position, result.position),
+                        text: result.label(),
+                    }));
+                }
+            }
             Ok(Some(inline_values))
         }
 
+        async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+            let uri = params.text_document.uri;
+            let Some(doc) = self.doc(&uri).await else {
+                return Ok(None);
+            };
+            Ok(Some(code_lenses(&doc, &uri)))
+        }
+
+        async fn execute_command(
+            &self,
+            params: ExecuteCommandParams,
+        ) -> Result<Option<serde_json::Value>> {
+            let (Some(uri), Some(line)) = (
+                (params.arguments.first())
+                    .and_then(|uri| uri.as_str())
+                    .and_then(|uri| Url::parse(uri).ok()),
+                params.arguments.get(1).and_then(|line| line.as_u64()),
+            ) else {
+                return Err(Error::invalid_params("Expected a document URI and a line"));
+            };
+            let Some(doc) = self.doc(&uri).await else {
+                return Ok(None);
+            };
+            let line = line as usize + 1;
+            let command = params.command;
+            if ![RUN_TEST_COMMAND, RUN_LINE_COMMAND, DEBUG_LINE_COMMAND].contains(&command.as_str())
+            {
+                return Err(Error::invalid_params(format!(
+                    "Unknown command `{command}`"
+                )));
+            }
+            // Running the code may take a while, so don't block the server
+            let results = tokio::task::spawn_blocking(move || match command.as_str() {
+                RUN_TEST_COMMAND => run_test_lens(&doc, line),
+                DEBUG_LINE_COMMAND => run_line_lens(&doc, line, true),
+                _ => run_line_lens(&doc, line, false),
+            })
+            .await
+            .map_err(|_| Error::internal_error())?;
+            let mut entry = self.lens_results.entry(uri).or_default();
+            entry.retain(|result| result.position.line as usize + 1 != line);
+            entry.extend(results);
+            drop(entry);
+            // The client may not answer these, so don't wait for them
+            let client = self.client.clone();
+            tokio::spawn(async move {
+                _ = client.inlay_hint_refresh().await;
+                _ = client.inline_value_refresh().await;
+            });
+            Ok(None)
+        }
+
         async fn shutdown(&self) -> Result<()> {
             Ok(())
         }
     }
 
     impl Backend {
-        fn doc(&self, uri: &Url) -> Option<Arc<LspDoc>> {
-            self.docs.get(uri).map(|doc| Arc::clone(&doc))
+        async fn doc(&self, uri: &Url) -> Option<Arc<LspDoc>> {
+            self.docs.get(uri).await
         }
-        fn bindings_in_file(
-            &self,
-            doc_uri: &Url,
-            path: &Path,
-        ) -> impl Iterator<Item = BindingInfo> + '_ {
-            let doc_uri = doc_uri.clone();
+        async fn open_docs(&self) -> Vec<(Url, Arc<LspDoc>)> {
+            self.docs.all().await
+        }
+        async fn bindings_in_file(&self, doc_uri: &Url, path: &Path) -> Vec<BindingInfo> {
             let canonical_path = path.canonicalize().ok();
-            self.docs.get(&doc_uri).into_iter().flat_map(move |doc| {
-                (doc.asm.bindings.iter())
-                    .filter(|binfo| {
-                        let path = match &binfo.span.src {
-                            InputSrc::File(file) => file.to_path_buf(),
-                            InputSrc::Str(_) | InputSrc::Macro(_) => uri_path(&doc_uri),
-                        };
-                        path.canonicalize().ok() == canonical_path
-                    })
-                    .cloned()
-                    .collect::<Vec<_>>()
-            })
+            let Some(doc) = self.doc(doc_uri).await else {
+                return Vec::new();
+            };
+            (doc.asm.bindings.iter())
+                .filter(|binfo| {
+                    let path = match &binfo.span.src {
+                        InputSrc::File(file) => file.to_path_buf(),
+                        InputSrc::Str(_) | InputSrc::Macro(_) => uri_path(doc_uri),
+                    };
+                    path.canonicalize().ok() == canonical_path
+                })
+                .cloned()
+                .collect()
         }
         async fn debug(&self, message: impl Into<String>) {
             self.client
@@ -2368,23 +2938,314 @@ mod server {
         }
     }
 
-    fn path_to_uri(path: &Path) -> Result<Url> {
-        Url::from_file_path(
-            path.canonicalize()
-                .map_err(|e| Error::invalid_params(format!("Invalid file path: {}", e)))?,
-        )
-        .map_err(|_| Error::invalid_params("Invalid file path"))
+    fn values_hint(position: Position, values: &[Value]) -> InlayHint {
+        let mut shown: Vec<String> = values.iter().map(Value::show).collect();
+        let mut md = "```uiua\n".to_string();
+        for shown in &shown {
+            md.push_str(shown);
+            md.push('\n');
+        }
+        md.push_str("\n```");
+        let tooltip = InlayHintTooltip::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: md,
+        });
+        let label = if shown.iter().any(|s| s.lines().count() > 1) {
+            let mut shapes = String::new();
+            for (i, val) in values.iter().rev().enumerate() {
+                if i > 0 {
+                    shapes.push_str(" | ");
+                }
+                shapes.push_str(&val.shape_string());
+            }
+            shapes
+        } else {
+            shown.reverse();
+            shown.join(" ")
+        };
+        InlayHint {
+            text_edits: None,
+            position,
+            label: InlayHintLabel::String(label),
+            kind: None,
+            tooltip: Some(tooltip),
+            padding_left: Some(true),
+            padding_right: None,
+            data: None,
+        }
     }
 
-    fn uri_path(uri: &Url) -> PathBuf {
-        let path = uri.path().replace("/c%3A", "C:");
-        let path = PathBuf::from(path);
-        path.canonicalize().unwrap_or(path)
+    const RUN_TEST_COMMAND: &str = "uiua.runTest";
+    const RUN_LINE_COMMAND: &str = "uiua.runLine";
+    const DEBUG_LINE_COMMAND: &str = "uiua.debugLine";
+    /// How long code run from a code lens may take
+    const LENS_EXECUTION_LIMIT: Duration = Duration::from_secs(2);
+
+    /// The backend for code run from a code lens
+    ///
+    /// Like [`SafeSys`], but files in the document's directory may be read so that imports work
+    struct LensSys {
+        safe: SafeSys,
+        root: PathBuf,
     }
 
-    fn lsp_pos_to_uiua(pos: Position, input: &str) -> (usize, usize) {
-        let line_no = pos.line as usize;
-        let line = input.split('\n').nth(line_no).unwrap();
+    impl LensSys {
+        fn new(src: &InputSrc) -> Self {
+            let dir = match src {
+                InputSrc::File(path) => path.parent().unwrap_or(Path::new(".")),
+                _ => Path::new("."),
+            };
+            LensSys {
+                safe: SafeSys::new(),
+                root: dir.canonicalize().unwrap_or_else(|_| dir.into()),
+            }
+        }
+    }
+
+    impl SysBackend for LensSys {
+        fn any(&self) -> &dyn Any {
+            self
+        }
+        fn any_mut(&mut self) -> &mut dyn Any {
+            self
+        }
+        fn print_str_stdout(&self, s: &str) -> std::result::Result<(), String> {
+            self.safe.print_str_stdout(s)
+        }
+        fn print_str_stderr(&self, s: &str) -> std::result::Result<(), String> {
+            self.safe.print_str_stderr(s)
+        }
+        fn print_str_trace(&self, s: &str) {
+            _ = self.safe.print_str_stderr(s);
+        }
+        fn file_read_all(&self, path: &Path) -> std::result::Result<Vec<u8>, String> {
+            let full = path.canonicalize().map_err(|e| e.to_string())?;
+            if !full.starts_with(&self.root) {
+                return Err(format!(
+                    "{} is outside of the document's directory",
+                    path.display()
+                ));
+            }
+            std::fs::read(full).map_err(|e| e.to_string())
+        }
+    }
+
+    /// The result of running code from a code lens
+    struct LensResult {
+        position: Position,
+        values: Vec<Value>,
+        /// An error or test summary
+        message: Option<String>,
+        /// Anything printed while running
+        output: String,
+    }
+
+    impl LensResult {
+        fn label(&self) -> String {
+            if let Some(message) = &self.message {
+                message.clone()
+            } else if self.values.is_empty() {
+                "(empty stack)".into()
+            } else {
+                (self.values.iter().rev())
+                    .map(Value::show)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            }
+        }
+        fn hint(&self) -> InlayHint {
+            let mut hint = values_hint(self.position, &self.values);
+            if self.message.is_some() || self.values.is_empty() {
+                hint.label = InlayHintLabel::String(self.label());
+            }
+            if self.values.is_empty() {
+                hint.tooltip = None;
+            }
+            if !self.output.is_empty() {
+                let values = match hint.tooltip {
+                    Some(InlayHintTooltip::MarkupContent(tooltip)) => tooltip.value,
+                    _ => String::new(),
+                };
+                hint.tooltip = Some(InlayHintTooltip::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: format!("```\n{}\n```\n{values}", self.output),
+                }));
+            }
+            hint
+        }
+    }
+
+    /// Get the code lenses for a document
+    fn code_lenses(doc: &LspDoc, uri: &Url) -> Vec<CodeLens> {
+        let lens = |span: &CodeSpan, title: &str, command: &str| {
+            let range = uiua_span_to_lsp(span, &doc.asm.inputs);
+            CodeLens {
+                range,
+                command: Some(Command {
+                    title: title.into(),
+                    command: command.into(),
+                    arguments: Some(vec![uri.as_str().into(), range.start.line.into()]),
+                }),
+                data: None,
+            }
+        };
+        let mut lenses = Vec::new();
+        for item in &doc.items {
+            match item {
+                Item::Module(m) if matches!(m.value.kind, ModuleKind::Test) => {
+                    lenses.push(lens(&m.value.open_span, "Run test", RUN_TEST_COMMAND));
+                }
+                Item::Words(lines) => {
+                    for line in lines {
+                        let Some(span) = line_code_span(line) else {
+                            continue;
+                        };
+                        lenses.push(lens(&span, "Run line", RUN_LINE_COMMAND));
+                        lenses.push(lens(&span, "Debug", DEBUG_LINE_COMMAND));
+                    }
+                }
+                _ => {}
+            }
+        }
+        lenses
+    }
+
+    /// Get the span of the code in a top-level line
+    fn line_code_span(line: &[Sp<Word>]) -> Option<CodeSpan> {
+        let mut code = line.iter().filter(|word| word.value.is_code());
+        let first = code.next()?;
+        let last = code.next_back().unwrap_or(first);
+        Some(first.span.clone().merge(last.span.clone()))
+    }
+
+    /// Run a top-level line and the code before it
+    ///
+    /// When debugging, the stack is shown after each of the line's words
+    fn run_line_lens(doc: &LspDoc, line: usize, debug: bool) -> Vec<LensResult> {
+        let Some(words) = (doc.items.iter())
+            .filter_map(|item| match item {
+                Item::Words(lines) => Some(lines),
+                _ => None,
+            })
+            .flatten()
+            .find(|words| {
+                line_code_span(words).is_some_and(|span| span.start.line as usize == line)
+            })
+        else {
+            return Vec::new();
+        };
+        let code: Vec<&Sp<Word>> = words.iter().filter(|word| word.value.is_code()).collect();
+        let line_start = code[0].span.start.byte_pos as usize;
+        let line_end = code[code.len() - 1].span.end.byte_pos as usize;
+        let steps = if debug { code.as_slice() } else { &code[..1] };
+        let mut results = Vec::new();
+        for word in steps.iter().rev() {
+            let word_start = word.span.start.byte_pos as usize;
+            let input = format!(
+                "{}{}",
+                &doc.input[..line_start],
+                &doc.input[word_start..line_end]
+            );
+            let position = if debug {
+                uiua_loc_to_lsp(word.span.start, &doc.input)
+            } else {
+                uiua_loc_to_lsp(code[code.len() - 1].span.end, &doc.input)
+            };
+            let (res, values, output) = run_lens_code(doc, &input, RunMode::Normal, None);
+            results.push(LensResult {
+                position,
+                values,
+                message: res.err().map(|e| lens_error_message(&e)),
+                output,
+            });
+        }
+        results
+    }
+
+    /// Run a single test scope and the code before it
+    fn run_test_lens(doc: &LspDoc, line: usize) -> Vec<LensResult> {
+        let Some(m) = doc.items.iter().find_map(|item| match item {
+            Item::Module(m)
+                if matches!(m.value.kind, ModuleKind::Test)
+                    && m.value.open_span.start.line as usize == line =>
+            {
+                Some(m)
+            }
+            _ => None,
+        }) else {
+            return Vec::new();
+        };
+        let name = test_scope_name(&m.value.items)
+            .unwrap_or_else(|| format!("test at line {}", m.value.open_span.start.line).into());
+        let input = &doc.input[..m.span.end.byte_pos as usize];
+        let (res, values, output) = run_lens_code(doc, input, RunMode::Test, Some(name.into()));
+        let message = match res {
+            Ok(reports) => {
+                let summary: Vec<String> = reports.iter().map(ToString::to_string).collect();
+                if summary.is_empty() {
+                    "✓ Test passed".into()
+                } else {
+                    summary.join(", ")
+                }
+            }
+            Err(e) => format!("✗ {}", lens_error_message(&e)),
+        };
+        vec![LensResult {
+            position: uiua_loc_to_lsp(m.value.open_span.end, &doc.input),
+            values,
+            message: Some(message),
+            output,
+        }]
+    }
+
+    /// Compile and run code in a sandbox
+    ///
+    /// Returns the run's reports, the resulting stack, and anything printed
+    fn run_lens_code(
+        doc: &LspDoc,
+        input: &str,
+        mode: RunMode,
+        test_filter: Option<String>,
+    ) -> (UiuaResult<Vec<crate::Report>>, Vec<Value>, String) {
+        let sys = Arc::new(LensSys::new(&doc.src));
+        let backend = || sys.clone() as Arc<dyn SysBackend>;
+        let mut comp = Compiler::with_backend(backend());
+        comp.mode(mode).test_filter(test_filter);
+        let mut env = Uiua::with_backend(backend()).with_execution_limit(LENS_EXECUTION_LIMIT);
+        let res = (comp.load_str_src(input, doc.src.clone()))
+            .and_then(|comp| env.run_compiler(comp))
+            .map(|_| env.take_reports());
+        let values = env.take_stack();
+        let mut output = String::from_utf8_lossy(&sys.safe.take_stdout()).into_owned();
+        output.push_str(&String::from_utf8_lossy(&sys.safe.take_stderr()));
+        (res, values, output.trim_end().into())
+    }
+
+    fn lens_error_message(error: &UiuaError) -> String {
+        match &error.kind {
+            UiuaErrorKind::Run { message, .. } => message.value.clone(),
+            UiuaErrorKind::Throw(value, ..) => value.format(),
+            _ => error.to_string().lines().next().unwrap_or_default().into(),
+        }
+    }
+
+    fn path_to_uri(path: &Path) -> Result<Url> {
+        Url::from_file_path(
+            path.canonicalize()
+                .map_err(|e| Error::invalid_params(format!("Invalid file path: {}", e)))?,
+        )
+        .map_err(|_| Error::invalid_params("Invalid file path"))
+    }
+
+    fn uri_path(uri: &Url) -> PathBuf {
+        let path = uri.path().replace("/c%3A", "C:");
+        let path = PathBuf::from(path);
+        path.canonicalize().unwrap_or(path)
+    }
+
+    fn lsp_pos_to_uiua(pos: Position, input: &str) -> (usize, usize) {
+        let line_no = pos.line as usize;
+        let line = input.split('\n').nth(line_no).unwrap_or("");
         let mut lsp_col = pos.character as usize;
         let mut uiua_col = 1;
         for c in line.chars() {
@@ -2414,6 +3275,1017 @@ mod server {
         uiua_locs_to_lsp(span.start, span.end, &inputs.get(&span.src))
     }
 
+    /// Get the symbols in open documents and the files they import that match a query
+    #[allow(deprecated)]
+    fn workspace_symbols(docs: &[(Url, Arc<LspDoc>)], query: &str) -> Vec<SymbolInformation> {
+        let mut infos = Vec::new();
+        let mut seen = HashSet::new();
+        fn flatten(
+            symbols: Vec<DocumentSymbol>,
+            uri: &Url,
+            container: Option<&str>,
+            infos: &mut Vec<SymbolInformation>,
+        ) {
+            for sym in symbols {
+                infos.push(SymbolInformation {
+                    name: sym.name.clone(),
+                    kind: sym.kind,
+                    tags: None,
+                    deprecated: None,
+                    location: Location {
+                        uri: uri.clone(),
+                        range: sym.selection_range,
+                    },
+                    container_name: container.map(Into::into),
+                });
+                if let Some(children) = sym.children {
+                    flatten(children, uri, Some(&sym.name), infos);
+                }
+            }
+        }
+        // Symbols in open documents
+        for (uri, doc) in docs {
+            seen.insert(uri_path(uri));
+            flatten(items_symbols(&doc.items, doc), uri, None, &mut infos);
+        }
+        // Bindings in imported files that are not open
+        for (_, doc) in docs {
+            for binding in &doc.asm.bindings {
+                let InputSrc::File(file) = &binding.span.src else {
+                    continue;
+                };
+                let path = file.canonicalize().unwrap_or_else(|_| file.to_path_buf());
+                if seen.contains(&path) {
+                    continue;
+                }
+                let Ok(uri) = path_to_uri(file) else {
+                    continue;
+                };
+                let name = binding.span.as_str(&doc.asm.inputs, |s| s.to_string());
+                infos.push(SymbolInformation {
+                    name,
+                    kind: binding_symbol_kind(&binding.kind),
+                    tags: None,
+                    deprecated: None,
+                    location: Location {
+                        uri,
+                        range: uiua_span_to_lsp(&binding.span, &doc.asm.inputs),
+                    },
+                    container_name: file
+                        .file_stem()
+                        .map(|stem| stem.to_string_lossy().into_owned()),
+                });
+            }
+        }
+        // The same imported binding is found through every document that imports it
+        let mut locations = HashSet::new();
+        infos.retain(|info| {
+            let Location { uri, range } = &info.location;
+            let (start, end) = (range.start, range.end);
+            info.name.to_lowercase().contains(query)
+                && locations.insert((
+                    uri.clone(),
+                    (start.line, start.character),
+                    (end.line, end.character),
+                ))
+        });
+        infos
+    }
+
+    fn binding_symbol_kind(kind: &BindingKind) -> SymbolKind {
+        match kind {
+            BindingKind::Const(_) => SymbolKind::CONSTANT,
+            BindingKind::Func(_) => SymbolKind::FUNCTION,
+            BindingKind::Import(_) => SymbolKind::NAMESPACE,
+            BindingKind::Module(_) => SymbolKind::MODULE,
+            BindingKind::IndexMacro(_) | BindingKind::CodeMacro(_) => SymbolKind::OPERATOR,
+        }
+    }
+
+    /// Get the outline symbols for some items
+    #[allow(deprecated)]
+    fn items_symbols(items: &[Item], doc: &LspDoc) -> Vec<DocumentSymbol> {
+        let inputs = &doc.asm.inputs;
+        let binding = |span: &CodeSpan| doc.asm.bindings.iter().find(|b| b.span == *span);
+        let symbol = |name: String,
+                      detail: Option<String>,
+                      kind: SymbolKind,
+                      range: &CodeSpan,
+                      selection: &CodeSpan,
+                      children: Option<Vec<DocumentSymbol>>| DocumentSymbol {
+            name,
+            detail,
+            kind,
+            tags: None,
+            deprecated: None,
+            range: uiua_span_to_lsp(range, inputs),
+            selection_range: uiua_span_to_lsp(selection, inputs),
+            children,
+        };
+        let mut symbols = Vec::new();
+        for item in items {
+            match item {
+                Item::Binding(b) => {
+                    let info = binding(&b.name.span);
+                    let kind =
+                        info.map_or(SymbolKind::VARIABLE, |info| binding_symbol_kind(&info.kind));
+                    let detail = info.and_then(|info| match &info.kind {
+                        BindingKind::IndexMacro(_) => Some("index macro".into()),
+                        BindingKind::CodeMacro(_) => Some("code macro".into()),
+                        BindingKind::Func(f) => Some(f.sig.to_string()),
+                        _ => None,
+                    });
+                    symbols.push(symbol(
+                        b.name.value.to_string(),
+                        detail,
+                        kind,
+                        &b.span(),
+                        &b.name.span,
+                        None,
+                    ));
+                }
+                Item::Module(m) => {
+                    let end = (m.value.close_span.clone()).unwrap_or_else(|| m.span.clone());
+                    let range = m.value.open_span.clone().merge(end);
+                    let children = Some(items_symbols(&m.value.items, doc));
+                    symbols.push(match &m.value.kind {
+                        ModuleKind::Named(name) => symbol(
+                            name.value.to_string(),
+                            None,
+                            SymbolKind::MODULE,
+                            &range,
+                            &name.span,
+                            children,
+                        ),
+                        ModuleKind::Test => symbol(
+                            crate::compile::test_scope_name(&m.value.items)
+                                .map_or_else(|| "test".into(), |name| name.to_string()),
+                            Some("test scope".into()),
+                            SymbolKind::NAMESPACE,
+                            &range,
+                            &m.value.open_span,
+                            children,
+                        ),
+                    });
+                }
+                Item::Import(import) => {
+                    let Some(name) = &import.name else {
+                        continue;
+                    };
+                    symbols.push(symbol(
+                        name.value.to_string(),
+                        Some(import.path.value.clone()),
+                        SymbolKind::NAMESPACE,
+                        &import.span(),
+                        &name.span,
+                        None,
+                    ));
+                }
+                Item::Data(data) => {
+                    let fields = data.fields.as_ref();
+                    let children = fields.map(|fields| {
+                        (fields.fields.iter())
+                            .map(|field| {
+                                symbol(
+                                    field.name.value.to_string(),
+                                    None,
+                                    SymbolKind::FIELD,
+                                    &field.name.span,
+                                    &field.name.span,
+                                    None,
+                                )
+                            })
+                            .collect()
+                    });
+                    let selection = (data.name.as_ref())
+                        .map_or_else(|| data.init_span.clone(), |name| name.span.clone());
+                    let end = (fields.and_then(|fields| fields.close_span.clone()))
+                        .unwrap_or_else(|| selection.clone());
+                    let range = data.init_span.clone().merge(end);
+                    let name = (data.name.as_ref())
+                        .map_or_else(|| "data".into(), |name| name.value.to_string());
+                    let kind = if data.variant {
+                        SymbolKind::ENUM_MEMBER
+                    } else {
+                        SymbolKind::STRUCT
+                    };
+                    symbols.push(symbol(name, None, kind, &range, &selection, children));
+                }
+                Item::Words(_) | Item::Error(_) => {}
+            }
+        }
+        symbols
+    }
+
+    /// Get the folding ranges for some items
+    fn items_folding_ranges(items: &[Item], ranges: &mut Vec<FoldingRange>) {
+        for item in items {
+            match item {
+                Item::Words(lines) => lines_folding_ranges(lines, ranges),
+                Item::Binding(binding) => words_folding_ranges(&binding.words, ranges),
+                Item::Module(m) => {
+                    let end = (m.value.close_span.as_ref()).unwrap_or(&m.span);
+                    push_folding_range(&m.value.open_span, end, None, ranges);
+                    items_folding_ranges(&m.value.items, ranges);
+                }
+                Item::Data(data) => {
+                    if let Some(words) = &data.func {
+                        words_folding_ranges(words, ranges);
+                    }
+                }
+                Item::Import(_) | Item::Error(_) => {}
+            }
+        }
+    }
+
+    fn lines_folding_ranges(lines: &[Vec<Sp<Word>>], ranges: &mut Vec<FoldingRange>) {
+        for line in lines {
+            words_folding_ranges(line, ranges);
+        }
+    }
+
+    fn words_folding_ranges(words: &[Sp<Word>], ranges: &mut Vec<FoldingRange>) {
+        for word in words {
+            match &word.value {
+                Word::Func(func) => {
+                    push_folding_range(&word.span, &word.span, None, ranges);
+                    lines_folding_ranges(&func.lines, ranges);
+                }
+                Word::Pack(pack) => {
+                    push_folding_range(&word.span, &word.span, None, ranges);
+                    for branch in &pack.branches {
+                        lines_folding_ranges(&branch.value.lines, ranges);
+                    }
+                }
+                Word::Array(arr) => {
+                    push_folding_range(&word.span, &word.span, None, ranges);
+                    lines_folding_ranges(&arr.lines, ranges);
+                }
+                Word::Modified(m) => words_folding_ranges(&m.operands, ranges),
+                Word::Subscripted(sub) => words_folding_ranges(slice::from_ref(&sub.word), ranges),
+                Word::MultilineString(_) | Word::MultilineFormatString(_) => {
+                    push_folding_range(&word.span, &word.span, None, ranges)
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn push_folding_range(
+        start: &CodeSpan,
+        end: &CodeSpan,
+        kind: Option<FoldingRangeKind>,
+        ranges: &mut Vec<FoldingRange>,
+    ) {
+        if start.start.line >= end.end.line {
+            return;
+        }
+        ranges.push(FoldingRange {
+            start_line: start.start.line as u32 - 1,
+            start_character: None,
+            end_line: end.end.line as u32 - 1,
+            end_character: None,
+            kind,
+            collapsed_text: None,
+        });
+    }
+
+    /// Get the chain of enclosing spans around a position in some items, from outermost to innermost
+    fn items_selection_spans(items: &[Item], line: usize, col: usize, spans: &mut Vec<CodeSpan>) {
+        for item in items {
+            match item {
+                Item::Words(lines) => lines_selection_spans(lines, line, col, spans),
+                Item::Binding(binding) => {
+                    let span = binding.span();
+                    if span_has_cursor(&span, line, col) {
+                        spans.push(span);
+                        words_selection_spans(&binding.words, line, col, spans);
+                    }
+                }
+                Item::Module(m) => {
+                    let end = (m.value.close_span.clone()).unwrap_or_else(|| m.span.clone());
+                    let span = m.value.open_span.clone().merge(end);
+                    if span_has_cursor(&span, line, col) {
+                        spans.push(span);
+                        items_selection_spans(&m.value.items, line, col, spans);
+                    }
+                }
+                Item::Import(import) => {
+                    let span = import.span();
+                    if span_has_cursor(&span, line, col) {
+                        spans.push(span);
+                    }
+                }
+                Item::Data(data) => {
+                    if let Some(words) = &data.func {
+                        words_selection_spans(words, line, col, spans);
+                    }
+                }
+                Item::Error(_) => {}
+            }
+        }
+    }
+
+    fn lines_selection_spans(
+        lines: &[Vec<Sp<Word>>],
+        line: usize,
+        col: usize,
+        spans: &mut Vec<CodeSpan>,
+    ) {
+        for words in lines {
+            words_selection_spans(words, line, col, spans);
+        }
+    }
+
+    fn words_selection_spans(
+        words: &[Sp<Word>],
+        line: usize,
+        col: usize,
+        spans: &mut Vec<CodeSpan>,
+    ) {
+        let code = || words.iter().filter(|word| word.value.is_code());
+        let (Some(first), Some(last)) = (code().next(), code().next_back()) else {
+            return;
+        };
+        let line_span = first.span.clone().merge(last.span.clone());
+        if !span_has_cursor(&line_span, line, col) {
+            return;
+        }
+        spans.push(line_span);
+        let Some(word) = code().find(|word| span_has_cursor(&word.span, line, col)) else {
+            return;
+        };
+        spans.push(word.span.clone());
+        match &word.value {
+            Word::Func(func) => lines_selection_spans(&func.lines, line, col, spans),
+            Word::Pack(pack) => {
+                for branch in &pack.branches {
+                    if span_has_cursor(&branch.span, line, col) {
+                        spans.push(branch.span.clone());
+                        lines_selection_spans(&branch.value.lines, line, col, spans);
+                    }
+                }
+            }
+            Word::Array(arr) => lines_selection_spans(&arr.lines, line, col, spans),
+            Word::Strand(items) => words_selection_spans(items, line, col, spans),
+            Word::Modified(m) => {
+                if span_has_cursor(&m.modifier.span, line, col) {
+                    spans.push(m.modifier.span.clone());
+                } else {
+                    words_selection_spans(&m.operands, line, col, spans);
+                }
+            }
+            Word::Subscripted(sub) => {
+                words_selection_spans(slice::from_ref(&sub.word), line, col, spans)
+            }
+            _ => {}
+        }
+    }
+
+    /// Whether a span contains a position, including its end
+    fn span_has_cursor(span: &CodeSpan, line: usize, col: usize) -> bool {
+        let start = (span.start.line as usize, span.start.col as usize);
+        let end = (span.end.line as usize, span.end.col as usize);
+        (start..=end).contains(&(line, col))
+    }
+
+    /// Get the index of the binding defined or referenced at a position
+    fn binding_at(doc: &LspDoc, path: &PathBuf, line: usize, col: usize) -> Option<usize> {
+        let in_doc = |span: &CodeSpan| span.src == *path && span_has_cursor(span, line, col);
+        (doc.asm.bindings.iter())
+            .position(|binding| in_doc(&binding.span))
+            .or_else(|| {
+                (doc.code_meta.global_references.iter())
+                    .find(|(span, _)| in_doc(span))
+                    .map(|(_, &index)| index)
+            })
+    }
+
+    /// A call to a function binding
+    struct CallEdge {
+        /// The calling binding, or `None` for top-level code
+        caller: Option<usize>,
+        callee: usize,
+        span: CodeSpan,
+    }
+
+    /// Get the calls to function bindings in a document's assembly
+    fn call_edges(doc: &LspDoc) -> Vec<CallEdge> {
+        let asm = &doc.asm;
+        let binding_funcs: HashMap<usize, usize> = (asm.bindings.iter().enumerate())
+            .filter_map(|(i, binding)| match &binding.kind {
+                BindingKind::Func(f) => Some((f.index, i)),
+                _ => None,
+            })
+            .collect();
+        fn recurse(
+            node: &Node,
+            caller: Option<usize>,
+            asm: &Assembly,
+            binding_funcs: &HashMap<usize, usize>,
+            visited: &mut HashSet<usize>,
+            edges: &mut Vec<(Option<usize>, usize, usize)>,
+        ) {
+            let mut rec = |node: &Node, visited: &mut HashSet<usize>| {
+                recurse(node, caller, asm, binding_funcs, visited, edges)
+            };
+            match node {
+                Node::Run(nodes) => nodes.iter().for_each(|node| rec(node, visited)),
+                Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+                    args.iter().for_each(|arg| rec(&arg.node, visited))
+                }
+                Node::Switch { branches, .. } => {
+                    branches.iter().for_each(|br| rec(&br.node, visited))
+                }
+                Node::CustomInverse(cust, _) => {
+                    let under = cust
+                        .under
+                        .iter()
+                        .flat_map(|(before, after)| [before, after]);
+                    (cust.normal.as_ref().ok().into_iter())
+                        .chain(&cust.un)
+                        .chain(&cust.anti)
+                        .chain(under)
+                        .for_each(|sn| rec(&sn.node, visited))
+                }
+                Node::Array { inner, .. } | Node::NoInline(inner) | Node::TrackCaller(inner) => {
+                    rec(inner, visited)
+                }
+                Node::Call(f, span) => {
+                    if let Some(&callee) = binding_funcs.get(&f.index) {
+                        edges.push((caller, callee, *span));
+                    } else if visited.insert(f.index) {
+                        rec(&asm[f], visited);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut raw = Vec::new();
+        recurse(
+            &asm.root,
+            None,
+            asm,
+            &binding_funcs,
+            &mut HashSet::new(),
+            &mut raw,
+        );
+        for (i, binding) in asm.bindings.iter().enumerate() {
+            if let BindingKind::Func(f) = &binding.kind {
+                let mut visited = HashSet::from([f.index]);
+                recurse(
+                    &asm[f],
+                    Some(i),
+                    asm,
+                    &binding_funcs,
+                    &mut visited,
+                    &mut raw,
+                );
+            }
+        }
+        let mut edges: Vec<CallEdge> = (raw.into_iter())
+            .filter_map(|(caller, callee, span)| match asm.spans.get(span) {
+                Some(Span::Code(span)) => Some(CallEdge {
+                    caller,
+                    callee,
+                    span: span.clone(),
+                }),
+                _ => None,
+            })
+            .collect();
+        // Calls in the document itself may have been evaluated at compile time,
+        // and recursive calls have no span, so also use the document's references
+        let mut bodies = Vec::new();
+        items_binding_spans(&doc.items, &mut bodies);
+        let enclosing = |span: &CodeSpan| {
+            let (name, _) = bodies.iter().find(|(_, body)| {
+                body.src == span.src && body.start <= span.start && span.end <= body.end
+            })?;
+            asm.bindings.iter().position(|b| b.span == *name)
+        };
+        let called: HashSet<CodeSpan> = edges.iter().map(|edge| edge.span.clone()).collect();
+        for (span, &callee) in &doc.code_meta.global_references {
+            if span.src == doc.src
+                && !called.contains(span)
+                && matches!(asm.bindings[callee].kind, BindingKind::Func(_))
+            {
+                edges.push(CallEdge {
+                    caller: enclosing(span),
+                    callee,
+                    span: span.clone(),
+                });
+            }
+        }
+        edges.sort_by(|a, b| a.span.cmp(&b.span));
+        edges.dedup_by(|a, b| a.span == b.span);
+        edges
+    }
+
+    /// Find the code words that exactly cover a selection,
+    /// along with the span of the item that contains them
+    fn items_selected_words(
+        items: &[Item],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<(Vec<Sp<Word>>, CodeSpan)> {
+        items.iter().find_map(|item| match item {
+            Item::Words(lines) => lines.iter().find_map(|line| {
+                let words = words_selected(line, start, end)?;
+                Some((words, line.first()?.span.clone()))
+            }),
+            Item::Binding(binding) => {
+                words_selected(&binding.words, start, end).map(|words| (words, binding.span()))
+            }
+            Item::Module(m) => items_selected_words(&m.value.items, start, end),
+            Item::Import(_) | Item::Data(_) | Item::Error(_) => None,
+        })
+    }
+
+    fn words_selected(
+        words: &[Sp<Word>],
+        start: (usize, usize),
+        end: (usize, usize),
+    ) -> Option<Vec<Sp<Word>>> {
+        let loc = |loc: Loc| (loc.line as usize, loc.col as usize);
+        let code: Vec<&Sp<Word>> = words.iter().filter(|w| w.value.is_code()).collect();
+        // The selection is inside a single word
+        if let Some(word) = code.iter().find(|w| {
+            let (w_start, w_end) = (loc(w.span.start), loc(w.span.end));
+            w_start <= start && end <= w_end && (w_start, w_end) != (start, end)
+        }) {
+            let inner: Vec<&[Sp<Word>]> = match &word.value {
+                Word::Func(func) => func.lines.iter().map(Vec::as_slice).collect(),
+                Word::Pack(pack) => (pack.branches.iter())
+                    .flat_map(|br| &br.value.lines)
+                    .map(Vec::as_slice)
+                    .collect(),
+                Word::Array(arr) => arr.lines.iter().map(Vec::as_slice).collect(),
+                Word::Modified(m) => vec![m.operands.as_slice()],
+                Word::Subscripted(sub) => vec![slice::from_ref(&sub.word)],
+                _ => Vec::new(),
+            };
+            return (inner.into_iter()).find_map(|words| words_selected(words, start, end));
+        }
+        let inside = |w: &Sp<Word>| start <= loc(w.span.start) && loc(w.span.end) <= end;
+        let overlaps = |w: &Sp<Word>| loc(w.span.start) < end && start < loc(w.span.end);
+        if code.iter().any(|w| overlaps(w) && !inside(w)) {
+            return None;
+        }
+        let selected: Vec<Sp<Word>> = (code.into_iter()).filter(|w| inside(w)).cloned().collect();
+        (!selected.is_empty()).then_some(selected)
+    }
+
+    /// Get the edits that extract some words into a new binding
+    ///
+    /// The binding is defined above the item that contains the words.
+    fn extract_binding_edits(
+        doc: &LspDoc,
+        words: Vec<Sp<Word>>,
+        item_span: &CodeSpan,
+    ) -> Vec<TextEdit> {
+        let inputs = &doc.asm.inputs;
+        let span = (words[0].span.clone()).merge(words[words.len() - 1].span.clone());
+        let code = span.as_str(inputs, str::to_string);
+        let mut comp = Compiler::with_backend(NativeSys);
+        comp.pre_eval_mode(PreEvalMode::Lsp);
+        let sig = (comp.load_str_src(&doc.input, doc.src.clone()).ok())
+            .and_then(|comp| comp.words_nodes_sig(words));
+        let names: HashSet<String> = (doc.asm.bindings.iter())
+            .map(|binding| binding.span.as_str(inputs, str::to_string))
+            .collect();
+        let name = (1usize..)
+            .map(|i| match i {
+                1 => "Extracted".to_string(),
+                i => format!(
+                    "Extracted{}",
+                    (i.to_string().bytes())
+                        .map(|d| SUBSCRIPT_DIGITS[(d - b'0') as usize])
+                        .collect::<String>()
+                ),
+            })
+            .find(|name| !names.contains(name))
+            .unwrap();
+        let item_line = item_span.start.line as usize - 1;
+        let indent: String = (doc.input.lines().nth(item_line).unwrap_or(""))
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect();
+        let sig = sig.map(|sig| format!("{sig} ")).unwrap_or_default();
+        let insert = Position::new(item_line as u32, 0);
+        vec![
+            TextEdit {
+                range: Range::new(insert, insert),
+                new_text: format!("{indent}{name} ← {sig}{code}\n"),
+            },
+            TextEdit {
+                range: uiua_span_to_lsp(&span, inputs),
+                new_text: name,
+            },
+        ]
+    }
+
+    /// Get the edits that replace the references to a binding in a document with its code
+    ///
+    /// If `remove_def` is set, the definition and its doc comment are removed as well.
+    fn inline_binding_edits(
+        doc: &LspDoc,
+        index: usize,
+        path: &PathBuf,
+        remove_def: bool,
+    ) -> Option<Vec<TextEdit>> {
+        let binding = &doc.asm.bindings[index];
+        if binding.span.src != *path
+            || !matches!(binding.kind, BindingKind::Func(_) | BindingKind::Const(_))
+        {
+            return None;
+        }
+        let inputs = &doc.asm.inputs;
+        let def = items_find_binding(&doc.items, &binding.span)?;
+        let mut code = def.words.iter().filter(|w| w.value.is_code());
+        let first = code.next()?;
+        let body = match code.next_back() {
+            Some(last) => first.span.clone().merge(last.span.clone()),
+            None => first.span.clone(),
+        };
+        let mut refs: Vec<&CodeSpan> = (doc.code_meta.global_references.iter())
+            .filter(|(span, &i)| i == index && span.src == *path)
+            .map(|(span, _)| span)
+            .collect();
+        refs.sort();
+        let def_span = def.span();
+        if refs.is_empty()
+            || (refs.iter()).any(|r| def_span.start <= r.start && r.end <= def_span.end)
+        {
+            return None;
+        }
+        let code = body.as_str(inputs, str::to_string);
+        let single = def.words.iter().filter(|w| w.value.is_code()).count() == 1;
+        let new_text = match &def.signature {
+            Some(sig) => format!("({} {code})", sig.value),
+            None if single => code,
+            None => format!("({code})"),
+        };
+        let mut edits: Vec<TextEdit> = (refs.iter())
+            .map(|span| TextEdit {
+                range: uiua_span_to_lsp(span, inputs),
+                new_text: new_text.clone(),
+            })
+            .collect();
+        if remove_def {
+            let lines: Vec<&str> = doc.input.lines().collect();
+            let mut start = def_span.start.line as usize - 1;
+            // A doc comment is either on the lines directly above or at the end of the binding
+            let trailing = (def.words.last()).is_some_and(|w| matches!(w.value, Word::Comment(_)));
+            if binding.comment.is_some() && !trailing {
+                while start > 0 && is_doc_comment_line(lines[start - 1]) {
+                    start -= 1;
+                }
+            }
+            edits.push(TextEdit {
+                range: Range::new(
+                    Position::new(start as u32, 0),
+                    Position::new(def_span.end.line as u32, 0),
+                ),
+                new_text: String::new(),
+            });
+        }
+        Some(edits)
+    }
+
+    /// Whether a line is part of a binding's doc comment
+    fn is_doc_comment_line(line: &str) -> bool {
+        let line = line.trim();
+        if !line.starts_with('#') || line.starts_with("##") {
+            return false;
+        }
+        // Semantic comments other than those that apply to the binding are not doc comments
+        match all::<SemanticComment>().find(|sc| sc.to_string() == line) {
+            Some(sc) => matches!(sc, SemanticComment::NoInline | SemanticComment::TrackCaller),
+            None => true,
+        }
+    }
+
+    /// Find the definition of a binding in some items
+    fn items_find_binding<'a>(items: &'a [Item], name: &CodeSpan) -> Option<&'a Binding> {
+        items.iter().find_map(|item| match item {
+            Item::Binding(binding) if binding.name.span == *name => Some(binding),
+            Item::Module(m) => items_find_binding(&m.value.items, name),
+            _ => None,
+        })
+    }
+
+    /// Get the name and full spans of the bindings in some items
+    fn items_binding_spans(items: &[Item], spans: &mut Vec<(CodeSpan, CodeSpan)>) {
+        for item in items {
+            match item {
+                Item::Binding(binding) => spans.push((binding.name.span.clone(), binding.span())),
+                Item::Module(m) => items_binding_spans(&m.value.items, spans),
+                _ => {}
+            }
+        }
+    }
+
+    /// Get the URI of the file that contains a span
+    fn span_uri(span: &CodeSpan, doc_uri: &Url) -> Option<Url> {
+        match &span.src {
+            InputSrc::File(file) => path_to_uri(file).ok(),
+            InputSrc::Str(_) | InputSrc::Macro(_) => Some(doc_uri.clone()),
+        }
+    }
+
+    /// Get the call hierarchy item for a binding
+    fn binding_call_item(doc: &LspDoc, doc_uri: &Url, index: usize) -> Option<CallHierarchyItem> {
+        let binding = &doc.asm.bindings[index];
+        let range = uiua_span_to_lsp(&binding.span, &doc.asm.inputs);
+        Some(CallHierarchyItem {
+            name: binding.span.as_str(&doc.asm.inputs, |s| s.to_string()),
+            kind: binding_symbol_kind(&binding.kind),
+            tags: None,
+            detail: match &binding.kind {
+                BindingKind::Func(f) => Some(f.sig.to_string()),
+                _ => None,
+            },
+            uri: span_uri(&binding.span, doc_uri)?,
+            range,
+            selection_range: range,
+            data: None,
+        })
+    }
+
+    /// Get the call hierarchy item for the top-level code of a file
+    fn file_call_item(uri: Url) -> CallHierarchyItem {
+        let name = (uri.path_segments())
+            .and_then(|mut segments| segments.next_back().map(Into::into))
+            .unwrap_or_else(|| uri.to_string());
+        CallHierarchyItem {
+            name,
+            kind: SymbolKind::FILE,
+            tags: None,
+            detail: None,
+            uri,
+            range: Range::default(),
+            selection_range: Range::default(),
+            data: None,
+        }
+    }
+
+    /// Add a call to a list of calls grouped by item
+    fn group_call(
+        groups: &mut Vec<(CallHierarchyItem, Vec<Range>)>,
+        item: CallHierarchyItem,
+        range: Range,
+    ) {
+        let same = |other: &CallHierarchyItem| {
+            other.uri == item.uri && other.selection_range == item.selection_range
+        };
+        match groups.iter_mut().find(|(other, _)| same(other)) {
+            Some((_, ranges)) => {
+                if !ranges.contains(&range) {
+                    ranges.push(range);
+                }
+            }
+            None => groups.push((item, vec![range])),
+        }
+    }
+
+    /// A function or modifier whose arguments are being written
+    enum Callee {
+        Primitive(Primitive, Option<i32>),
+        Binding(usize),
+    }
+
+    /// Find the innermost call or modifier that the cursor is filling an argument of,
+    /// along with the index of that argument
+    fn items_signature_target(
+        items: &[Item],
+        line: usize,
+        col: usize,
+        doc: &LspDoc,
+    ) -> Option<(Callee, bool, usize)> {
+        items.iter().find_map(|item| match item {
+            Item::Words(lines) => lines_signature_target(lines, line, col, doc),
+            Item::Binding(binding) => words_signature_target(&binding.words, line, col, doc),
+            Item::Module(m) => items_signature_target(&m.value.items, line, col, doc),
+            Item::Import(_) | Item::Data(_) | Item::Error(_) => None,
+        })
+    }
+
+    fn lines_signature_target(
+        lines: &[Vec<Sp<Word>>],
+        line: usize,
+        col: usize,
+        doc: &LspDoc,
+    ) -> Option<(Callee, bool, usize)> {
+        (lines.iter()).find_map(|words| words_signature_target(words, line, col, doc))
+    }
+
+    fn words_signature_target(
+        words: &[Sp<Word>],
+        line: usize,
+        col: usize,
+        doc: &LspDoc,
+    ) -> Option<(Callee, bool, usize)> {
+        let cursor = (line, col);
+        let start = |span: &CodeSpan| (span.start.line as usize, span.start.col as usize);
+        let end = |span: &CodeSpan| (span.end.line as usize, span.end.col as usize);
+        // The cursor is inside a word
+        if let Some(word) = (words.iter()).find(|word| {
+            start(&word.span) < cursor
+                && (cursor < end(&word.span) || cursor == end(&word.span) && word_is_open(word))
+        }) {
+            return match &word.value {
+                Word::Func(func) => lines_signature_target(&func.lines, line, col, doc),
+                Word::Pack(pack) => (pack.branches.iter())
+                    .find_map(|br| lines_signature_target(&br.value.lines, line, col, doc)),
+                Word::Array(arr) => lines_signature_target(&arr.lines, line, col, doc),
+                Word::Subscripted(sub) => {
+                    words_signature_target(slice::from_ref(&sub.word), line, col, doc)
+                }
+                Word::Modified(m) => {
+                    let callee = match &m.modifier.value {
+                        Modifier::Primitive(prim) => Some(Callee::Primitive(*prim, None)),
+                        Modifier::Ref(r) => (doc.code_meta.global_references.get(&r.name.span))
+                            .map(|&index| Callee::Binding(index)),
+                        Modifier::Macro(_) => None,
+                    };
+                    let mut slot = 0;
+                    for op in m.code_operands() {
+                        if start(&op.span) < cursor && cursor <= end(&op.span) {
+                            let nested =
+                                words_signature_target(slice::from_ref(op), line, col, doc);
+                            if nested.is_some() {
+                                return nested;
+                            }
+                            if let Word::Pack(pack) = &op.value {
+                                slot += (pack.branches.iter())
+                                    .filter(|br| end(&br.span) < cursor)
+                                    .count();
+                            }
+                            break;
+                        }
+                        if end(&op.span) <= cursor {
+                            slot += match &op.value {
+                                Word::Pack(pack) => pack.branches.len(),
+                                _ => 1,
+                            };
+                        }
+                    }
+                    callee.map(|callee| (callee, true, slot))
+                }
+                _ => None,
+            };
+        }
+        let (first, last) = (words.first()?, words.last()?);
+        if !(start(&first.span) < cursor && cursor <= end(&last.span)) {
+            return None;
+        }
+        // Simulate the stack from the cursor back to the function being called
+        let mut values = 0;
+        for word in words.iter().rev() {
+            if end(&word.span) > cursor || !word.value.is_code() {
+                continue;
+            }
+            let (callee, sig) = match &word.value {
+                Word::Primitive(prim) => (Some(Callee::Primitive(*prim, None)), prim.sig()?),
+                Word::Subscripted(sub) => match &sub.word.value {
+                    Word::Primitive(prim) => {
+                        let n = sub.n.value.n();
+                        (Some(Callee::Primitive(*prim, n)), prim.subscript_sig(n)?)
+                    }
+                    _ => return None,
+                },
+                Word::Ref(r) => {
+                    let index = *doc.code_meta.global_references.get(&r.name.span)?;
+                    match &doc.asm.bindings[index].kind {
+                        BindingKind::Func(f) => (Some(Callee::Binding(index)), f.sig),
+                        BindingKind::Const(_) => (None, Signature::new(0, 1)),
+                        _ => return None,
+                    }
+                }
+                Word::Func(_) => (None, doc.code_meta.function_sigs.get(&word.span)?.sig),
+                Word::Number(_)
+                | Word::Char(_)
+                | Word::String(_)
+                | Word::MultilineString(_)
+                | Word::FormatString(_)
+                | Word::MultilineFormatString(_)
+                | Word::Strand(_)
+                | Word::Array(_) => (None, Signature::new(0, 1)),
+                Word::Label(_) | Word::SemanticComment(_) | Word::OutputComment { .. } => continue,
+                _ => return None,
+            };
+            if sig.args > values {
+                return callee.map(|callee| (callee, false, values));
+            }
+            values = values - sig.args + sig.outputs;
+        }
+        None
+    }
+
+    /// Whether a word could still be extended at its end
+    fn word_is_open(word: &Sp<Word>) -> bool {
+        match &word.value {
+            Word::Func(func) => !func.closed,
+            Word::Pack(pack) => !pack.closed,
+            Word::Array(arr) => !arr.closed,
+            Word::Subscripted(sub) => word_is_open(&sub.word),
+            Word::Modified(m) => {
+                let args = match &m.modifier.value {
+                    Modifier::Primitive(prim) => prim.modifier_args().unwrap_or(0),
+                    Modifier::Ref(r) => ident_modifier_args(&r.name.value),
+                    Modifier::Macro(mac) => ident_modifier_args(&mac.ident.value),
+                };
+                let operands: usize = (m.code_operands())
+                    .map(|op| match &op.value {
+                        Word::Pack(pack) => pack.branches.len(),
+                        _ => 1,
+                    })
+                    .sum();
+                operands < args || m.code_operands().last().is_some_and(word_is_open)
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the signature information for a call or modifier
+    fn callee_signature(callee: &Callee, is_modifier: bool, doc: &LspDoc) -> SignatureInformation {
+        const ARG_NAMES: [&str; 5] = ["a", "b", "c", "d", "e"];
+        const FUNC_NAMES: [&str; 5] = ["F", "G", "H", "I", "J"];
+        let generic = |names: &[&str], n: usize| -> Vec<String> {
+            (0..n)
+                .map(|i| {
+                    names
+                        .get(i)
+                        .map_or_else(|| format!("x{}", i + 1), |s| s.to_string())
+                })
+                .collect()
+        };
+        let (name, sig, params, documentation) = match callee {
+            Callee::Primitive(prim, n) => {
+                let sig = match n {
+                    _ if is_modifier => None,
+                    Some(_) => prim.subscript_sig(*n),
+                    None => prim.sig(),
+                };
+                let params = if let Some(margs) = prim.modifier_args().filter(|_| is_modifier) {
+                    generic(&FUNC_NAMES, margs)
+                } else {
+                    generic(&ARG_NAMES, sig.map_or(0, |sig| sig.args))
+                };
+                let doc = prim.doc().short_text().into_owned();
+                (prim.format().to_string(), sig, params, doc)
+            }
+            Callee::Binding(index) => {
+                let binding = &doc.asm.bindings[*index];
+                let name = binding.span.as_str(&doc.asm.inputs, |s| s.to_string());
+                let comment = binding.comment.as_ref();
+                let (sig, params) = match &binding.kind {
+                    BindingKind::Func(f) => {
+                        let names = (comment.and_then(|c| c.sig.as_ref()))
+                            .filter(|csig| csig.matches_sig(f.sig))
+                            .map(|csig| csig.args.iter().map(|arg| arg.name.to_string()).collect());
+                        (
+                            Some(f.sig),
+                            names.unwrap_or_else(|| generic(&ARG_NAMES, f.sig.args)),
+                        )
+                    }
+                    _ => (None, generic(&FUNC_NAMES, ident_modifier_args(&name))),
+                };
+                let text = comment.map(|c| c.text.to_string()).unwrap_or_default();
+                (name, sig, params, text)
+            }
+        };
+        let mut label = name;
+        let mut offsets = Vec::with_capacity(params.len());
+        for param in &params {
+            label.push(' ');
+            let start = label.encode_utf16().count() as u32;
+            label.push_str(param);
+            offsets.push([start, label.encode_utf16().count() as u32]);
+        }
+        if let Some(sig) = sig {
+            label.push_str(&format!("  {sig}"));
+        }
+        SignatureInformation {
+            label,
+            documentation: (!documentation.is_empty()).then_some(Documentation::MarkupContent(
+                MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: documentation,
+                },
+            )),
+            parameters: Some(
+                (offsets.into_iter())
+                    .map(|offsets| ParameterInformation {
+                        label: ParameterLabel::LabelOffsets(offsets),
+                        documentation: None,
+                    })
+                    .collect(),
+            ),
+            active_parameter: None,
+        }
+    }
+
     fn doc_frag_markdown(md: &mut String, frag: &PrimDocFragment) {
         match frag {
             PrimDocFragment::Text(text) => md.push_str(text),
@@ -2485,4 +4357,478 @@ mod server {
         }
         value
     }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        /// Create a fresh directory for test files
+        fn temp_dir(name: &str) -> PathBuf {
+            let dir = std::env::temp_dir().join(format!(
+                "uiua_lsp_{name}_{}_{}",
+                std::process::id(),
+                std::time::SystemTime::now()
+                    .duration_since(std::time::UNIX_EPOCH)
+                    .unwrap()
+                    .as_nanos()
+            ));
+            std::fs::create_dir_all(&dir).unwrap();
+            dir
+        }
+
+        fn analyze(path: &Path, code: &str) -> LspDoc {
+            LspDoc::analyze(path, code.into(), None, &ImportCache::default(), &|| false).unwrap()
+        }
+
+        fn block_on<F: std::future::Future>(f: F) -> F::Output {
+            (tokio::runtime::Builder::new_current_thread().build())
+                .unwrap()
+                .block_on(f)
+        }
+
+        #[test]
+        fn docs_debounce() {
+            let dir = temp_dir("debounce");
+            let uri = Url::from_file_path(dir.join("doc.ua")).unwrap();
+            let docs = Docs::new();
+            for version in 1..=3 {
+                docs.update(uri.clone(), version, format!("{version}\n"), true);
+            }
+            // Debounced changes wait for further changes
+            assert!(docs.shared.pending.contains_key(&uri));
+            assert!(docs.shared.analyzed.is_empty());
+            // Requests do not wait for the debounce
+            let doc = block_on(docs.get(&uri)).unwrap();
+            assert_eq!(doc.input, "3\n");
+            assert!(docs.shared.pending.is_empty());
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn docs_cancel_stale() {
+            let dir = temp_dir("cancel");
+            let uri = Url::from_file_path(dir.join("doc.ua")).unwrap();
+            let docs = Docs::new();
+            // The analysis of a long version is canceled by a newer one
+            docs.update(uri.clone(), 1, "X ← +1 1\n".repeat(2000), false);
+            docs.update(uri.clone(), 2, "Y ← 5\n".into(), false);
+            let doc = block_on(docs.get(&uri)).unwrap();
+            assert_eq!(doc.input, "Y ← 5\n");
+            // Nothing stale arrives later
+            thread::sleep(ANALYSIS_DEBOUNCE);
+            let (_, doc) = block_on(docs.all()).pop().unwrap();
+            assert_eq!(doc.input, "Y ← 5\n");
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn workspace_symbols_dedup() {
+            let dir = temp_dir("symbols");
+            std::fs::write(dir.join("lib.ua"), "F ← +1\nG ← ×2\n").unwrap();
+            let mut docs = Vec::new();
+            for name in ["a.ua", "b.ua"] {
+                let path = dir.join(name);
+                let code = "Lib ~ \"lib.ua\"\nLib~F 1\n";
+                std::fs::write(&path, code).unwrap();
+                let uri = Url::from_file_path(&path).unwrap();
+                docs.push((uri, Arc::new(analyze(&path, code))));
+            }
+            let symbols = workspace_symbols(&docs, "");
+            let imported: Vec<_> = (symbols.iter())
+                .filter(|sym| sym.location.uri.path().ends_with("lib.ua"))
+                .map(|sym| sym.name.as_str())
+                .collect();
+            assert_eq!(imported, ["F", "G"]);
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn folding_ranges() {
+            let code = "\
+F ← (
+  +1
+)
+┌─╴M
+  G ← [1
+    2]
+└─╴
+\"single line
+H ← $ multiline
+    $ string";
+            let (items, _, _) = parse(code, (), &mut Inputs::default());
+            let mut ranges = Vec::new();
+            items_folding_ranges(&items, &mut ranges);
+            let lines: Vec<_> = (ranges.iter())
+                .map(|range| (range.start_line, range.end_line))
+                .collect();
+            assert_eq!(lines, [(0, 2), (3, 6), (4, 5), (8, 9)]);
+        }
+
+        #[test]
+        fn selection_spans() {
+            let code = "X ← 5\nF ← ⊃(+1|×2) 3";
+            let mut inputs = Inputs::default();
+            let (items, _, _) = parse(code, (), &mut inputs);
+            let mut spans = Vec::new();
+            items_selection_spans(&items, 2, 8, &mut spans);
+            let texts: Vec<_> = (spans.iter())
+                .map(|span| span.as_str(&inputs, |s| s.to_string()))
+                .collect();
+            // Repeated spans are merged when building the selection ranges
+            assert_eq!(
+                texts,
+                [
+                    "F ← ⊃(+1|×2) 3",
+                    "⊃(+1|×2) 3",
+                    "⊃(+1|×2)",
+                    "(+1|×2)",
+                    "(+1|×2)",
+                    "+1",
+                    "+1",
+                    "+"
+                ]
+            );
+            spans.clear();
+            items_selection_spans(&items, 1, 10, &mut spans);
+            assert!(spans.is_empty());
+        }
+
+        #[test]
+        fn call_hierarchy_edges() {
+            let code = "\
+F ← +1
+G ← F F
+H ← ⍥G 2
+H 3
+F 4";
+            let doc = analyze(Path::new("calls.ua"), code);
+            let name = |i: usize| {
+                doc.asm.bindings[i]
+                    .span
+                    .as_str(&doc.asm.inputs, |s| s.to_string())
+            };
+            let edges: Vec<_> = (call_edges(&doc).into_iter())
+                .map(|edge| {
+                    let caller = edge.caller.map(name);
+                    (caller, name(edge.callee), edge.span.start.line)
+                })
+                .collect();
+            let edge = |caller: Option<&str>, callee: &str, line| {
+                (caller.map(Into::into), callee.to_string(), line)
+            };
+            assert_eq!(
+                edges,
+                [
+                    edge(Some("G"), "F", 2),
+                    edge(Some("G"), "F", 2),
+                    edge(Some("H"), "G", 3),
+                    edge(None, "H", 4),
+                    edge(None, "F", 5),
+                ]
+            );
+        }
+
+        #[test]
+        fn selected_words() {
+            let code = "X ← +1 ×2 345\n┌─╴M\n  F ← ⊃(+1|×2)\n└─╴";
+            let mut inputs = Inputs::default();
+            let (items, _, _) = parse(code, (), &mut inputs);
+            let text = |words: &[Sp<Word>]| {
+                (words.first().unwrap().span.clone())
+                    .merge(words.last().unwrap().span.clone())
+                    .as_str(&inputs, str::to_string)
+            };
+            let selected = |start, end| {
+                items_selected_words(&items, start, end).map(|(words, item_span)| {
+                    (text(&words), item_span.as_str(&inputs, str::to_string))
+                })
+            };
+            // Whole words in a binding
+            assert_eq!(
+                selected((1, 5), (1, 10)),
+                Some(("+1 ×2".into(), "X ← +1 ×2 345".into()))
+            );
+            // Words inside a function pack in a module
+            assert_eq!(
+                selected((3, 12), (3, 14)),
+                Some(("×2".into(), "F ← ⊃(+1|×2)".into()))
+            );
+            // A selection that splits a word
+            assert_eq!(selected((1, 12), (1, 13)), None);
+            // Nothing selected
+            assert_eq!(words_selected(&[], (1, 1), (1, 2)), None);
+        }
+
+        #[test]
+        fn document_symbols() {
+            let code = "\
+┌─╴M
+  F ← +1
+  ┌─╴N
+    G ← ×2
+  └─╴
+└─╴
+---
+# Check F
+⍤\"oops\" =2 M~F 1
+---
+~Point {X Y}
+Twice! ← ^0^0
+Code! ←^ ◌\"+1\"
+";
+            let dir = temp_dir("symbols");
+            let doc = analyze(&dir.join("symbols.ua"), code);
+            fn flatten(
+                symbols: &[DocumentSymbol],
+                depth: usize,
+                flat: &mut Vec<(usize, String, SymbolKind, Option<String>)>,
+            ) {
+                for symbol in symbols {
+                    let detail = symbol.detail.clone();
+                    flat.push((depth, symbol.name.clone(), symbol.kind, detail));
+                    flatten(symbol.children.as_deref().unwrap_or(&[]), depth + 1, flat);
+                }
+            }
+            let mut flat = Vec::new();
+            flatten(&items_symbols(&doc.items, &doc), 0, &mut flat);
+            let flat: Vec<_> = (flat.iter())
+                .map(|(depth, name, kind, detail)| {
+                    (*depth, name.as_str(), *kind, detail.as_deref())
+                })
+                .collect();
+            assert_eq!(
+                flat,
+                [
+                    (0, "M", SymbolKind::MODULE, None),
+                    (1, "F", SymbolKind::FUNCTION, Some("|1")),
+                    (1, "N", SymbolKind::MODULE, None),
+                    (2, "G", SymbolKind::FUNCTION, Some("|1")),
+                    (0, "Check F", SymbolKind::NAMESPACE, Some("test scope")),
+                    (0, "Point", SymbolKind::STRUCT, None),
+                    (1, "X", SymbolKind::FIELD, None),
+                    (1, "Y", SymbolKind::FIELD, None),
+                    (0, "Twice!", SymbolKind::OPERATOR, Some("index macro")),
+                    (0, "Code!", SymbolKind::OPERATOR, Some("code macro")),
+                ]
+            );
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn find_binding() {
+            let code = "X ← 1\n┌─╴M\n  Y ← 2\n└─╴";
+            let mut inputs = Inputs::default();
+            let (items, _, _) = parse(code, (), &mut inputs);
+            let find = |name: &str| {
+                let mut names = Vec::new();
+                fn collect(items: &[Item], names: &mut Vec<CodeSpan>) {
+                    for item in items {
+                        match item {
+                            Item::Binding(binding) => names.push(binding.name.span.clone()),
+                            Item::Module(m) => collect(&m.value.items, names),
+                            _ => {}
+                        }
+                    }
+                }
+                collect(&items, &mut names);
+                let span = (names.into_iter())
+                    .find(|span| span.as_str(&inputs, |s| s == name))
+                    .unwrap();
+                items_find_binding(&items, &span).map(|binding| binding.span())
+            };
+            assert_eq!(find("Y").unwrap().start.line, 3);
+            assert_eq!(find("X").unwrap().start.line, 1);
+        }
+
+        /// Apply edits to some text
+        fn apply_edits(text: &str, mut edits: Vec<TextEdit>) -> String {
+            edits.sort_by_key(|edit| edit.range.start);
+            let mut text = text.to_string();
+            for edit in edits.into_iter().rev() {
+                let offset = |pos: Position| {
+                    let line_start: usize = (text.split_inclusive('\n'))
+                        .take(pos.line as usize)
+                        .map(str::len)
+                        .sum();
+                    let line = text[line_start..].split('\n').next().unwrap();
+                    let col: usize = (line.chars().take(pos.character as usize))
+                        .map(char::len_utf8)
+                        .sum();
+                    line_start + col
+                };
+                let range = offset(edit.range.start)..offset(edit.range.end);
+                text.replace_range(range, &edit.new_text);
+            }
+            text
+        }
+
+        #[test]
+        fn extract_binding() {
+            let code = "X ← 5\nY ← +1 ×2 X\n";
+            let doc = analyze(Path::new("extract.ua"), code);
+            let (words, item_span) = items_selected_words(&doc.items, (2, 5), (2, 10)).unwrap();
+            let edits = extract_binding_edits(&doc, words, &item_span);
+            assert_eq!(
+                apply_edits(code, edits),
+                "X ← 5\nExtracted ← |1 +1 ×2\nY ← Extracted X\n"
+            );
+        }
+
+        #[test]
+        fn inline_binding() {
+            let code = "\
+# Experimental!
+F ← +1 # Increment
+G ← F F 1
+
+# Not about H
+
+# Double
+# No inline!
+H ← ×2
+I ← H 3
+";
+            let dir = temp_dir("inline");
+            let path = dir.join("inline.ua");
+            std::fs::write(&path, code).unwrap();
+            let doc = analyze(&path, code);
+            let inline = |name: &str, remove_def| {
+                let index = (doc.asm.bindings.iter())
+                    .position(|b| b.span.as_str(&doc.asm.inputs, |s| s == name))
+                    .unwrap();
+                let edits = inline_binding_edits(&doc, index, &path, remove_def).unwrap();
+                apply_edits(code, edits)
+            };
+            // A trailing doc comment does not remove the comments above
+            assert!(inline("F", true).starts_with("# Experimental!\nG ← (+1) (+1) 1\n"));
+            assert!(inline("F", false)
+                .starts_with("# Experimental!\nF ← +1 # Increment\nG ← (+1) (+1) 1\n"));
+            // Only the doc comment directly above is removed
+            assert!(inline("H", true).ends_with("G ← F F 1\n\n# Not about H\n\nI ← (×2) 3\n"));
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn lenses() {
+            let code = "F ← +1\nF 2\n---\n⍤\"oops\" =2 F 1\n---\n";
+            let dir = temp_dir("lenses");
+            let path = dir.join("lenses.ua");
+            let doc = analyze(&path, code);
+            let uri = Url::from_file_path(&path).unwrap();
+            let lenses: Vec<_> = (code_lenses(&doc, &uri).into_iter())
+                .map(|lens| (lens.range.start.line, lens.command.unwrap().command))
+                .collect();
+            assert_eq!(
+                lenses,
+                [
+                    (1, RUN_LINE_COMMAND.to_string()),
+                    (1, DEBUG_LINE_COMMAND.to_string()),
+                    (2, RUN_TEST_COMMAND.to_string()),
+                ]
+            );
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn line_lens() {
+            let dir = temp_dir("line_lens");
+            let path = dir.join("line.ua");
+            let doc = analyze(&path, "F ← +1\nF ×2 3\n");
+            let show = |results: Vec<LensResult>| {
+                (results.iter())
+                    .map(|result| (result.position.character, result.label()))
+                    .collect::<Vec<_>>()
+            };
+            assert_eq!(show(run_line_lens(&doc, 2, false)), [(6, "7".into())]);
+            // Debug steps run from right to left
+            assert_eq!(
+                show(run_line_lens(&doc, 2, true)),
+                [
+                    (5, "3".into()),
+                    (3, "2 3".into()),
+                    (2, "6".into()),
+                    (0, "7".into())
+                ]
+            );
+            assert!(run_line_lens(&doc, 1, false).is_empty());
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_lens() {
+            let dir = temp_dir("test_lens");
+            let path = dir.join("test.ua");
+            let code = "F ← +1\n---\n⍤\"oops\" =2 F 1\n---\n---\n⍤\"oops\" =3 F 1\n---\n";
+            let doc = analyze(&path, code);
+            let message = |line| run_test_lens(&doc, line).pop().unwrap().message.unwrap();
+            assert_eq!(message(2), "Test passed");
+            assert!(message(5).starts_with("✗"), "{}", message(5));
+            assert!(run_test_lens(&doc, 1).is_empty());
+            _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn lens_sandbox() {
+            let dir = temp_dir("lens_sandbox");
+            std::fs::write(dir.join("lib.ua"), "X ← 5\n").unwrap();
+            let outside = temp_dir("lens_outside").join("secret.txt");
+            std::fs::write(&outside, "secret").unwrap();
+            let path = dir.join("main.ua");
+            let code = format!(
+                "Lib ~ \"lib.ua\"\nLib~X\n&fras {:?}\n",
+                outside.display().to_string()
+            );
+            let doc = analyze(&path, &code);
+            // Imports from the document's directory work
+            assert_eq!(run_line_lens(&doc, 2, false)[0].label(), "5");
+            // Other files cannot be read
+            let result = run_line_lens(&doc, 3, false).pop().unwrap();
+            assert!(result.message.unwrap().contains("outside"));
+            _ = std::fs::remove_dir_all(&dir);
+            _ = std::fs::remove_dir_all(outside.parent().unwrap());
+        }
+
+        #[test]
+        fn signature_label() {
+            let doc = analyze(Path::new("sig.ua"), "# Add then split\n# ? x y\nF ← ⊃+-\n");
+            let label = |callee| callee_signature(&callee, false, &doc).label;
+            assert_eq!(
+                label(Callee::Primitive(Primitive::Add, None)),
+                "+ add a b  |2"
+            );
+            assert_eq!(label(Callee::Binding(0)), "F x y  |2.2");
+        }
+
+        #[test]
+        fn signature_target() {
+            let target = |code: &str, line, col| {
+                let doc = analyze(Path::new("target.ua"), code);
+                let (callee, is_modifier, active) =
+                    items_signature_target(&doc.items, line, col, &doc)?;
+                let name = match callee {
+                    Callee::Primitive(prim, _) => prim.format().to_string(),
+                    Callee::Binding(index) => {
+                        (doc.asm.bindings[index].span).as_str(&doc.asm.inputs, str::to_string)
+                    }
+                };
+                Some((name, is_modifier, active))
+            };
+            // Primitive calls count the values after them
+            assert_eq!(target("+ 1", 1, 3), Some(("+ add".into(), false, 0)));
+            assert_eq!(target("+ 1", 1, 4), Some(("+ add".into(), false, 1)));
+            assert_eq!(target("⊂ 1 2", 1, 6), None);
+            // Modifier operand slots
+            assert_eq!(target("⊃(+1|×2) 5", 1, 6), Some(("⊃ fork".into(), true, 1)));
+            assert_eq!(target("⊃(+1|×2) 5", 1, 3), Some(("⊃ fork".into(), true, 0)));
+            assert_eq!(
+                target("⊃(+1|×2) 5", 1, 8),
+                Some(("× multiply".into(), false, 1))
+            );
+            assert_eq!(target("⊃(+1|", 1, 6), Some(("⊃ fork".into(), true, 1)));
+            // Binding calls
+            let code = "F ← +\nF 1";
+            assert_eq!(target(code, 2, 2), Some(("F".into(), false, 0)));
+            assert_eq!(target(code, 2, 4), Some(("F".into(), false, 1)));
+        }
+    }
 }
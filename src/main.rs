@@ -5,7 +5,7 @@ use std::{
     env,
     error::Error,
     fmt, fs,
-    io::{self, stderr, stdin, stdout, BufRead, Write},
+    io::{self, stderr, stdin, stdout, Read, Write},
     path::{Path, PathBuf},
     process::{exit, Child, Command, Stdio},
     sync::{
@@ -24,8 +24,11 @@ use parking_lot::Mutex;
 use rustyline::{error::ReadlineError, DefaultEditor};
 use terminal_size::terminal_size;
 use uiua::{
-    format::{format_file, format_str, FormatConfig, FormatConfigSource},
+    bench::{self, BenchFormat},
+    format::{format, format_file, format_str, FormatConfig, FormatConfigSource, FormatOutput},
+    lint::{lint_file, LintConfig, LintLevel, LINT_RULES},
     lsp::BindingDocsKind,
+    testing::{json_report, junit_report, CoverageFormat, TestFormat},
     Assembly, CodeSpan, Compiler, NativeSys, PreEvalMode, PrimClass, PrimDocFragment, PrimDocLine,
     Primitive, RunMode, SafeSys, SpanKind, Spans, Uiua, UiuaError, UiuaErrorKind, UiuaResult,
     Value, CONSTANTS,
@@ -122,29 +125,58 @@ fn main() {
         Some(Comm::Fmt {
             path,
             formatter_options,
-            io,
+            stdin: from_stdin,
+            check,
+            diff,
+            ascii,
         }) => {
-            let config =
+            let mut config =
                 FormatConfig::from_source(formatter_options.format_config_source, path.as_deref())
                     .unwrap_or_else(fail);
+            if ascii {
+                config = config.with_ascii(true);
+            }
 
-            if io {
-                let mut buffer = String::new();
+            if from_stdin {
                 let mut code = String::new();
-                let stdin = stdin();
-                let mut stdin = stdin.lock();
-                loop {
-                    buffer.clear();
-                    if stdin.read_line(&mut buffer).is_err() {
-                        break;
+                if let Err(e) = stdin().lock().read_to_string(&mut code) {
+                    eprintln!("Failed to read stdin: {e}");
+                    exit(1);
+                }
+                let formatted = format_str(&code, &config).unwrap_or_else(fail);
+                if check || diff {
+                    if report_format_changes("<stdin>", &code, &formatted, check, diff) && check {
+                        exit(1);
                     }
-                    if buffer.is_empty() {
-                        break;
+                } else {
+                    print!("{}", formatted.output);
+                }
+            } else if check || diff {
+                let paths = if let Some(path) = path {
+                    vec![path]
+                } else {
+                    uiua_files_in(".".as_ref()).unwrap_or_else(fail)
+                };
+                let mut changed = 0;
+                for path in paths {
+                    let code = fs::read_to_string(&path)
+                        .map_err(|e| UiuaError::load(path.clone(), e))
+                        .unwrap_or_else(fail);
+                    let formatted = format(&code, &path, &config).unwrap_or_else(fail);
+                    let name = path
+                        .strip_prefix(".")
+                        .unwrap_or(&path)
+                        .display()
+                        .to_string();
+                    if report_format_changes(&name, &code, &formatted, check, diff) {
+                        changed += 1;
                     }
-                    code.push_str(&buffer);
                 }
-                let formatted = format_str(&code, &config).unwrap_or_else(fail);
-                print!("{}", formatted.output);
+                if check && changed > 0 {
+                    let s = if changed == 1 { "" } else { "s" };
+                    eprintln!("{changed} file{s} would be reformatted");
+                    exit(1);
+                }
             } else if let Some(path) = path {
                 format_single_file(path, &config).unwrap_or_else(fail);
             } else {
@@ -234,7 +266,15 @@ fn main() {
         }
         Some(Comm::Test {
             path,
+            no_format,
             formatter_options,
+            format,
+            filter,
+            fail_fast,
+            bless,
+            seed,
+            coverage,
+            coverage_format,
             args,
         }) => {
             let path = if let Some(path) = path {
@@ -248,26 +288,130 @@ fn main() {
                     }
                 }
             };
-            let config =
-                FormatConfig::from_source(formatter_options.format_config_source, Some(&path))
-                    .unwrap_or_else(fail);
-            format_file(&path, &config).unwrap_or_else(fail);
+            if !no_format {
+                let config =
+                    FormatConfig::from_source(formatter_options.format_config_source, Some(&path))
+                        .unwrap_or_else(fail);
+                format_file(&path, &config).unwrap_or_else(fail);
+            }
             let mut rt = Uiua::with_native_sys()
                 .with_file_path(&path)
-                .with_args(args);
+                .with_args(args)
+                .with_fail_fast(fail_fast)
+                .with_bless_snapshots(bless)
+                .with_property_seed(seed)
+                .with_coverage(coverage.is_some());
             let res = rt.compile_run(|comp| {
+                if coverage.is_some() {
+                    // Pre-evaluated code would not be counted
+                    comp.pre_eval_mode(PreEvalMode::Lazy);
+                }
                 comp.mode(RunMode::Test)
                     .print_diagnostics(true)
-                    .load_file(path)
+                    .doc_tests(true)
+                    .test_filter(filter)
+                    .load_file(&path)
             });
-            if let Err(e) = &res {
-                println!("{}", e.report());
+            match format {
+                TestFormat::Pretty => {
+                    if let Err(e) = &res {
+                        println!("{}", e.report());
+                    }
+                    rt.print_reports();
+                }
+                format => {
+                    // Errors that are not test failures still go to stderr
+                    if let Err(e) = &res {
+                        eprintln!("{}", e.report());
+                    }
+                    let cases = rt.take_test_cases();
+                    let report = if format == TestFormat::Json {
+                        json_report(&cases)
+                    } else {
+                        junit_report(&cases, &path.to_string_lossy())
+                    };
+                    print!("{report}");
+                }
+            }
+            if let Some((path, report)) = coverage.zip(rt.coverage()) {
+                if let Err(e) = fs::write(&path, report.report(coverage_format)) {
+                    eprintln!("Failed to write coverage to {}: {e}", path.display());
+                    exit(1);
+                }
             }
-            rt.print_reports();
             if res.is_err() {
                 exit(1);
             }
         }
+        Some(Comm::Bench {
+            path,
+            format,
+            filter,
+            save,
+            baseline,
+            threshold,
+            args,
+        }) => {
+            let path = if let Some(path) = path {
+                path
+            } else {
+                match working_file_path() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            };
+            let baseline = baseline.map(|path| {
+                fs::read_to_string(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|json| bench::parse_baseline(&json))
+                    .unwrap_or_else(|e| {
+                        eprintln!("Failed to read baseline {}: {e}", path.display());
+                        exit(1)
+                    })
+            });
+            let mut rt = Uiua::with_native_sys()
+                .with_file_path(&path)
+                .with_args(args)
+                .with_benchmarking(true);
+            let res = rt.compile_run(|comp| {
+                comp.mode(RunMode::Test)
+                    .print_diagnostics(true)
+                    .test_filter(filter)
+                    .load_file(&path)
+            });
+            if let Err(e) = &res {
+                eprintln!("{}", e.report());
+            }
+            let results = rt.take_bench_results();
+            let comparisons = baseline
+                .map(|baseline| bench::compare(&results, &baseline, threshold / 100.0))
+                .unwrap_or_default();
+            match format {
+                BenchFormat::Pretty => print!("{}", bench::pretty_report(&results, &comparisons)),
+                BenchFormat::Json => println!("{}", bench::json_report(&results)),
+            }
+            if let Some(path) = save {
+                if let Err(e) = fs::write(&path, bench::json_report(&results)) {
+                    eprintln!("Failed to write benchmarks to {}: {e}", path.display());
+                    exit(1);
+                }
+            }
+            let regressions = (comparisons.iter().flatten())
+                .filter(|comp| comp.regressed)
+                .count();
+            if regressions > 0 {
+                eprintln!(
+                    "{regressions} benchmark{} regressed by more than {threshold}%",
+                    if regressions == 1 { "" } else { "s" }
+                );
+            }
+            if res.is_err() || regressions > 0 {
+                exit(1);
+            }
+        }
         Some(Comm::Watch {
             no_format,
             no_color,
@@ -294,6 +438,29 @@ fn main() {
         }
         #[cfg(feature = "lsp")]
         Some(Comm::Lsp) => uiua::lsp::run_language_server(),
+        #[cfg(feature = "jupyter")]
+        Some(Comm::Jupyter {
+            connection_file,
+            install,
+        }) => {
+            if install {
+                match uiua::jupyter::install_kernel_spec() {
+                    Ok(dir) => println!("Installed Uiua kernel to {}", dir.display()),
+                    Err(e) => {
+                        eprintln!("Failed to install kernel: {e}");
+                        exit(1);
+                    }
+                }
+            } else if let Some(path) = connection_file {
+                if let Err(e) = uiua::jupyter::run_kernel(&path) {
+                    eprintln!("Kernel error: {e}");
+                    exit(1);
+                }
+            } else {
+                eprintln!("Expected a connection file or --install");
+                exit(1);
+            }
+        }
         Some(Comm::Repl {
             file,
             formatter_options,
@@ -396,7 +563,63 @@ fn main() {
             }
         }
         Some(Comm::Doc { name }) => doc(&name),
+        Some(Comm::Highlight { path, format }) => {
+            let code = fs::read_to_string(&path)
+                .map_err(|e| UiuaError::load(path.clone(), e))
+                .unwrap_or_else(fail);
+            print!(
+                "{}",
+                uiua::highlight::highlight_with_backend(&code, NativeSys, format)
+            );
+        }
+        Some(Comm::Docgen {
+            path,
+            format,
+            output,
+        }) => {
+            let path = if let Some(path) = path {
+                path
+            } else {
+                match working_file_path() {
+                    Ok(path) => path,
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        return;
+                    }
+                }
+            };
+            let pages = uiua::docgen::generate_docs(&path, format).unwrap_or_else(fail);
+            if let Err(e) = fs::create_dir_all(&output) {
+                eprintln!("Failed to create {}: {e}", output.display());
+                exit(1);
+            }
+            for page in &pages {
+                let page_path = output.join(&page.file_name);
+                if let Err(e) = fs::write(&page_path, &page.content) {
+                    eprintln!("Failed to write {}: {e}", page_path.display());
+                    exit(1);
+                }
+            }
+            println!(
+                "Generated {} page{} in {}",
+                pages.len(),
+                if pages.len() == 1 { "" } else { "s" },
+                output.display()
+            );
+        }
         Some(Comm::Check { path }) => check(path).unwrap_or_else(fail),
+        Some(Comm::Lint { path, rules }) => {
+            if rules {
+                for rule in LINT_RULES {
+                    let default = format!("{:?}", rule.default).to_lowercase();
+                    println!("{:<24}{:<7}{}", rule.name, default, rule.description);
+                }
+                return;
+            }
+            if !lint(path).unwrap_or_else(fail) {
+                exit(1);
+            }
+        }
         Some(Comm::Find { path, text, raw }) => find(path, text, raw).unwrap_or_else(fail),
         None => {
             #[cfg(feature = "window")]
@@ -787,8 +1010,62 @@ enum Comm {
     #[clap(about = "Format and test a file")]
     Test {
         path: Option<PathBuf>,
+        #[clap(long, help = "Don't format the file before running")]
+        no_format: bool,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
+        #[clap(
+            long,
+            default_value = "pretty",
+            help = "The report format (pretty, json, or junit)"
+        )]
+        format: TestFormat,
+        #[clap(long, help = "Only run test scopes whose names contain this pattern")]
+        filter: Option<String>,
+        #[clap(long, help = "Stop at the first test failure")]
+        fail_fast: bool,
+        #[clap(long, help = "Overwrite snapshots with the current values")]
+        bless: bool,
+        #[clap(long, help = "The seed to generate property test cases from")]
+        seed: Option<u64>,
+        #[clap(
+            long,
+            num_args = 0..=1,
+            require_equals = true,
+            default_missing_value = "lcov.info",
+            help = "Write a code coverage report to this file"
+        )]
+        coverage: Option<PathBuf>,
+        #[clap(
+            long,
+            default_value = "lcov",
+            help = "The coverage report format (lcov or cobertura)"
+        )]
+        coverage_format: CoverageFormat,
+        #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
+        args: Vec<String>,
+    },
+    #[clap(about = "Run the benchmarks in a file")]
+    Bench {
+        path: Option<PathBuf>,
+        #[clap(
+            long,
+            default_value = "pretty",
+            help = "The report format (pretty or json)"
+        )]
+        format: BenchFormat,
+        #[clap(long, help = "Only run test scopes whose names contain this pattern")]
+        filter: Option<String>,
+        #[clap(long, help = "Save the results as JSON to this file")]
+        save: Option<PathBuf>,
+        #[clap(long, help = "Compare the results against a file saved with --save")]
+        baseline: Option<PathBuf>,
+        #[clap(
+            long,
+            default_value_t = 10.0,
+            help = "The percent increase in mean time that counts as a regression"
+        )]
+        threshold: f64,
         #[clap(trailing_var_arg = true, help = "Arguments to pass to the program")]
         args: Vec<String>,
     },
@@ -819,19 +1096,68 @@ enum Comm {
         path: Option<PathBuf>,
         #[clap(flatten)]
         formatter_options: FormatterOptions,
-        #[clap(long, help = "Format lines read from stdin")]
-        io: bool,
+        #[clap(
+            long,
+            alias = "io",
+            help = "Format code read from stdin and print it to stdout"
+        )]
+        stdin: bool,
+        #[clap(
+            long,
+            help = "List files that would change and exit with an error if there are any, \
+                    without modifying them"
+        )]
+        check: bool,
+        #[clap(
+            long,
+            help = "Print a diff of the changes that would be made, without modifying files"
+        )]
+        diff: bool,
+        #[clap(
+            long,
+            help = "Render primitives by their names or ASCII tokens instead of their glyphs"
+        )]
+        ascii: bool,
     },
     #[clap(about = "Show the documentation for a function, modifier, or constant")]
     Doc {
         #[clap(help = "The name of the function, modifier, or constant")]
         name: String,
     },
+    #[clap(about = "Generate documentation for a module and the modules it imports")]
+    Docgen {
+        path: Option<PathBuf>,
+        #[clap(
+            long,
+            default_value_t = uiua::docgen::DocFormat::Html,
+            help = "The output format (html or markdown)"
+        )]
+        format: uiua::docgen::DocFormat,
+        #[clap(long, short, default_value = "docs", help = "The output directory")]
+        output: PathBuf,
+    },
+    #[clap(about = "Export a Uiua file as syntax-highlighted HTML, ANSI text, or SVG")]
+    Highlight {
+        path: PathBuf,
+        #[clap(
+            long,
+            default_value_t = uiua::highlight::HighlightFormat::Ansi,
+            help = "The output format (ansi, html, or svg)"
+        )]
+        format: uiua::highlight::HighlightFormat,
+    },
     #[clap(about = "Check that Uiua files compile")]
     Check {
         #[clap(help = "The path to a file or directory to check")]
         path: Option<PathBuf>,
     },
+    #[clap(about = "Check Uiua files for lint rule violations")]
+    Lint {
+        #[clap(help = "The path to a file or directory to lint")]
+        path: Option<PathBuf>,
+        #[clap(long, help = "List the lint rules and their default levels")]
+        rules: bool,
+    },
     #[clap(about = "Find some Uiua code that matches the given unformatted text")]
     Find {
         text: String,
@@ -879,6 +1205,14 @@ enum Comm {
     #[cfg(feature = "lsp")]
     #[clap(about = "Run the Language Server")]
     Lsp,
+    #[cfg(feature = "jupyter")]
+    #[clap(about = "Run the Jupyter kernel")]
+    Jupyter {
+        #[clap(help = "The connection file provided by Jupyter")]
+        connection_file: Option<PathBuf>,
+        #[clap(long, help = "Install the kernel spec so Jupyter can find the kernel")]
+        install: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -1085,6 +1419,25 @@ fn format_single_file(path: PathBuf, config: &FormatConfig) -> Result<(), UiuaEr
     Ok(())
 }
 
+/// Print the changes formatting would make to some code, returning whether there are any
+fn report_format_changes(
+    name: &str,
+    code: &str,
+    formatted: &FormatOutput,
+    check: bool,
+    diff: bool,
+) -> bool {
+    if formatted.output == code {
+        return false;
+    }
+    if diff {
+        print!("{}", formatted.diff(code, name));
+    } else if check {
+        println!("{name}");
+    }
+    true
+}
+
 fn format_multi_files(config: &FormatConfig) -> Result<(), UiuaError> {
     for path in uiua_files_in(".".as_ref())? {
         format_file(path, config)?;
@@ -1353,6 +1706,40 @@ fn update_modules(modules: &[PathBuf]) -> io::Result<()> {
     Ok(())
 }
 
+/// Lint files and return whether no rule was denied
+fn lint(path: Option<PathBuf>) -> UiuaResult<bool> {
+    let mut errors = 0;
+    let mut warnings = 0;
+    for path in uiua_files(path.as_deref())? {
+        let config = LintConfig::find(&path).unwrap_or_else(|e| {
+            eprintln!("Failed to load lint configuration: {e}");
+            exit(1)
+        });
+        for lint in lint_file(&path, &config, NativeSys)? {
+            if lint.level == LintLevel::Deny {
+                errors += 1;
+            } else {
+                warnings += 1;
+            }
+            println!("{}", lint.report());
+        }
+    }
+    let plural = |n: usize| if n == 1 { "" } else { "s" };
+    let message = format!(
+        "{errors} error{}, {warnings} warning{}",
+        plural(errors),
+        plural(warnings)
+    );
+    if errors > 0 {
+        println!("{}", message.bright_red());
+    } else if warnings > 0 {
+        println!("{}", message.bright_yellow());
+    } else {
+        println!("{}", message.bright_green());
+    }
+    Ok(errors == 0)
+}
+
 fn check(path: Option<PathBuf>) -> UiuaResult {
     let paths = uiua_files(path.as_deref())?;
     let path_count = paths.len();
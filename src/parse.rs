@@ -123,6 +123,23 @@ pub fn parse(
     src: impl IntoInputSrc,
     inputs: &mut Inputs,
 ) -> (Vec<Item>, Vec<Sp<ParseError>>, Vec<Diagnostic>) {
+    let (items, errors, diagnostics, _) = parse_with_tokens(input, src, inputs);
+    (items, errors, diagnostics)
+}
+
+type ParseOutput = (
+    Vec<Item>,
+    Vec<Sp<ParseError>>,
+    Vec<Diagnostic>,
+    Vec<Sp<crate::lex::Token>>,
+);
+
+/// Parse Uiua code into an AST, also returning the lexed tokens
+pub(crate) fn parse_with_tokens(
+    input: &str,
+    src: impl IntoInputSrc,
+    inputs: &mut Inputs,
+) -> ParseOutput {
     let (tokens, lex_errors, src) = lex(input, src, inputs);
     fn parse(
         input: &str,
@@ -130,7 +147,7 @@ pub fn parse(
         tokens: Vec<Sp<crate::lex::Token>>,
         lex_errors: Vec<Sp<LexError>>,
         src: InputSrc,
-    ) -> (Vec<Item>, Vec<Sp<ParseError>>, Vec<Diagnostic>) {
+    ) -> ParseOutput {
         let mut errors: Vec<_> = lex_errors
             .into_iter()
             .map(|e| e.map(ParseError::Lex))
@@ -182,15 +199,18 @@ pub fn parse(
                     DiagnosticKind::Warning => WARNING_MAX_WIDTH,
                     DiagnosticKind::Info => unreachable!(),
                 };
-                diagnostics.push(Diagnostic::new(
-                    format!(
-                        "Split this into multiple lines \
+                diagnostics.push(
+                    Diagnostic::new(
+                        format!(
+                            "Split this into multiple lines \
                         (heuristic: {heuristic}/{max}) {face}"
-                    ),
-                    span,
-                    kind,
-                    inputs.clone(),
-                ));
+                        ),
+                        span,
+                        kind,
+                        inputs.clone(),
+                    )
+                    .with_lint("long-line"),
+                );
             }
         }
 
@@ -206,14 +226,6 @@ pub fn parse(
             depth: 0,
         };
         let items = parser.items(false);
-        if parser.errors.is_empty() && parser.index < parser.tokens.len() {
-            parser.errors.push(
-                parser
-                    .tokens
-                    .remove(parser.index)
-                    .map(ParseError::Unexpected),
-            );
-        }
         let mut errors = parser.errors;
         if let Some(error) = errors
             .iter()
@@ -222,7 +234,7 @@ pub fn parse(
             let error = error.clone();
             errors = vec![error];
         }
-        (items, errors, parser.diagnostics)
+        (items, errors, parser.diagnostics, parser.tokens)
     }
     parse(input, inputs, tokens, lex_errors, src)
 }
@@ -292,7 +304,14 @@ impl<'i> Parser<'i> {
                 Some(item) => items.push(item),
                 None => {
                     if self.exact(Newline).is_none() {
-                        break;
+                        // Resynchronize at the next line
+                        match self.unparsed_line(in_scope) {
+                            Some(span) => {
+                                items.push(Item::Error(span));
+                                continue;
+                            }
+                            None => break,
+                        }
                     }
                     self.spaces();
                     let mut extra_newlines = false;
@@ -308,6 +327,50 @@ impl<'i> Parser<'i> {
         }
         items
     }
+    /// Skip the rest of a line that could not be parsed
+    ///
+    /// Returns [`None`] at the end of the input or of the current scope
+    fn unparsed_line(&mut self, in_scope: bool) -> Option<CodeSpan> {
+        let start = self.index;
+        let token = self.tokens.get(start)?.clone();
+        if in_scope && self.module_close().is_some() {
+            self.index = start;
+            return None;
+        }
+        let mut span = token.span.clone();
+        if !(self.errors.last()).is_some_and(|e| e.span == span) {
+            self.errors
+                .push(span.clone().sp(ParseError::Unexpected(token.value)));
+        }
+        while let Some(token) = self.tokens.get(self.index) {
+            if token.value == Newline {
+                break;
+            }
+            span.merge_with(token.span.clone());
+            self.index += 1;
+        }
+        Some(span)
+    }
+    /// Check whether a new top-level item starts here
+    ///
+    /// This is used to recover from unclosed brackets
+    fn at_item_start(&mut self) -> bool {
+        let reset = self.index;
+        let found = self.import_init().is_some()
+            || self.ident().is_some() && {
+                self.spaces();
+                [
+                    LeftArrow,
+                    LeftArrowTilde,
+                    LeftStrokeArrow,
+                    EqualTilde.into(),
+                ]
+                .into_iter()
+                .any(|token| self.exact(token).is_some())
+            };
+        self.index = reset;
+        found
+    }
     fn item(&mut self, in_scope: bool) -> Option<Item> {
         if self.too_deep() {
             return None;
@@ -847,6 +910,7 @@ impl<'i> Parser<'i> {
         let mut lines = Vec::new();
         while self.spaces().is_some() {}
         let mut newlines: usize = 0;
+        let mut line_end = self.index;
         loop {
             let curr = self.index;
             if check_for_bindings
@@ -857,9 +921,19 @@ impl<'i> Parser<'i> {
                 self.index = curr;
                 break;
             }
+            if !check_for_bindings && newlines > 0 && self.at_item_start() {
+                // The enclosing bracket is unclosed, so leave the newline for the outer item
+                self.index = line_end;
+                if newlines > 1 {
+                    lines.pop();
+                }
+                newlines = 0;
+                break;
+            }
             if let Some(words) = self.words() {
                 newlines = 0;
                 lines.push(words);
+                line_end = self.index;
                 while self.exact(Newline).is_some() {
                     newlines += 1;
                     self.spaces();
@@ -1660,3 +1734,36 @@ fn arr_is_normal_di(arr: &Arr) -> bool {
     });
     is_di
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, PreEvalMode};
+
+    #[test]
+    fn parse_recovery() {
+        let code = "\
+F ← (+ 1
+G ← 5
+H ← ) 6
+X ← [1 2
+Y ← ×2 G";
+        let (items, errors, _) = parse(code, (), &mut Inputs::default());
+        assert_eq!(errors.len(), 3);
+        assert!(items.iter().any(|item| matches!(item, Item::Error(_))));
+        let bindings = (items.iter())
+            .filter(|item| matches!(item, Item::Binding(_)))
+            .count();
+        assert_eq!(bindings, 5);
+        // Well-formed items are still compiled for tooling
+        let mut comp = Compiler::new();
+        comp.pre_eval_mode(PreEvalMode::Lsp);
+        assert!(comp.load_str(code).is_err());
+        let asm = comp.assembly();
+        assert!((asm.bindings.iter()).any(|b| b.span.as_str(&asm.inputs, |s| s == "Y")));
+        // But not otherwise
+        let mut comp = Compiler::new();
+        assert!(comp.load_str(code).is_err());
+        assert!(comp.assembly().bindings.is_empty());
+    }
+}
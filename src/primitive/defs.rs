@@ -3311,6 +3311,36 @@ primitive!(
     ///
     /// [astar] is designed to be maximally flexible, so it can be used with graphs or grids or any other structure.
     ((2)[3], Astar, Misc, "astar"),
+    /// Check that a property holds for randomly generated inputs
+    ///
+    /// Expects a number of cases to check. The first function generates the inputs, and the second function is the property, which must return `1` if it holds.
+    /// If the first function takes no arguments, it returns a spec for each argument of the property.
+    /// The leading axes of a spec give the maximum shape of the generated array, and the last axis gives the possible elements at each position.
+    /// ex: # Experimental!
+    ///   : property(↯5⇡10|≍⇌⇌.) 100
+    ///   : property(↯5"abc"|≍⊸(°⇌⇌)) 100
+    /// If the first function takes an argument, it is called with a random seed and returns the property's arguments itself. This works well with [gen].
+    /// ex: # Experimental!
+    ///   : property(gen 5|≍⊃⍆(⍆⍆)) 20
+    /// When a property fails, its inputs are shrunk toward a minimal counterexample by shortening axes and moving numbers toward zero.
+    /// The failure is reported along with the seed that the cases were generated from. Running `uiua test --seed <seed>` generates the same cases again.
+    /// ex! # Experimental!
+    ///   : property(↯10⇡100|<50/+) 100
+    ///
+    /// Like an [assert]ion, [property] is reported as a test when running `uiua test`.
+    (1(0)[2], Property, Misc, "property", Impure),
+    /// Measure how long a function takes to run
+    ///
+    /// Expects a name for the benchmark, followed by the function's arguments.
+    /// Normally, [bench] simply calls the function once and discards its outputs.
+    /// ex: # Experimental!
+    ///   : bench(/+⇡) "sum" 1000
+    /// When running `uiua bench`, the function is instead run repeatedly and timed.
+    /// After a short warmup, the number of iterations is chosen based on how long the function takes.
+    /// The mean, median, and standard deviation of the time are reported, along with the number of outlier samples.
+    ///
+    /// `uiua bench --save` stores the results as JSON, and `uiua bench --baseline` reports regressions against stored results.
+    ((0)[1], Bench, Misc, "bench", Impure),
     /// Calculate the derivative of a mathematical expression
     ///
     /// Basic polynomials are supported, along with [sine] and [logarithm].
@@ -3756,6 +3786,10 @@ impl_primitive!(
     (2(1), ValidateType),
     (2(0), ValidateTypeConsume),
     (2(0), TestAssert, Impure),
+    /// `with` `match` that records mismatches for test reports
+    (2(2), TestMatch, Impure),
+    (0(0), BeginTest, Impure),
+    (0(0), EndTest, Impure),
     /// Validate that a non-boxed variant field has a valid type and rank
     (1, ValidateNonBoxedVariant),
     (2(1), ValidateVariant),
@@ -32,6 +32,7 @@ use crate::{
     encode,
     lex::{AsciiToken, SUBSCRIPT_DIGITS},
     sys::*,
+    testing::{value_diff, TestScopeRun},
     value::*,
     FunctionId, Ops, Shape, Signature, Uiua, UiuaErrorKind, UiuaResult,
 };
@@ -320,6 +321,9 @@ impl fmt::Display for ImplPrimitive {
             ValidateType => write!(f, "{Un}…{Type}{Dup}"),
             ValidateTypeConsume => write!(f, "{Un}…{Type}"),
             TestAssert => write!(f, "{Assert}"),
+            TestMatch => write!(f, "{With}{Match}"),
+            BeginTest => write!(f, "<begin test>"),
+            EndTest => write!(f, "<end test>"),
             ValidateNonBoxedVariant => write!(f, "|…[…]"),
             ValidateVariant => write!(f, "|…°[…]"),
             TagVariant => write!(f, "<tag variant>"),
@@ -527,7 +531,7 @@ impl Primitive {
             (Reach | Off | Backward | Above | Around)
                 | (Tuples | Stencil)
                 | (Or | Base | Fft | Case | Layout | Binary)
-                | Astar
+                | (Astar | Property | Bench)
                 | (Derivative | Integral)
                 | Sys(Ffi | MemCopy | MemFree | TlsListen)
                 | (Stringify | Quote | Sig)
@@ -869,6 +873,7 @@ impl Primitive {
             Primitive::Assert => {
                 let msg = env.pop(1)?;
                 let cond = env.pop(2)?;
+                env.rt.test_mismatch = None;
                 if !cond.as_nat(env, "").is_ok_and(|n| n == 1) {
                     return Err(UiuaErrorKind::Throw(
                         msg.into(),
@@ -1102,6 +1107,8 @@ impl Primitive {
             }
             Primitive::Dump => dump(ops, env, false)?,
             Primitive::Astar => algorithm::astar(ops, env)?,
+            Primitive::Property => crate::testing::property(ops, env)?,
+            Primitive::Bench => crate::bench::bench(ops, env)?,
             Primitive::Memo => {
                 let [f] = get_ops(ops, env)?;
                 let mut args = Vec::with_capacity(f.sig.args);
@@ -1604,16 +1611,43 @@ impl ImplPrimitive {
             ImplPrimitive::TestAssert => {
                 let msg = env.pop(1)?;
                 let cond = env.pop(2)?;
+                let mismatch = env.rt.test_mismatch.take();
                 let mut res = Ok(());
                 if !cond.as_nat(env, "").is_ok_and(|n| n == 1) {
-                    res = Err(UiuaErrorKind::Throw(
+                    res = Err(crate::UiuaError::from(UiuaErrorKind::Throw(
                         msg.into(),
                         env.span().clone(),
                         env.asm.inputs.clone().into(),
-                    )
-                    .into());
+                    ))
+                    .with_info(mismatch.map(|diff| (diff, None))));
+                }
+                env.record_test_result(res)?;
+            }
+            ImplPrimitive::TestMatch => {
+                let expected = env.pop(1)?;
+                let actual = env.pop(2)?;
+                let diff = value_diff(&expected, &actual);
+                env.push(diff.is_none());
+                env.push(actual);
+                env.rt.test_mismatch = diff;
+            }
+            ImplPrimitive::BeginTest => {
+                let now = env.rt.backend.now();
+                env.rt.test_scopes.push(TestScopeRun {
+                    span: env.span_index(),
+                    start: now,
+                    duration: None,
+                });
+                env.rt.test_clock = now;
+                env.rt.test_mismatch = None;
+            }
+            ImplPrimitive::EndTest => {
+                let now = env.rt.backend.now();
+                if let Some(scope) = env.rt.test_scopes.last_mut() {
+                    scope.duration = Some(now - scope.start);
                 }
-                env.rt.test_results.push(res);
+                env.rt.test_clock = now;
+                env.rt.test_mismatch = None;
             }
             ImplPrimitive::ValidateNonBoxedVariant => {
                 let val = env.pop(1)?;
@@ -18,9 +18,11 @@ use thread_local::ThreadLocal;
 
 use crate::{
     algorithm::{self, validate_size_impl},
+    bench::BenchResult,
     fill::Fill,
     invert::match_format_pattern,
     lex::Span,
+    testing::{collect_test_cases, CoverageData, TestAssertResult, TestCase, TestScopeRun},
     Array, ArrayLen, Assembly, BindingKind, Boxed, CodeSpan, Compiler, Function, FunctionId, Ident,
     Inputs, IntoSysBackend, LocalName, Node, Primitive, Report, SafeSys, SigNode, Signature,
     SysBackend, SysOp, TraceFrame, UiuaError, UiuaErrorKind, UiuaResult, Value, VERSION,
@@ -81,8 +83,30 @@ pub(crate) struct Runtime {
     pub(crate) output_comments: HashMap<usize, Vec<Vec<Value>>>,
     /// Memoized values
     pub(crate) memo: Arc<ThreadLocal<RefCell<MemoMap>>>,
-    /// The results of tests
-    pub(crate) test_results: Vec<UiuaResult>,
+    /// The results of test assertions
+    pub(crate) test_results: Vec<TestAssertResult>,
+    /// The test scopes that have started running
+    pub(crate) test_scopes: Vec<TestScopeRun>,
+    /// The time of the last test event, used to time assertions
+    pub(crate) test_clock: f64,
+    /// Whether to stop at the first test failure
+    pub(crate) fail_fast: bool,
+    /// Whether tests were stopped early because of a failure
+    pub(crate) tests_stopped: bool,
+    /// Whether to overwrite snapshots instead of comparing against them
+    pub(crate) bless_snapshots: bool,
+    /// The seed that property tests generate their cases from, if fixed
+    pub(crate) property_seed: Option<u64>,
+    /// A description of the last mismatch found by a tested match
+    pub(crate) test_mismatch: Option<String>,
+    /// Test cases from finished runs
+    test_cases: Vec<TestCase>,
+    /// Execution counts for coverage, if enabled
+    pub(crate) coverage: Option<CoverageData>,
+    /// Whether to measure benchmarks instead of just running them
+    pub(crate) benchmarking: bool,
+    /// The results of benchmarks
+    pub(crate) bench_results: Vec<BenchResult>,
     /// Reports to print
     pub(crate) reports: Vec<Report>,
 }
@@ -212,6 +236,17 @@ impl Default for Runtime {
             memo: Arc::new(ThreadLocal::new()),
             unevaluated_constants: HashMap::new(),
             test_results: Vec::new(),
+            test_scopes: Vec::new(),
+            test_clock: 0.0,
+            fail_fast: false,
+            tests_stopped: false,
+            bless_snapshots: false,
+            property_seed: None,
+            test_mismatch: None,
+            test_cases: Vec::new(),
+            coverage: None,
+            benchmarking: false,
+            bench_results: Vec::new(),
             reports: Vec::new(),
         }
     }
@@ -261,6 +296,14 @@ impl Uiua {
     pub fn take_reports(&mut self) -> Vec<Report> {
         take(&mut self.rt.reports)
     }
+    /// Take the test cases from finished runs
+    pub fn take_test_cases(&mut self) -> Vec<TestCase> {
+        take(&mut self.rt.test_cases)
+    }
+    /// Take the results of benchmarks
+    pub fn take_bench_results(&mut self) -> Vec<BenchResult> {
+        take(&mut self.rt.bench_results)
+    }
     /// Print all pending reports
     pub fn print_reports(&mut self) {
         for report in self.take_reports() {
@@ -286,6 +329,35 @@ impl Uiua {
         self.rt.execution_limit = limit.map(|limit| limit.as_secs_f64());
         self
     }
+    /// Set whether to stop running at the first failed test assertion
+    pub fn with_fail_fast(mut self, fail_fast: bool) -> Self {
+        self.rt.fail_fast = fail_fast;
+        self
+    }
+    /// Set whether to overwrite snapshots instead of comparing against them
+    pub fn with_bless_snapshots(mut self, bless: bool) -> Self {
+        self.rt.bless_snapshots = bless;
+        self
+    }
+    /// Set the seed that property tests generate their cases from
+    ///
+    /// If this is not set, a random seed is used for each property.
+    pub fn with_property_seed(mut self, seed: Option<u64>) -> Self {
+        self.rt.property_seed = seed;
+        self
+    }
+    /// Set whether to measure benchmarks instead of just running them
+    pub fn with_benchmarking(mut self, benchmarking: bool) -> Self {
+        self.rt.benchmarking = benchmarking;
+        self
+    }
+    /// Set whether to record which code is executed
+    ///
+    /// The results can be retrieved with [`Uiua::coverage`]
+    pub fn with_coverage(mut self, coverage: bool) -> Self {
+        self.rt.coverage = coverage.then(CoverageData::default);
+        self
+    }
     /// Set the recursion limit
     ///
     /// Default is 100 for release builds and 20 for debug builds
@@ -363,9 +435,24 @@ impl Uiua {
         fn run_asm(env: &mut Uiua, asm: Assembly) -> UiuaResult {
             env.asm = asm;
             env.rt.execution_start = env.rt.backend.now();
+            env.rt.test_clock = env.rt.execution_start;
             let mut res = env
                 .catching_crash(|env| env.exec(env.asm.root.clone()))
                 .unwrap_or_else(Err);
+            if env.rt.tests_stopped {
+                env.rt.tests_stopped = false;
+                if res
+                    .as_ref()
+                    .is_err_and(|e| matches!(e.kind, UiuaErrorKind::Interrupted))
+                {
+                    res = Ok(());
+                }
+            }
+            if env.asm.test_assert_count > 0 || !env.rt.test_scopes.is_empty() {
+                let cases = collect_test_cases(env, res.as_ref().err());
+                env.rt.test_cases.extend(cases);
+                env.rt.test_scopes.clear();
+            }
             let mut push_error = |te: UiuaError| match &mut res {
                 Ok(()) => res = Err(te),
                 Err(e) => e.multi.push(te),
@@ -375,7 +462,7 @@ impl Uiua {
                 let not_run = env.asm.test_assert_count.saturating_sub(total_run);
                 let mut successes = 0;
                 for res in env.rt.test_results.drain(..) {
-                    match res {
+                    match res.result {
                         Ok(()) => successes += 1,
                         Err(e) => push_error(e),
                     }
@@ -388,6 +475,13 @@ impl Uiua {
                     execution_limit: env.rt.execution_limit,
                     time_instrs: env.rt.time_instrs,
                     output_comments: take(&mut env.rt.output_comments),
+                    fail_fast: env.rt.fail_fast,
+                    bless_snapshots: env.rt.bless_snapshots,
+                    property_seed: env.rt.property_seed,
+                    test_cases: take(&mut env.rt.test_cases),
+                    coverage: take(&mut env.rt.coverage),
+                    benchmarking: env.rt.benchmarking,
+                    bench_results: take(&mut env.rt.bench_results),
                     reports: take(&mut env.rt.reports),
                     ..Runtime::default()
                 };
@@ -468,6 +562,11 @@ impl Uiua {
             formatted_node = format!("{node:?}");
             self.rt.last_time = self.rt.backend.now();
         }
+        if let Some(coverage) = &mut self.rt.coverage {
+            if let Some(span) = node.span().filter(|_| !matches!(node, Node::Run(_))) {
+                *coverage.spans.entry(span).or_default() += 1;
+            }
+        }
         let res = match node {
             // Pause execution timer during &sc
             Node::Prim(prim @ Primitive::Sys(SysOp::ScanLine), span) => {
@@ -899,6 +998,13 @@ impl Uiua {
         }
         Ok(())
     }
+    /// Record that a branch of the current switch was taken
+    pub(crate) fn record_branch(&mut self, branch: usize) {
+        let span = self.span_index();
+        if let Some(coverage) = &mut self.rt.coverage {
+            *coverage.branches.entry((span, branch)).or_default() += 1;
+        }
+    }
     pub(crate) fn span_index(&self) -> usize {
         self.rt.call_stack.last().map_or(0, |frame| {
             (frame.spans.last())
@@ -1433,6 +1539,17 @@ impl Uiua {
                 memo: self.rt.memo.clone(),
                 unevaluated_constants: HashMap::new(),
                 test_results: Vec::new(),
+                test_scopes: Vec::new(),
+                test_clock: 0.0,
+                fail_fast: self.rt.fail_fast,
+                tests_stopped: false,
+                bless_snapshots: self.rt.bless_snapshots,
+                property_seed: self.rt.property_seed,
+                test_mismatch: None,
+                test_cases: Vec::new(),
+                coverage: None,
+                benchmarking: false,
+                bench_results: Vec::new(),
                 reports: Vec::new(),
                 thread,
             },
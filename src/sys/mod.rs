@@ -275,6 +275,15 @@ sys_op! {
     /// On the web, this example will hang for 1 second.
     /// ex: ⚂ &sl 1
     (1(0), Sleep, Misc, "&sl", "sleep", Mutating),
+    /// Compare a value against a stored snapshot
+    ///
+    /// Expects a snapshot name and a value.
+    /// Snapshots are stored as `<name>.uasnap` files in a `snapshots` directory next to the file that takes them.
+    /// The stored value is exact, so any array, including images and audio, can be snapshotted.
+    ///
+    /// A mismatch is reported like a failed test assertion, along with a description of how the values differ.
+    /// Snapshots are created or updated by running `uiua test --bless`.
+    (2(0), Snapshot, Misc, "&snap", "snapshot", Mutating),
     /// Read characters formed by at most n bytes from a stream
     ///
     /// Expects a count and a stream handle.
@@ -1174,6 +1183,13 @@ impl SysOp {
                 let (width, height) = env.rt.backend.term_size().map_err(|e| env.error(e))?;
                 env.push(cowslice![height as f64, width as f64])
             }
+            SysOp::Snapshot => {
+                let name = env
+                    .pop(1)?
+                    .as_string(env, "Snapshot name must be a string")?;
+                let value = env.pop(2)?;
+                env.snapshot(&name, value)?;
+            }
             SysOp::Exit => {
                 let status = env.pop(1)?.as_int(env, "Status must be an integer")? as i32;
                 (env.rt.backend).exit(status).map_err(|e| env.error(e))?;
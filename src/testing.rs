@@ -0,0 +1,1236 @@
+//! Test results, code coverage, and machine-readable test reports
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Write,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use ecow::EcoVec;
+use rand::prelude::*;
+use serde::Serialize;
+
+use crate::{
+    algorithm::get_ops, primitive::RNG, val_as_arr, Array, ArrayValue, BindingKind, CodeSpan,
+    InputSrc, Node, Ops, Shape, SigNode, Span, Uiua, UiuaError, UiuaErrorKind, UiuaResult, Value,
+};
+
+/// The directory that snapshots are stored in
+pub const SNAPSHOT_DIR: &str = "snapshots";
+
+/// The format of a test report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TestFormat {
+    /// Human-readable output
+    #[default]
+    Pretty,
+    /// A JSON object
+    Json,
+    /// JUnit XML
+    Junit,
+}
+
+impl FromStr for TestFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "pretty" => Ok(TestFormat::Pretty),
+            "json" => Ok(TestFormat::Json),
+            "junit" => Ok(TestFormat::Junit),
+            _ => Err(format!("unknown test format `{s}`")),
+        }
+    }
+}
+
+/// Whether a test case is a whole test scope or a single assertion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TestCaseKind {
+    /// A test scope
+    Scope,
+    /// An assertion
+    Assertion,
+}
+
+/// A test case from a test run
+#[derive(Debug, Clone, Serialize)]
+pub struct TestCase {
+    /// The name of the test case
+    pub name: String,
+    /// The kind of test case
+    pub kind: TestCaseKind,
+    /// The name of the test scope that contains the case
+    ///
+    /// This is `None` for scopes and for assertions outside of test scopes
+    pub scope: Option<String>,
+    /// The file that contains the case
+    pub file: Option<String>,
+    /// The line of the case
+    pub line: usize,
+    /// The column of the case
+    pub column: usize,
+    /// How long the case took to run in seconds
+    pub duration: f64,
+    /// The failure message if the case failed
+    pub failure: Option<String>,
+}
+
+impl TestCase {
+    /// Whether the case passed
+    pub fn passed(&self) -> bool {
+        self.failure.is_none()
+    }
+}
+
+/// The result of a test assertion
+#[derive(Clone)]
+pub(crate) struct TestAssertResult {
+    /// The span index of the assertion
+    pub span: usize,
+    /// The index of the test scope run that contains the assertion
+    pub scope: Option<usize>,
+    /// The time since the previous assertion or the start of the scope
+    pub duration: f64,
+    /// The result
+    pub result: UiuaResult,
+}
+
+/// A run of a test scope
+#[derive(Clone)]
+pub(crate) struct TestScopeRun {
+    /// The span index of the test scope
+    pub span: usize,
+    /// The time the scope started
+    pub start: f64,
+    /// How long the scope took, if it finished
+    pub duration: Option<f64>,
+}
+
+impl Uiua {
+    /// Record the result of a test assertion at the current span
+    ///
+    /// Returns an error if the result is a failure and tests should stop
+    pub(crate) fn record_test_result(&mut self, result: UiuaResult) -> UiuaResult {
+        let now = self.rt.backend.now();
+        let scope = (self.rt.test_scopes.last())
+            .filter(|scope| scope.duration.is_none())
+            .map(|_| self.rt.test_scopes.len() - 1);
+        let failed = result.is_err();
+        self.rt.test_results.push(TestAssertResult {
+            span: self.span_index(),
+            scope,
+            duration: now - self.rt.test_clock,
+            result,
+        });
+        self.rt.test_clock = now;
+        if failed && self.rt.fail_fast {
+            self.rt.tests_stopped = true;
+            return Err(UiuaErrorKind::Interrupted.into());
+        }
+        Ok(())
+    }
+}
+
+impl Uiua {
+    /// Compare a value against a stored snapshot and record the result
+    ///
+    /// When blessing, the snapshot is overwritten instead
+    pub(crate) fn snapshot(&mut self, name: &str, value: Value) -> UiuaResult {
+        if name.is_empty()
+            || (name.split(['/', '\\'])).any(|part| part.is_empty() || part == "." || part == "..")
+        {
+            return Err(self.error(format!("Invalid snapshot name {name:?}")));
+        }
+        // Snapshots live next to the file that takes them
+        let base = match self.span() {
+            Span::Code(span) => match &span.src {
+                InputSrc::File(file) => file.parent().map(Path::to_path_buf),
+                _ => None,
+            },
+            Span::Builtin => None,
+        };
+        let path = (base.unwrap_or_default())
+            .join(SNAPSHOT_DIR)
+            .join(format!("{name}.uasnap"));
+        if self.rt.bless_snapshots {
+            let bytes = value.to_binary(self)?;
+            if let Some(dir) = path.parent() {
+                (self.rt.backend.make_dir(dir)).map_err(|e| self.error(e))?;
+            }
+            (self.rt.backend)
+                .file_write_all(&path, &bytes)
+                .map_err(|e| self.error(e))?;
+            return self.record_test_result(Ok(()));
+        }
+        let failure = if !self.rt.backend.file_exists(&path.to_string_lossy()) {
+            Some(format!(
+                "Snapshot {name:?} does not exist. Run `uiua test --bless` to create it."
+            ))
+        } else {
+            let bytes = (self.rt.backend.file_read_all(&path)).map_err(|e| self.error(e))?;
+            let expected = Value::from_binary(&bytes, self)?;
+            value_diff(&expected, &value)
+                .map(|diff| format!("Snapshot {name:?} does not match\n{diff}"))
+        };
+        let result = match failure {
+            Some(message) => Err(self.error(message)),
+            None => Ok(()),
+        };
+        self.record_test_result(result)
+    }
+}
+
+/// The maximum number of shrinking attempts for a failing property
+const MAX_SHRINK_STEPS: usize = 1000;
+
+/// Check that a property holds for randomly generated inputs
+///
+/// The first function either returns one spec per argument of the property
+/// or takes a seed and returns the arguments themselves.
+pub(crate) fn property(ops: Ops, env: &mut Uiua) -> UiuaResult {
+    let [gen, prop] = get_ops(ops, env)?;
+    let cases = env
+        .pop("case count")?
+        .as_nat(env, "Case count must be a natural number")?;
+    if prop.sig.outputs != 1 {
+        return Err(env.error(format!(
+            "A property must return a single boolean, \
+            but its signature is {}",
+            prop.sig
+        )));
+    }
+    if gen.sig.args > 1 || gen.sig.outputs != prop.sig.args {
+        return Err(env.error(format!(
+            "A property generator must take 0 or 1 arguments \
+            and return the property's {} arguments, but its signature is {}",
+            prop.sig.args, gen.sig
+        )));
+    }
+    let specs = if gen.sig.args == 0 {
+        env.exec(gen.clone())?;
+        let specs = (0..gen.sig.outputs)
+            .map(|i| env.pop(i + 1))
+            .collect::<UiuaResult<Vec<_>>>()?;
+        if let Some(spec) = specs
+            .iter()
+            .find(|spec| spec.rank() == 0 || spec.shape().ends_with(&[0]))
+        {
+            return Err(env.error(format!(
+                "A property spec must have a non-empty last axis, \
+                but its shape is {}",
+                spec.shape()
+            )));
+        }
+        Some(specs)
+    } else {
+        None
+    };
+    // Every case is derived from a single seed so that a failure can be reproduced
+    let seed = (env.rt.property_seed)
+        .unwrap_or_else(|| RNG.with_borrow_mut(|rng| rng.gen_range(0..1u64 << 32)));
+    let mut seeds = SmallRng::seed_from_u64(seed);
+    for case in 0..cases {
+        let case_seed = seeds.gen_range(0..1u64 << 32);
+        // Generate the arguments
+        let args = if let Some(specs) = &specs {
+            let mut rng = SmallRng::seed_from_u64(case_seed);
+            (specs.iter())
+                .map(|spec| val_as_arr!(spec, |arr| generate(arr, &mut rng).into()))
+                .collect()
+        } else {
+            env.push(case_seed as f64);
+            env.exec(gen.clone())?;
+            (0..gen.sig.outputs)
+                .map(|i| env.pop(i + 1))
+                .collect::<UiuaResult<Vec<_>>>()?
+        };
+        if check_property(&prop, &args, env)? {
+            continue;
+        }
+        // Shrink the counterexample
+        let mut args = args;
+        let mut steps = 0;
+        'shrink: while steps < MAX_SHRINK_STEPS {
+            for i in 0..args.len() {
+                for candidate in shrink_candidates(&args[i]) {
+                    steps += 1;
+                    let mut shrunk = args.clone();
+                    shrunk[i] = candidate;
+                    if !check_property(&prop, &shrunk, env)? {
+                        args = shrunk;
+                        continue 'shrink;
+                    }
+                    if steps >= MAX_SHRINK_STEPS {
+                        break 'shrink;
+                    }
+                }
+            }
+            break;
+        }
+        let mut message = format!(
+            "Property failed on case {} of {cases} with seed {seed}\n\
+            Run `uiua test --seed {seed}` to reproduce it\nCounterexample:",
+            case + 1
+        );
+        for arg in &args {
+            for line in arg.show().lines() {
+                message.push_str("\n  ");
+                message.push_str(line);
+            }
+        }
+        let error = env.error(message);
+        return env.record_test_result(Err(error));
+    }
+    env.record_test_result(Ok(()))
+}
+
+/// Run a property on some arguments and return whether it held
+fn check_property(prop: &SigNode, args: &[Value], env: &mut Uiua) -> UiuaResult<bool> {
+    let height = env.stack_height();
+    for arg in args.iter().rev() {
+        env.push(arg.clone());
+    }
+    match env.exec(prop.clone()) {
+        Ok(()) => {
+            let res = env.pop("property result")?;
+            env.truncate_stack(height);
+            Ok(res.as_nat(env, "").is_ok_and(|n| n == 1))
+        }
+        Err(e)
+            if matches!(
+                e.kind,
+                UiuaErrorKind::Interrupted | UiuaErrorKind::Timeout(..)
+            ) =>
+        {
+            Err(e)
+        }
+        Err(_) => {
+            env.truncate_stack(height);
+            Ok(false)
+        }
+    }
+}
+
+/// Generate an array from a property spec
+///
+/// The leading axes of the spec are the maximum shape,
+/// and the last axis contains the possible elements at each position
+fn generate<T: ArrayValue>(spec: &Array<T>, rng: &mut SmallRng) -> Array<T> {
+    let (max_shape, pool) = spec.shape().split_at(spec.rank() - 1);
+    let pool = pool[0];
+    let shape: Shape = max_shape.iter().map(|&d| rng.gen_range(0..=d)).collect();
+    let count = shape.iter().product();
+    let mut data = EcoVec::with_capacity(count);
+    for i in 0..count {
+        let mut rem = i;
+        let mut spec_i = 0;
+        let mut stride = pool;
+        for (&len, &max) in shape.iter().zip(max_shape).rev() {
+            spec_i += rem % len * stride;
+            rem /= len;
+            stride *= max;
+        }
+        data.push(spec.data[spec_i + rng.gen_range(0..pool)].clone());
+    }
+    Array::new(shape, data)
+}
+
+/// Get simpler versions of a value to try when shrinking
+///
+/// Axes are shortened and numbers are moved toward zero
+fn shrink_candidates(val: &Value) -> Vec<Value> {
+    let mut candidates: Vec<Value> = Vec::new();
+    for (axis, &len) in val.shape().iter().enumerate() {
+        let mut lens = vec![0, len / 2, len.saturating_sub(1)];
+        lens.dedup();
+        for new_len in lens.into_iter().filter(|&l| l < len) {
+            candidates.push(val_as_arr!(val, |arr| shorten_axis(arr, axis, new_len).into()));
+        }
+    }
+    match val {
+        Value::Num(arr) if arr.data.iter().any(|&n| n != 0.0) => {
+            let mut zeroed = arr.clone();
+            if let Some(n) = zeroed.data.as_mut_slice().iter_mut().find(|n| **n != 0.0) {
+                *n = 0.0;
+            }
+            let mut halved = arr.clone();
+            for n in halved.data.as_mut_slice() {
+                *n = (*n / 2.0).trunc();
+            }
+            candidates.extend([zeroed.into(), halved.into()]);
+        }
+        Value::Byte(arr) if arr.data.iter().any(|&n| n != 0) => {
+            let mut zeroed = arr.clone();
+            if let Some(n) = zeroed.data.as_mut_slice().iter_mut().find(|n| **n != 0) {
+                *n = 0;
+            }
+            let mut halved = arr.clone();
+            for n in halved.data.as_mut_slice() {
+                *n /= 2;
+            }
+            candidates.extend([zeroed.into(), halved.into()]);
+        }
+        _ => {}
+    }
+    candidates.retain(|candidate| candidate != val);
+    candidates
+}
+
+/// Take a prefix of an array along an axis
+fn shorten_axis<T: ArrayValue>(arr: &Array<T>, axis: usize, new_len: usize) -> Array<T> {
+    let mut shape = arr.shape().clone();
+    let old_len = shape[axis];
+    shape[axis] = new_len;
+    let inner: usize = shape[axis + 1..].iter().product();
+    let outer: usize = shape[..axis].iter().product();
+    let mut data = EcoVec::with_capacity(shape.elements());
+    for o in 0..outer {
+        let start = o * old_len * inner;
+        data.extend_from_slice(&arr.data[start..start + new_len * inner]);
+    }
+    Array::new(shape, data)
+}
+
+/// The maximum number of differing elements to show in a diff
+const MAX_DIFF_ELEMS: usize = 10;
+
+/// Describe how two values differ
+///
+/// Returns `None` if the values are the same
+pub(crate) fn value_diff(expected: &Value, actual: &Value) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+    let mut lines = Vec::new();
+    let (expected_label, actual_label) = (&expected.meta().label, &actual.meta().label);
+    if expected_label != actual_label {
+        let label = |label: &Option<_>| match label {
+            Some(label) => format!("${label}"),
+            None => "no label".into(),
+        };
+        lines.push(format!(
+            "Label mismatch: expected {}, got {}",
+            label(expected_label),
+            label(actual_label)
+        ));
+    }
+    match (expected.is_map(), actual.is_map()) {
+        (true, true) => lines.extend(map_diff(expected, actual)),
+        (true, false) => lines.push("Expected a map, got an array without keys".into()),
+        (false, true) => lines.push("Expected an array without keys, got a map".into()),
+        (false, false) if expected.shape() != actual.shape() => lines.push(format!(
+            "Shape mismatch: expected {}, got {}",
+            expected.shape(),
+            actual.shape()
+        )),
+        (false, false)
+            if expected.type_id() != actual.type_id() && !both_numeric(expected, actual) =>
+        {
+            lines.push(format!(
+                "Type mismatch: expected {}, got {}",
+                expected.type_name_plural(),
+                actual.type_name_plural()
+            ))
+        }
+        (false, false) => lines.extend(element_diff(expected, actual)),
+    }
+    if lines.is_empty() {
+        lines.push("Values differ in metadata".into());
+    }
+    Some(lines.join("\n"))
+}
+
+fn both_numeric(a: &Value, b: &Value) -> bool {
+    let numeric = |val: &Value| matches!(val, Value::Num(_) | Value::Byte(_));
+    numeric(a) && numeric(b)
+}
+
+/// Describe the differing elements of two values with the same shape
+fn element_diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let (mut flat_expected, mut flat_actual) = (expected.clone(), actual.clone());
+    flat_expected.deshape();
+    flat_actual.deshape();
+    let differing: Vec<usize> = (0..flat_expected.row_count())
+        .filter(|&i| flat_expected.row(i) != flat_actual.row(i))
+        .collect();
+    if differing.is_empty() {
+        return Vec::new();
+    }
+    let shape = expected.shape();
+    let mut rows = vec![["Index".into(), "Expected".into(), "Actual".into()]];
+    for &i in differing.iter().take(MAX_DIFF_ELEMS) {
+        let mut index = vec![0; shape.len()];
+        let mut rem = i;
+        for (dim, ix) in shape.iter().zip(&mut index).rev() {
+            *ix = rem % dim;
+            rem /= dim;
+        }
+        let index: Vec<String> = index.iter().map(|i| i.to_string()).collect();
+        rows.push([
+            format!("[{}]", index.join(" ")),
+            flat_expected.row(i).show(),
+            flat_actual.row(i).show(),
+        ]);
+    }
+    let mut lines = vec![format!(
+        "{} of {} elements differ{}",
+        differing.len(),
+        flat_expected.row_count(),
+        if differing.len() > MAX_DIFF_ELEMS {
+            format!(", showing the first {MAX_DIFF_ELEMS}")
+        } else {
+            String::new()
+        }
+    )];
+    lines.extend(table(&rows));
+    lines
+}
+
+/// Describe the key and value differences of two maps
+fn map_diff(expected: &Value, actual: &Value) -> Vec<String> {
+    let expected = expected.map_kv();
+    let actual = actual.map_kv();
+    let mut rows = vec![["Key".into(), "Expected".into(), "Actual".into()]];
+    let missing = "(missing)".to_string();
+    for (key, value) in &expected {
+        match actual.iter().find(|(k, _)| k == key) {
+            Some((_, actual_value)) if actual_value == value => {}
+            Some((_, actual_value)) => rows.push([key.show(), value.show(), actual_value.show()]),
+            None => rows.push([key.show(), value.show(), missing.clone()]),
+        }
+    }
+    for (key, value) in &actual {
+        if !expected.iter().any(|(k, _)| k == key) {
+            rows.push([key.show(), missing.clone(), value.show()]);
+        }
+    }
+    if rows.len() == 1 {
+        return Vec::new();
+    }
+    let mut lines = vec![format!("{} map entries differ", rows.len() - 1)];
+    lines.extend(table(&rows));
+    lines
+}
+
+/// Lay out multi-line cells in aligned columns
+fn table(rows: &[[String; 3]]) -> Vec<String> {
+    let mut widths = [0; 3];
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            let cell_width = cell.lines().map(|l| l.chars().count()).max().unwrap_or(0);
+            *width = (*width).max(cell_width);
+        }
+    }
+    let mut lines = Vec::new();
+    for row in rows {
+        let cells: Vec<Vec<&str>> = row.iter().map(|cell| cell.lines().collect()).collect();
+        let height = cells.iter().map(Vec::len).max().unwrap_or(0).max(1);
+        for i in 0..height {
+            let mut line = String::from("  ");
+            for (cell, width) in cells.iter().zip(widths) {
+                let text = cell.get(i).copied().unwrap_or("");
+                line.push_str(text);
+                line.push_str(&" ".repeat(width - text.chars().count() + 2));
+            }
+            lines.push(line.trim_end().into());
+        }
+    }
+    lines
+}
+
+/// Turn the test results of a run into test cases
+///
+/// `error` is the error that stopped the run, if any
+pub(crate) fn collect_test_cases(env: &Uiua, error: Option<&UiuaError>) -> Vec<TestCase> {
+    let now = env.rt.backend.now();
+    let scope_name = |span: usize| -> String {
+        (env.asm.test_scope_names.get(&span))
+            .map(|name| name.to_string())
+            .unwrap_or_else(|| format!("test at {}", env.asm.spans[span]))
+    };
+    let assertion_case = |res: &TestAssertResult| {
+        let span = &env.asm.spans[res.span];
+        let loc = location(span);
+        let scope = res.scope.map(|i| scope_name(env.rt.test_scopes[i].span));
+        TestCase {
+            name: match &scope {
+                Some(scope) => format!("{scope} at {}:{}", loc.line, loc.column),
+                None => format!("assertion at {span}"),
+            },
+            kind: TestCaseKind::Assertion,
+            scope,
+            duration: res.duration,
+            failure: res.result.as_ref().err().map(failure_message),
+            ..loc
+        }
+    };
+    let mut cases = Vec::new();
+    // Each scope is followed by its assertions
+    for (i, scope) in env.rt.test_scopes.iter().enumerate() {
+        let assertions: Vec<&TestAssertResult> = (env.rt.test_results.iter())
+            .filter(|res| res.scope == Some(i))
+            .collect();
+        let failure = if let Some(res) = assertions.iter().find(|res| res.result.is_err()) {
+            res.result.as_ref().err().map(failure_message)
+        } else if scope.duration.is_none() {
+            Some(
+                error
+                    .map(failure_message)
+                    .unwrap_or_else(|| "Did not finish".into()),
+            )
+        } else {
+            None
+        };
+        cases.push(TestCase {
+            name: scope_name(scope.span),
+            kind: TestCaseKind::Scope,
+            scope: None,
+            duration: scope.duration.unwrap_or(now - scope.start),
+            failure,
+            ..location(&env.asm.spans[scope.span])
+        });
+        cases.extend(assertions.into_iter().map(assertion_case));
+    }
+    // Assertions outside of test scopes
+    cases.extend(
+        (env.rt.test_results.iter())
+            .filter(|res| res.scope.is_none())
+            .map(assertion_case),
+    );
+    cases
+}
+
+fn location(span: &Span) -> TestCase {
+    let (file, line, column) = match span {
+        Span::Code(span) => (
+            match &span.src {
+                InputSrc::File(path) => Some(path.to_string_lossy().into_owned()),
+                _ => None,
+            },
+            span.start.line as usize,
+            span.start.col as usize,
+        ),
+        Span::Builtin => (None, 0, 0),
+    };
+    TestCase {
+        name: String::new(),
+        kind: TestCaseKind::Assertion,
+        scope: None,
+        file,
+        line,
+        column,
+        duration: 0.0,
+        failure: None,
+    }
+}
+
+fn failure_message(error: &UiuaError) -> String {
+    let mut message = match &error.kind {
+        UiuaErrorKind::Throw(value, ..) => value.format(),
+        UiuaErrorKind::Run { message, .. } => message.value.clone(),
+        _ => error.to_string(),
+    };
+    for (info, _) in &error.infos {
+        message.push('\n');
+        message.push_str(info);
+    }
+    message
+}
+
+/// Render test cases as JSON
+pub fn json_report(cases: &[TestCase]) -> String {
+    #[derive(Serialize)]
+    struct JsonReport<'a> {
+        passed: usize,
+        failed: usize,
+        tests: &'a [TestCase],
+    }
+    let passed = cases.iter().filter(|case| case.passed()).count();
+    serde_json::to_string_pretty(&JsonReport {
+        passed,
+        failed: cases.len() - passed,
+        tests: cases,
+    })
+    .unwrap()
+}
+
+/// Render test cases as JUnit XML
+///
+/// Cases are grouped into test suites by file
+pub fn junit_report(cases: &[TestCase], name: &str) -> String {
+    let failures = |cases: &[&TestCase]| cases.iter().filter(|case| !case.passed()).count();
+    let time = |cases: &[&TestCase]| {
+        (cases.iter())
+            .filter(|case| case.kind == TestCaseKind::Scope || case.scope.is_none())
+            .map(|case| case.duration)
+            .sum::<f64>()
+    };
+    let mut suites: Vec<(&str, Vec<&TestCase>)> = Vec::new();
+    for case in cases {
+        let file = case.file.as_deref().unwrap_or(name);
+        if let Some((_, suite)) = suites.iter_mut().find(|(f, _)| *f == file) {
+            suite.push(case);
+        } else {
+            suites.push((file, vec![case]));
+        }
+    }
+    let all: Vec<&TestCase> = cases.iter().collect();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    _ = writeln!(
+        xml,
+        "<testsuites name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">",
+        escape_xml(name),
+        all.len(),
+        failures(&all),
+        time(&all)
+    );
+    for (file, cases) in &suites {
+        _ = writeln!(
+            xml,
+            "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" time=\"{:.6}\">",
+            escape_xml(file),
+            cases.len(),
+            failures(cases),
+            time(cases)
+        );
+        for case in cases {
+            let classname = case.scope.as_deref().unwrap_or(file);
+            _ = write!(
+                xml,
+                "    <testcase name=\"{}\" classname=\"{}\" file=\"{}\" line=\"{}\" time=\"{:.6}\"",
+                escape_xml(&case.name),
+                escape_xml(classname),
+                escape_xml(file),
+                case.line,
+                case.duration
+            );
+            if let Some(failure) = &case.failure {
+                _ = writeln!(
+                    xml,
+                    ">\n      <failure message=\"{}\"/>\n    </testcase>",
+                    escape_xml(failure)
+                );
+            } else {
+                xml.push_str("/>\n");
+            }
+        }
+        xml.push_str("  </testsuite>\n");
+    }
+    xml.push_str("</testsuites>\n");
+    xml
+}
+
+fn escape_xml(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            '\n' => escaped.push_str("&#10;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Execution counts recorded while running with coverage enabled
+#[derive(Debug, Clone, Default)]
+pub(crate) struct CoverageData {
+    /// How many times the node with each span index was executed
+    pub spans: HashMap<usize, u64>,
+    /// How many times each branch of a switch was taken, keyed by the switch's span index
+    pub branches: HashMap<(usize, usize), u64>,
+}
+
+/// The format of a coverage report
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CoverageFormat {
+    /// LCOV tracefile
+    #[default]
+    Lcov,
+    /// Cobertura XML
+    Cobertura,
+}
+
+impl FromStr for CoverageFormat {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "lcov" => Ok(CoverageFormat::Lcov),
+            "cobertura" => Ok(CoverageFormat::Cobertura),
+            _ => Err(format!("unknown coverage format `{s}`")),
+        }
+    }
+}
+
+/// Code coverage from a run
+#[derive(Debug, Clone, Default)]
+pub struct Coverage {
+    /// The coverage of each file
+    pub files: Vec<FileCoverage>,
+}
+
+/// Code coverage of a single file
+#[derive(Debug, Clone)]
+pub struct FileCoverage {
+    /// The path of the file
+    pub path: PathBuf,
+    /// The execution count of each line with code, keyed by 1-based line number
+    pub lines: BTreeMap<usize, u64>,
+    /// The functions bound in the file
+    pub functions: Vec<FunctionCoverage>,
+    /// The branches of switches in the file
+    pub branches: Vec<BranchCoverage>,
+}
+
+/// Code coverage of a function binding
+#[derive(Debug, Clone)]
+pub struct FunctionCoverage {
+    /// The name of the function
+    pub name: String,
+    /// The line the function is bound on
+    pub line: usize,
+    /// How many times the function's body was executed
+    pub hits: u64,
+}
+
+/// Code coverage of a single branch of a switch
+#[derive(Debug, Clone)]
+pub struct BranchCoverage {
+    /// The line of the switch
+    pub line: usize,
+    /// An identifier for the switch that is unique within the file
+    pub block: usize,
+    /// The index of the branch in the switch
+    pub branch: usize,
+    /// How many times the branch was taken
+    ///
+    /// This is `None` if the switch itself never ran
+    pub hits: Option<u64>,
+}
+
+impl Uiua {
+    /// Get the code coverage of the last run
+    ///
+    /// Returns `None` if coverage was not enabled with [`Uiua::with_coverage`]
+    pub fn coverage(&self) -> Option<Coverage> {
+        let data = self.rt.coverage.as_ref()?;
+        let mut instrumented = HashSet::new();
+        let mut switches = BTreeMap::new();
+        let mut nodes: Vec<&Node> = vec![&self.asm.root];
+        nodes.extend(&self.asm.functions);
+        for node in nodes {
+            coverage_nodes(node, &mut instrumented, &mut switches);
+        }
+        instrumented.extend(data.spans.keys().copied());
+
+        let mut files: Vec<FileCoverage> = Vec::new();
+        let code_span = |span: usize| match self.get_span(span) {
+            Span::Code(span) => Some(span),
+            Span::Builtin => None,
+        };
+
+        // Lines
+        let hits = |span: usize| data.spans.get(&span).copied().unwrap_or(0);
+        for &span in &instrumented {
+            let Some(code_span) = code_span(span) else {
+                continue;
+            };
+            if let Some(file) = file_coverage(&mut files, &code_span) {
+                let line = file.lines.entry(code_span.start.line as usize).or_default();
+                *line = (*line).max(hits(span));
+            }
+        }
+
+        // Functions
+        for binding in &self.asm.bindings {
+            let BindingKind::Func(f) = &binding.kind else {
+                continue;
+            };
+            let mut body = HashSet::new();
+            coverage_nodes(&self.asm[f], &mut body, &mut BTreeMap::new());
+            let name = binding.span.as_str(&self.asm.inputs, |s| s.to_string());
+            if let Some(file) = file_coverage(&mut files, &binding.span) {
+                file.functions.push(FunctionCoverage {
+                    name,
+                    line: binding.span.start.line as usize,
+                    hits: body.into_iter().map(hits).max().unwrap_or(0),
+                });
+            }
+        }
+
+        // Branches
+        for (span, count) in switches {
+            let Some(code_span) = code_span(span) else {
+                continue;
+            };
+            let ran = data.spans.contains_key(&span);
+            if let Some(file) = file_coverage(&mut files, &code_span) {
+                for branch in 0..count {
+                    file.branches.push(BranchCoverage {
+                        line: code_span.start.line as usize,
+                        block: span,
+                        branch,
+                        hits: ran
+                            .then(|| (data.branches.get(&(span, branch)).copied()).unwrap_or(0)),
+                    });
+                }
+            }
+        }
+
+        files.sort_by(|a, b| a.path.cmp(&b.path));
+        for file in &mut files {
+            file.functions.sort_by_key(|f| f.line);
+        }
+        Some(Coverage { files })
+    }
+}
+
+/// Get the coverage entry for the file of a span, creating it if necessary
+fn file_coverage<'a>(
+    files: &'a mut Vec<FileCoverage>,
+    span: &CodeSpan,
+) -> Option<&'a mut FileCoverage> {
+    let InputSrc::File(path) = &span.src else {
+        return None;
+    };
+    let i = match files.iter().position(|file| *file.path == **path) {
+        Some(i) => i,
+        None => {
+            files.push(FileCoverage {
+                path: path.to_path_buf(),
+                lines: BTreeMap::new(),
+                functions: Vec::new(),
+                branches: Vec::new(),
+            });
+            files.len() - 1
+        }
+    };
+    Some(&mut files[i])
+}
+
+/// Collect the spans of instrumentable nodes and the branch counts of switches
+fn coverage_nodes(node: &Node, spans: &mut HashSet<usize>, switches: &mut BTreeMap<usize, usize>) {
+    if let Some(span) = node.span().filter(|_| !matches!(node, Node::Run(_))) {
+        spans.insert(span);
+    }
+    match node {
+        Node::Run(nodes) => {
+            for node in nodes {
+                coverage_nodes(node, spans, switches);
+            }
+        }
+        Node::Mod(_, args, _) | Node::ImplMod(_, args, _) => {
+            for arg in args {
+                coverage_nodes(&arg.node, spans, switches);
+            }
+        }
+        Node::Switch { branches, span, .. } => {
+            switches.insert(*span, branches.len());
+            for branch in branches {
+                coverage_nodes(&branch.node, spans, switches);
+            }
+        }
+        Node::Array { inner, .. } | Node::NoInline(inner) | Node::TrackCaller(inner) => {
+            coverage_nodes(inner, spans, switches)
+        }
+        Node::CustomInverse(cust, _) => {
+            if let Ok(normal) = &cust.normal {
+                coverage_nodes(&normal.node, spans, switches);
+            }
+        }
+        _ => {}
+    }
+}
+
+impl Coverage {
+    /// Render the coverage in a format
+    pub fn report(&self, format: CoverageFormat) -> String {
+        match format {
+            CoverageFormat::Lcov => self.lcov(),
+            CoverageFormat::Cobertura => self.cobertura(),
+        }
+    }
+    /// Render the coverage as an LCOV tracefile
+    pub fn lcov(&self) -> String {
+        let mut s = String::new();
+        for file in &self.files {
+            _ = writeln!(s, "TN:");
+            _ = writeln!(s, "SF:{}", file.path.display());
+            for f in &file.functions {
+                _ = writeln!(s, "FN:{},{}", f.line, f.name);
+            }
+            for f in &file.functions {
+                _ = writeln!(s, "FNDA:{},{}", f.hits, f.name);
+            }
+            _ = writeln!(s, "FNF:{}", file.functions.len());
+            _ = writeln!(s, "FNH:{}", file.functions_hit());
+            for b in &file.branches {
+                let hits = b.hits.map_or_else(|| "-".into(), |hits| hits.to_string());
+                _ = writeln!(s, "BRDA:{},{},{},{hits}", b.line, b.block, b.branch);
+            }
+            _ = writeln!(s, "BRF:{}", file.branches.len());
+            _ = writeln!(s, "BRH:{}", file.branches_hit());
+            for (line, hits) in &file.lines {
+                _ = writeln!(s, "DA:{line},{hits}");
+            }
+            _ = writeln!(s, "LF:{}", file.lines.len());
+            _ = writeln!(s, "LH:{}", file.lines_hit());
+            _ = writeln!(s, "end_of_record");
+        }
+        s
+    }
+    /// Render the coverage as Cobertura XML
+    pub fn cobertura(&self) -> String {
+        let rate = |hit: usize, total: usize| {
+            if total == 0 {
+                1.0
+            } else {
+                hit as f64 / total as f64
+            }
+        };
+        let lines_valid: usize = self.files.iter().map(|f| f.lines.len()).sum();
+        let lines_covered: usize = self.files.iter().map(|f| f.lines_hit()).sum();
+        let branches_valid: usize = self.files.iter().map(|f| f.branches.len()).sum();
+        let branches_covered: usize = self.files.iter().map(|f| f.branches_hit()).sum();
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        _ = writeln!(
+            xml,
+            "<coverage line-rate=\"{:.4}\" branch-rate=\"{:.4}\" lines-covered=\"{lines_covered}\" \
+            lines-valid=\"{lines_valid}\" branches-covered=\"{branches_covered}\" \
+            branches-valid=\"{branches_valid}\" complexity=\"0\" version=\"{}\" timestamp=\"0\">",
+            rate(lines_covered, lines_valid),
+            rate(branches_covered, branches_valid),
+            crate::VERSION,
+        );
+        xml.push_str("  <sources>\n    <source>.</source>\n  </sources>\n");
+        xml.push_str("  <packages>\n");
+        _ = writeln!(
+            xml,
+            "    <package name=\"uiua\" line-rate=\"{:.4}\" branch-rate=\"{:.4}\" complexity=\"0\">",
+            rate(lines_covered, lines_valid),
+            rate(branches_covered, branches_valid),
+        );
+        xml.push_str("      <classes>\n");
+        for file in &self.files {
+            let path = escape_xml(&file.path.to_string_lossy());
+            _ = writeln!(
+                xml,
+                "        <class name=\"{path}\" filename=\"{path}\" line-rate=\"{:.4}\" \
+                branch-rate=\"{:.4}\" complexity=\"0\">",
+                rate(file.lines_hit(), file.lines.len()),
+                rate(file.branches_hit(), file.branches.len()),
+            );
+            xml.push_str("          <methods>\n");
+            for f in &file.functions {
+                let hit = (f.hits > 0) as usize;
+                _ = writeln!(
+                    xml,
+                    "            <method name=\"{}\" signature=\"\" line-rate=\"{hit}\" branch-rate=\"1\">\n              \
+                    <lines>\n                <line number=\"{}\" hits=\"{}\"/>\n              </lines>\n            </method>",
+                    escape_xml(&f.name),
+                    f.line,
+                    f.hits
+                );
+            }
+            xml.push_str("          </methods>\n          <lines>\n");
+            for (line, hits) in &file.lines {
+                let branches: Vec<&BranchCoverage> =
+                    file.branches.iter().filter(|b| b.line == *line).collect();
+                if branches.is_empty() {
+                    _ = writeln!(
+                        xml,
+                        "            <line number=\"{line}\" hits=\"{hits}\" branch=\"false\"/>"
+                    );
+                } else {
+                    let taken = branches.iter().filter(|b| b.hits > Some(0)).count();
+                    _ = writeln!(
+                        xml,
+                        "            <line number=\"{line}\" hits=\"{hits}\" branch=\"true\" \
+                        condition-coverage=\"{}% ({taken}/{})\"/>",
+                        taken * 100 / branches.len(),
+                        branches.len()
+                    );
+                }
+            }
+            xml.push_str("          </lines>\n        </class>\n");
+        }
+        xml.push_str("      </classes>\n    </package>\n  </packages>\n</coverage>\n");
+        xml
+    }
+}
+
+impl FileCoverage {
+    /// The number of lines that were executed
+    pub fn lines_hit(&self) -> usize {
+        self.lines.values().filter(|&&hits| hits > 0).count()
+    }
+    /// The number of functions that were executed
+    pub fn functions_hit(&self) -> usize {
+        self.functions.iter().filter(|f| f.hits > 0).count()
+    }
+    /// The number of branches that were taken
+    pub fn branches_hit(&self) -> usize {
+        self.branches.iter().filter(|b| b.hits > Some(0)).count()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Compiler, PreEvalMode, RunMode};
+
+    #[test]
+    fn test_cases() {
+        let code = "\
+---
+# First
+⍤\"a\" 0
+⍤\"b\" 1
+---
+---
+# Second
+⍤\"c\" 1
+---";
+        let run = |filter: Option<&str>, fail_fast: bool| {
+            let mut env = Uiua::with_safe_sys().with_fail_fast(fail_fast);
+            let mut comp = Compiler::new();
+            comp.mode(RunMode::Test).test_filter(filter.map(Into::into));
+            _ = comp
+                .load_str(code)
+                .and_then(|comp| env.run_asm(comp.finish()));
+            env.take_test_cases()
+        };
+        let cases = run(None, false);
+        let names: Vec<_> = cases.iter().map(|case| case.name.as_str()).collect();
+        assert_eq!(
+            names,
+            [
+                "First",
+                "First at 3:1",
+                "First at 4:1",
+                "Second",
+                "Second at 8:1"
+            ]
+        );
+        let failed: Vec<_> = cases.iter().filter(|case| !case.passed()).collect();
+        assert_eq!(failed.len(), 2);
+        assert_eq!(failed[0].failure.as_deref(), Some("a"));
+        assert_eq!(run(Some("Second"), false).len(), 2);
+        assert_eq!(run(None, true).len(), 2);
+    }
+
+    #[test]
+    fn coverage() {
+        let code = "\
+F ← ⨬(+1|×2|-3)
+G ← ⊂1
+F0 F1 5";
+        let mut env = Uiua::with_safe_sys().with_coverage(true);
+        let mut comp = Compiler::new();
+        comp.pre_eval_mode(PreEvalMode::Lazy)
+            .load_str_src(code, Path::new("cov.ua"))
+            .unwrap();
+        env.run_asm(comp.finish()).unwrap();
+        let coverage = env.coverage().unwrap();
+        let file = &coverage.files[0];
+        assert_eq!(file.lines.get(&1), Some(&2));
+        assert_eq!(file.lines.get(&2), Some(&0));
+        let hits: Vec<_> = file.branches.iter().map(|b| b.hits).collect();
+        assert_eq!(hits, [Some(1), Some(1), Some(0)]);
+        let functions: Vec<_> = (file.functions.iter())
+            .map(|f| (f.name.as_str(), f.hits))
+            .collect();
+        assert_eq!(functions, [("F", 2), ("G", 0)]);
+        assert!(coverage.lcov().contains("BRDA:1,"));
+    }
+
+    #[test]
+    fn snapshots() {
+        let mut env = Uiua::with_safe_sys();
+        _ = env.run_str("&snap \"a\" [1 2 3]");
+        let cases = env.take_test_cases();
+        assert!(cases[0]
+            .failure
+            .as_ref()
+            .unwrap()
+            .contains("does not exist"));
+        let diff = |a: &str, b: &str| {
+            let mut env = Uiua::with_safe_sys();
+            env.run_str(&format!("{a} {b}")).unwrap();
+            let a = env.pop(1).unwrap();
+            let b = env.pop(2).unwrap();
+            value_diff(&a, &b)
+        };
+        assert_eq!(diff("[1 2 3]", "[1 2 3]"), None);
+        assert!(diff("[1 2 3]", "[1 2]")
+            .unwrap()
+            .starts_with("Shape mismatch"));
+        assert!(diff("[1 2 3]", "\"abc\"")
+            .unwrap()
+            .starts_with("Type mismatch"));
+        let message = diff("[1_2 3_4]", "[1_2 3_5]").unwrap();
+        assert!(message.starts_with("1 of 4 elements differ"));
+        assert!(message.contains("[1 1]  4         5"));
+    }
+
+    #[test]
+    fn match_diff() {
+        let mut env = Uiua::with_safe_sys();
+        let mut comp = Compiler::new();
+        comp.mode(RunMode::Test);
+        _ = comp
+            .load_str(
+                "⍤⤙≍ map 1_2 3_4 map 1_2 3_5\n\
+                ⍤⤙≍ [1 2] [1 2]\n\
+                ◌◌⤙≍ [1 2] [1 3]\n\
+                ⍤\"plain\" 0",
+            )
+            .and_then(|comp| env.run_asm(comp.finish()));
+        let cases = env.take_test_cases();
+        let failure = cases[0].failure.as_deref().unwrap();
+        assert!(failure.contains("1 map entries differ"));
+        assert!(failure.contains("2    4         5"));
+        assert!(cases[1].passed());
+        // Matches that are not asserted do not leave a diff behind
+        assert_eq!(cases[2].failure.as_deref(), Some("plain"));
+    }
+
+    #[test]
+    fn property() {
+        let mut env = Uiua::with_safe_sys();
+        let mut comp = Compiler::new();
+        comp.mode(RunMode::Test);
+        _ = comp
+            .load_str(
+                "# Experimental!\n\
+                property(↯5⇡10|≍⊸(°⇌⇌)) 50\n\
+                property(↯10⇡100|<3⧻) 50",
+            )
+            .and_then(|comp| env.run_asm(comp.finish()));
+        let cases = env.take_test_cases();
+        assert!(cases[0].passed());
+        let failure = cases[1].failure.as_deref().unwrap();
+        assert!(failure.contains("with seed"));
+        // The counterexample is shrunk to the shortest failing length with zeros
+        let counterexample = failure.split("Counterexample:").nth(1).unwrap();
+        assert!(counterexample.trim_start().starts_with("[0 0 0]"));
+        // A fixed seed generates the same cases every time
+        let run = |seed: u64| {
+            let mut env = Uiua::with_safe_sys().with_property_seed(Some(seed));
+            let mut comp = Compiler::new();
+            comp.mode(RunMode::Test);
+            _ = comp
+                .load_str("# Experimental!\nproperty(↯1⇡10|≠0⊢) 50")
+                .and_then(|comp| env.run_asm(comp.finish()));
+            env.take_test_cases().remove(0).failure.unwrap()
+        };
+        let failure = run(12345);
+        assert!(failure.contains("with seed 12345"), "{failure}");
+        assert_eq!(failure, run(12345));
+    }
+}